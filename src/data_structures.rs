@@ -1,7 +1,13 @@
-use crate::link::{EK, PP, VK};
+use crate::error::Error;
+use crate::link::{SparseMatrix, EK, PP, VK};
 use ark_ec::pairing::Pairing;
+use ark_ec::short_weierstrass::{Affine as SWAffine, SWCurveConfig};
+use ark_ec::AffineRepr;
+use ark_ff::Field;
+use ark_relations::r1cs::SynthesisError;
 use ark_serialize::*;
-use ark_std::vec::Vec;
+use ark_std::{collections::BTreeMap, vec::Vec};
+use core::ops::Range;
 
 /// A proof in the Groth16 SNARK.
 #[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
@@ -16,6 +22,44 @@ pub struct Proof<E: Pairing> {
     pub d: E::G1Affine,
 }
 
+/// The `a`, `b` and `c` elements of a [`Proof`] prepared for use in pairings, as returned by
+/// [`Proof::prepare`]. Useful when they are the same across many verifications and only the public
+/// inputs or the commitment `d` change.
+#[derive(Clone, Debug)]
+pub struct PreparedProof<E: Pairing> {
+    /// The `A` element in `G1`, prepared for use in pairings.
+    pub a: E::G1Prepared,
+    /// The `B` element in `G2`, prepared for use in pairings.
+    pub b: E::G2Prepared,
+    /// The `C` element in `G1`, prepared for use in pairings.
+    pub c: E::G1Prepared,
+}
+
+/// The elements of a [`Proof`] that do not depend on the commitment randomness `v`, as returned by
+/// [`create_proof_reusable`](crate::create_proof_reusable).
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct PartialProof<E: Pairing> {
+    /// The `A` element in `G1`.
+    pub a: E::G1Affine,
+    /// The `B` element in `G2`.
+    pub b: E::G2Affine,
+}
+
+/// What is left of the prover's work to complete a [`PartialProof`] for some `v`, as returned by
+/// [`create_proof_reusable`](crate::create_proof_reusable) and used by
+/// [`finalize_proof`](crate::finalize_proof).
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProverContext<E: Pairing> {
+    /// `C` without the term `-v * eta / delta`.
+    pub c_acc: E::G1,
+    /// `D` without the term `v * eta / gamma`, i.e. the commitment to the witnesses alone.
+    pub d_acc: E::G1,
+    /// `eta / delta` in G1, copied from the proving key, which `v` multiplies to complete `c_acc`.
+    pub eta_delta_inv_g1: E::G1Affine,
+    /// `eta / gamma` in G1, copied from the verifying key, which `v` multiplies to complete `d_acc`.
+    pub eta_gamma_inv_g1: E::G1Affine,
+}
+
 /// A proof with link in the Groth16 SNARK.
 #[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
 pub struct ProofWithLink<E: Pairing> {
@@ -47,6 +91,210 @@ impl<E: Pairing> Default for ProofWithLink<E> {
     }
 }
 
+impl<E: Pairing> Eq for Proof<E> {}
+
+impl<E: Pairing> core::hash::Hash for Proof<E> {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        core::hash::Hash::hash(&self.a, state);
+        core::hash::Hash::hash(&self.b, state);
+        core::hash::Hash::hash(&self.c, state);
+        core::hash::Hash::hash(&self.d, state);
+    }
+}
+
+/// Affine coordinates of the elements of a [`Proof`] over the base prime field `F` of the curve, e.g.
+/// for passing a proof to a library that doesn't use arkworks' encoding. `None` stands for the point
+/// at infinity.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProofCoords<F> {
+    /// `[x, y]` of `A`.
+    pub a: Option<[F; 2]>,
+    /// The coordinates of `B` over the base prime field, i.e. `[x0, x1, y0, y1]` when `G2` is over
+    /// a quadratic extension.
+    pub b: Option<Vec<F>>,
+    /// `[x, y]` of `C`.
+    pub c: Option<[F; 2]>,
+    /// `[x, y]` of `D`.
+    pub d: Option<[F; 2]>,
+}
+
+impl<E, P1, P2> Proof<E>
+where
+    E: Pairing<G1Affine = SWAffine<P1>, G2Affine = SWAffine<P2>>,
+    P1: SWCurveConfig,
+    P2: SWCurveConfig,
+    P2::BaseField: Field<BasePrimeField = P1::BaseField>,
+{
+    /// Get the affine coordinates of the proof elements.
+    pub fn to_coordinates(&self) -> ProofCoords<P1::BaseField> {
+        let g1 = |p: &SWAffine<P1>| p.xy().map(|(x, y)| [*x, *y]);
+        ProofCoords {
+            a: g1(&self.a),
+            b: self.b.xy().map(|(x, y)| {
+                x.to_base_prime_field_elements()
+                    .chain(y.to_base_prime_field_elements())
+                    .collect()
+            }),
+            c: g1(&self.c),
+            d: g1(&self.d),
+        }
+    }
+
+    /// Build a proof from the coordinates returned by [`Self::to_coordinates`]. Returns
+    /// `Error::InvalidProofPoint` if a point is not on the curve or not in the prime order subgroup,
+    /// or if `B` has the wrong number of coordinates.
+    pub fn from_coordinates(coords: &ProofCoords<P1::BaseField>) -> crate::Result<Self> {
+        fn checked<P: SWCurveConfig>(p: SWAffine<P>) -> crate::Result<SWAffine<P>> {
+            if p.is_on_curve() && p.is_in_correct_subgroup_assuming_on_curve() {
+                Ok(p)
+            } else {
+                Err(Error::InvalidProofPoint)
+            }
+        }
+        let g1 = |xy: &Option<[P1::BaseField; 2]>| match xy {
+            Some([x, y]) => checked(SWAffine::new_unchecked(*x, *y)),
+            None => Ok(SWAffine::identity()),
+        };
+        let b = match &coords.b {
+            Some(xy) => {
+                let degree = P2::BaseField::extension_degree() as usize;
+                if xy.len() != 2 * degree {
+                    return Err(Error::InvalidProofPoint);
+                }
+                let x = P2::BaseField::from_base_prime_field_elems(&xy[..degree]);
+                let y = P2::BaseField::from_base_prime_field_elems(&xy[degree..]);
+                match (x, y) {
+                    (Some(x), Some(y)) => checked(SWAffine::new_unchecked(x, y))?,
+                    _ => return Err(Error::InvalidProofPoint),
+                }
+            }
+            None => SWAffine::identity(),
+        };
+        Ok(Self {
+            a: g1(&coords.a)?,
+            b,
+            c: g1(&coords.c)?,
+            d: g1(&coords.d)?,
+        })
+    }
+}
+
+impl<E: Pairing> Proof<E> {
+    /// Whether this is a LegoGroth16 proof, i.e. has a commitment `d` to the committed witnesses,
+    /// rather than a plain Groth16 proof with `d` left as the identity. Only the former can be checked
+    /// with [`verify_witness_commitment`](crate::verify_witness_commitment) or be part of a
+    /// [`ProofWithLink`].
+    pub fn is_legogroth(&self) -> bool {
+        !self.d.is_zero()
+    }
+
+    /// Prepare `a`, `b` and `c` for use in pairings, to verify with
+    /// [`verify_prepared_proof`](crate::verify_prepared_proof) against many statements and
+    /// commitments `d` without preparing them again.
+    pub fn prepare(&self) -> PreparedProof<E> {
+        PreparedProof {
+            a: self.a.into(),
+            b: self.b.into(),
+            c: self.c.into(),
+        }
+    }
+
+    /// Compare the proofs as group elements rather than by their affine representation. Points
+    /// produced by this crate and by deserialization are already in canonical form, so the derived
+    /// `PartialEq` is enough for them; prefer this when a proof may have been assembled from points
+    /// built by other means, e.g. an identity with arbitrary coordinates.
+    pub fn eq_normalized(&self, other: &Self) -> bool {
+        self.a.into_group() == other.a.into_group()
+            && self.b.into_group() == other.b.into_group()
+            && self.c.into_group() == other.c.into_group()
+            && self.d.into_group() == other.d.into_group()
+    }
+
+    /// Serialize the proof but omit `d` when it is the identity, as for proofs without committed
+    /// witnesses. A flag byte before `d` says whether it is present.
+    pub fn serialize_compact<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        self.a.serialize_compressed(&mut writer)?;
+        self.b.serialize_compressed(&mut writer)?;
+        self.c.serialize_compressed(&mut writer)?;
+        if self.d.is_zero() {
+            0u8.serialize_compressed(&mut writer)
+        } else {
+            1u8.serialize_compressed(&mut writer)?;
+            self.d.serialize_compressed(&mut writer)
+        }
+    }
+
+    /// Deserialize a proof serialized with [`Self::serialize_compact`].
+    pub fn deserialize_compact<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        let a = E::G1Affine::deserialize_compressed(&mut reader)?;
+        let b = E::G2Affine::deserialize_compressed(&mut reader)?;
+        let c = E::G1Affine::deserialize_compressed(&mut reader)?;
+        let d = match u8::deserialize_compressed(&mut reader)? {
+            0 => E::G1Affine::zero(),
+            1 => E::G1Affine::deserialize_compressed(&mut reader)?,
+            _ => return Err(SerializationError::InvalidData),
+        };
+        Ok(Self { a, b, c, d })
+    }
+}
+
+impl<E: Pairing> ProofWithLink<E> {
+    /// Whether both commitments are present, i.e. `groth16_proof.d` and `link_d` are not the identity,
+    /// so the proof can go to [`verify_proof_with_link`](crate::verify_proof_with_link).
+    pub fn has_commitments(&self) -> bool {
+        self.groth16_proof.is_legogroth() && !self.link_d.is_zero()
+    }
+
+    /// Assemble a proof from a Groth16 proof and a CP-link proof that were created separately, e.g.
+    /// by different services. Returns `Error::InvalidWitnessCommitment` if `groth16_proof.d` is the
+    /// identity as there is then no commitment for the CP-link proof to link.
+    pub fn from_parts(
+        groth16_proof: Proof<E>,
+        link_d: E::G1Affine,
+        link_pi: E::G1Affine,
+    ) -> crate::Result<Self> {
+        if groth16_proof.d.is_zero() {
+            return Err(Error::InvalidWitnessCommitment);
+        }
+        Ok(Self {
+            groth16_proof,
+            link_d,
+            link_pi,
+        })
+    }
+
+    /// Serialize only the Groth16 part of the proof, i.e. `(A, B, C, D)`.
+    pub fn serialize_groth16_part<W: Write>(&self, writer: W) -> Result<(), SerializationError> {
+        self.groth16_proof.serialize_compressed(writer)
+    }
+
+    /// Serialize only the CP-link part of the proof, i.e. `link_d` and `link_pi`.
+    pub fn serialize_link_part<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        self.link_d.serialize_compressed(&mut writer)?;
+        self.link_pi.serialize_compressed(&mut writer)
+    }
+
+    /// Rebuild a proof from the outputs of [`Self::serialize_groth16_part`] and
+    /// [`Self::serialize_link_part`]. Returns `SerializationError::InvalidData` if either has bytes
+    /// left over, so that each proof has a single encoding.
+    pub fn reassemble(
+        mut groth16_bytes: &[u8],
+        mut link_bytes: &[u8],
+    ) -> Result<Self, SerializationError> {
+        let groth16_proof = Proof::<E>::deserialize_compressed(&mut groth16_bytes)?;
+        let link_d = E::G1Affine::deserialize_compressed(&mut link_bytes)?;
+        let link_pi = E::G1Affine::deserialize_compressed(&mut link_bytes)?;
+        if !groth16_bytes.is_empty() || !link_bytes.is_empty() {
+            return Err(SerializationError::InvalidData);
+        }
+        Ok(Self {
+            groth16_proof,
+            link_d,
+            link_pi,
+        })
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////
 
@@ -65,13 +313,16 @@ pub struct VerifyingKey<E: Pairing> {
     pub gamma_abc_g1: Vec<E::G1Affine>,
     /// The element `eta*gamma^-1 * G` in `E::G1`.
     pub eta_gamma_inv_g1: E::G1Affine,
-
+    /// Number of witnesses committed in the proof's `d`. Serialized last, so keys serialized
+    /// before this field existed, up to version 0.2 of the crate, must be read with
+    /// [`Self::deserialize_legacy`].
+    pub commit_witness_count: u32,
     // pub link_pp: PP<E::G1Affine, E::G2Affine>,
     // pub link_bases: Vec<E::G1Affine>,
     // pub link_vk: VK<E::G2Affine>,
 }
 
-/// A verification key with CP_link 
+/// A verification key with CP_link
 #[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
 pub struct VerifyingKeyWithLink<E: Pairing> {
     pub groth16_vk: VerifyingKey<E>,
@@ -92,7 +343,7 @@ impl<E: Pairing> Default for VerifyingKey<E> {
             delta_g2: E::G2Affine::default(),
             gamma_abc_g1: Vec::new(),
             eta_gamma_inv_g1: E::G1Affine::default(),
-            // commit_witness_count: 0,
+            commit_witness_count: 0,
         }
     }
 }
@@ -122,6 +373,24 @@ pub struct PreparedVerifyingKey<E: Pairing> {
     pub delta_g2_neg_pc: E::G2Prepared,
 }
 
+/// A [`PreparedVerifyingKey`] that also has the bases `gamma_abc_g1` of the public inputs in
+/// struct-of-arrays layout, i.e. their `x` and `y` coordinates in separate arrays, as created by
+/// [`prepare_verifying_key_soa`](crate::prepare_verifying_key_soa). The input MSM of
+/// [`prepare_inputs_soa`](crate::prepare_inputs_soa) then streams over the coordinates, which
+/// prefetches better for statements with many inputs.
+#[cfg(feature = "soa-inputs")]
+#[derive(Clone, Debug)]
+pub struct PreparedVerifyingKeySoa<E: Pairing, P: SWCurveConfig> {
+    /// The prepared verification key, `gamma_abc_g1` of which is kept as is.
+    pub pvk: PreparedVerifyingKey<E>,
+    /// The `x` coordinates of `gamma_abc_g1`, zero for the point at infinity.
+    pub gamma_abc_x: Vec<P::BaseField>,
+    /// The `y` coordinates of `gamma_abc_g1`, zero for the point at infinity.
+    pub gamma_abc_y: Vec<P::BaseField>,
+    /// Whether each element of `gamma_abc_g1` is the point at infinity.
+    pub gamma_abc_infinity: Vec<bool>,
+}
+
 impl<E: Pairing> From<PreparedVerifyingKey<E>> for VerifyingKey<E> {
     fn from(other: PreparedVerifyingKey<E>) -> Self {
         other.vk
@@ -145,6 +414,54 @@ impl<E: Pairing> Default for PreparedVerifyingKey<E> {
     }
 }
 
+/// Prepared verification keys for the two curves of a cycle, so that proofs over either curve can be
+/// verified through a single key.
+#[derive(Clone, Debug)]
+pub struct DualPreparedVerifyingKey<E1: Pairing, E2: Pairing> {
+    /// Prepared verification key over the first curve.
+    pub first: PreparedVerifyingKey<E1>,
+    /// Prepared verification key over the second curve.
+    pub second: PreparedVerifyingKey<E2>,
+}
+
+/// A proof and its public inputs over one of the curves of a [`DualPreparedVerifyingKey`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum DualProof<'a, E1: Pairing, E2: Pairing> {
+    First(&'a Proof<E1>, &'a [E1::ScalarField]),
+    Second(&'a Proof<E2>, &'a [E2::ScalarField]),
+}
+
+/// Fixed-base window tables of the commitment key of cp_link, for checking many openings of link
+/// commitments under the same key.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinkCommitmentVerifier<E: Pairing> {
+    /// Window size used for the tables.
+    pub window: usize,
+    /// Window table of each base of the commitment key.
+    pub tables: Vec<Vec<Vec<E::G1Affine>>>,
+}
+
+/// Fixed-base window tables of the commitment key of cp_link, for computing many link commitments
+/// `link_d` under the same key, created by [`ProvingKeyWithLink::prepare_link_bases`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct PreparedLinkBases<E: Pairing> {
+    /// Window size used for the tables.
+    pub window: usize,
+    /// Window table of each base of the commitment key.
+    pub tables: Vec<Vec<Vec<E::G1Affine>>>,
+}
+
+/// Public inputs prepared like [`prepare_inputs`](crate::prepare_inputs) that can be updated one
+/// input at a time, for verifying many proofs whose public inputs mostly stay the same.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UpdatableInputs<'a, E: Pairing> {
+    /// The bases `gamma_abc_g1` of the public inputs, without the one of the constant `1`.
+    pub input_bases: &'a [E::G1Affine],
+    /// The prepared inputs, to pass to
+    /// [`verify_proof_with_prepared_inputs`](crate::verify_proof_with_prepared_inputs).
+    pub acc: E::G1,
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////
 
@@ -169,6 +486,74 @@ pub struct ProvingKeyCommon<E: Pairing> {
     pub l_query: Vec<E::G1Affine>,
 }
 
+/// Density of the QAP of a circuit, as computed by the generator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QapDensity {
+    /// Number of QAP columns with a non-zero `a_i(t)`.
+    pub non_zero_a: usize,
+    /// Number of QAP columns with a non-zero `b_i(t)`, used to size the window of the G2 fixed-base
+    /// MSM.
+    pub non_zero_b: usize,
+    /// Number of variables of the QAP excluding the constant `1`, so there are
+    /// `qap_num_variables + 1` columns.
+    pub qap_num_variables: usize,
+    /// Size of the evaluation domain.
+    pub m_raw: usize,
+}
+
+/// Shape of the R1CS of a circuit, as returned by [`analyze_circuit`](crate::analyze_circuit). The
+/// histograms map a number of terms to the number of constraints whose linear combination in that
+/// matrix has that many terms, after the linear combinations have been inlined.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CircuitStats {
+    pub num_constraints: usize,
+    /// Number of instance variables, including the constant `1`.
+    pub num_instance_variables: usize,
+    pub num_witness_variables: usize,
+    pub a_histogram: BTreeMap<usize, usize>,
+    pub b_histogram: BTreeMap<usize, usize>,
+    pub c_histogram: BTreeMap<usize, usize>,
+    /// Total number of terms in `A`, `B` and `C`.
+    pub a_num_non_zero: usize,
+    pub b_num_non_zero: usize,
+    pub c_num_non_zero: usize,
+}
+
+/// Position of each G1 query of [`ProvingKeyCommon`] in [`ProvingKeyCommon::concat_g1_queries`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct G1QueryRanges {
+    pub a: Range<usize>,
+    pub b_g1: Range<usize>,
+    pub h: Range<usize>,
+    pub l: Range<usize>,
+}
+
+impl<E: Pairing> ProvingKeyCommon<E> {
+    /// All G1 queries laid out contiguously as `a_query || b_g1_query || h_query || l_query`, so that
+    /// `C` can be computed with a single MSM. See [`Self::g1_query_ranges`] for where each one is.
+    pub fn concat_g1_queries(&self) -> Vec<E::G1Affine> {
+        let mut queries = Vec::with_capacity(self.g1_query_ranges().l.end);
+        queries.extend_from_slice(&self.a_query);
+        queries.extend_from_slice(&self.b_g1_query);
+        queries.extend_from_slice(&self.h_query);
+        queries.extend_from_slice(&self.l_query);
+        queries
+    }
+
+    /// Position of each query in [`Self::concat_g1_queries`].
+    pub fn g1_query_ranges(&self) -> G1QueryRanges {
+        let b_g1_start = self.a_query.len();
+        let h_start = b_g1_start + self.b_g1_query.len();
+        let l_start = h_start + self.h_query.len();
+        G1QueryRanges {
+            a: 0..b_g1_start,
+            b_g1: b_g1_start..h_start,
+            h: h_start..l_start,
+            l: l_start..l_start + self.l_query.len(),
+        }
+    }
+}
+
 /// The prover key for for the Groth16 zkSNARK.
 #[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
 pub struct ProvingKey<E: Pairing> {
@@ -177,6 +562,35 @@ pub struct ProvingKey<E: Pairing> {
     pub common: ProvingKeyCommon<E>,
 }
 
+impl<E: Pairing> ProvingKey<E> {
+    /// Deserialize a key written, compressed, by version 0.2 of the crate or earlier, whose
+    /// verifying key is read with [`VerifyingKey::deserialize_legacy`].
+    pub fn deserialize_legacy<R: Read>(
+        mut reader: R,
+        public_inputs_count: usize,
+    ) -> Result<Self, SerializationError> {
+        let vk = VerifyingKey::deserialize_legacy(&mut reader, public_inputs_count)?;
+        let common = ProvingKeyCommon::deserialize_compressed(&mut reader)?;
+        Ok(Self { vk, common })
+    }
+
+    /// Number of `(G1, G2)` elements in the key, including those of the verifying key, e.g. to
+    /// estimate its size without serializing it.
+    pub fn element_count(&self) -> (usize, usize) {
+        let common = &self.common;
+        // `alpha_g1`, `eta_gamma_inv_g1`, `beta_g1`, `delta_g1` and `eta_delta_inv_g1`
+        let g1 = 5
+            + self.vk.gamma_abc_g1.len()
+            + common.a_query.len()
+            + common.b_g1_query.len()
+            + common.h_query.len()
+            + common.l_query.len();
+        // `beta_g2`, `gamma_g2` and `delta_g2`
+        let g2 = 3 + common.b_g2_query.len();
+        (g1, g2)
+    }
+}
+
 /// The prover key for for the Groth16 zkSNARK with CP_link parameters
 #[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
 pub struct ProvingKeyWithLink<E: Pairing> {
@@ -187,11 +601,166 @@ pub struct ProvingKeyWithLink<E: Pairing> {
     pub link_ek: EK<E::G1Affine>,
 }
 
+/// Proof that the commitments `d` of two proofs for the same verifying key commit to the same values
+/// at some positions of the committed witnesses, without revealing any witness. It is a proof of
+/// knowledge of the opening of `d1 - d2` over the bases of the other positions and
+/// `eta_gamma_inv_g1`, created by [`create_shared_commitment_proof`](crate::create_shared_commitment_proof).
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct SharedCommitmentProof<E: Pairing> {
+    /// Commitment to the blindings of the responses.
+    pub t: E::G1Affine,
+    /// Responses for the differences of the non-shared witnesses, in order, followed by the one for
+    /// the difference of the randomness `v`.
+    pub responses: Vec<E::ScalarField>,
+}
+
+/// Proof that two cp_link commitments `link_d1` and `link_d2` under the same bases commit to the same
+/// witnesses, without revealing them or the randomness. It is a proof of knowledge of
+/// `link_v1 - link_v2` such that `link_d1 - link_d2` is that multiple of the base of the randomness,
+/// created by [`prove_commitment_equality`](crate::prove_commitment_equality).
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct CommitmentEqualityProof<E: Pairing> {
+    /// Commitment to the blinding of the response.
+    pub t: E::G1Affine,
+    /// Response for the difference of the randomness.
+    pub response: E::ScalarField,
+}
+
+/// Opening of a single position of the committed witnesses in a proof's commitment `d`, without
+/// revealing the other witnesses or the randomness. It is a proof of knowledge of the opening of
+/// `d - value * base` over the bases of the other positions and `eta_gamma_inv_g1`, where `base` is
+/// the base of the opened position, created by [`open_position`](crate::open_position).
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct PositionOpening<E: Pairing> {
+    /// Commitment to the blindings of the responses.
+    pub t: E::G1Affine,
+    /// Responses for the other witnesses, in order, followed by the one for the randomness `v`.
+    pub responses: Vec<E::ScalarField>,
+}
+
+/// Proof that a [`ProvingKey`] was updated with [`ProvingKey::update_delta`] by someone knowing the
+/// ratio `delta_ratio` between the new and the old `delta`, as in the phase-2 contributions of
+/// Bowe, Gabizon and Miers (BGM17). The point `r_g2` is not part of the proof: it is derived by
+/// hashing the keys' `delta` elements, `s_g1` and `s_delta_ratio_g1`.
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct DeltaUpdateProof<E: Pairing> {
+    /// The element `s * G` in `E::G1` for a random `s`.
+    pub s_g1: E::G1Affine,
+    /// The element `s * delta_ratio * G` in `E::G1`.
+    pub s_delta_ratio_g1: E::G1Affine,
+    /// The element `delta_ratio * r_g2` in `E::G2`.
+    pub r_delta_ratio_g2: E::G2Affine,
+}
+
 impl<E: Pairing> VerifyingKey<E> {
+    /// Deserialize a key written, compressed, by version 0.2 of the crate or earlier, which had no
+    /// `commit_witness_count`. As all witnesses were committed in `d` then, the count is what is
+    /// left of `gamma_abc_g1` after the constant `1` and the `public_inputs_count` public inputs.
+    /// Returns `SerializationError::InvalidData` if `gamma_abc_g1` has no room for these.
+    pub fn deserialize_legacy<R: Read>(
+        mut reader: R,
+        public_inputs_count: usize,
+    ) -> Result<Self, SerializationError> {
+        let alpha_g1 = E::G1Affine::deserialize_compressed(&mut reader)?;
+        let beta_g2 = E::G2Affine::deserialize_compressed(&mut reader)?;
+        let gamma_g2 = E::G2Affine::deserialize_compressed(&mut reader)?;
+        let delta_g2 = E::G2Affine::deserialize_compressed(&mut reader)?;
+        let gamma_abc_g1 = Vec::<E::G1Affine>::deserialize_compressed(&mut reader)?;
+        let eta_gamma_inv_g1 = E::G1Affine::deserialize_compressed(&mut reader)?;
+        let commit_witness_count = gamma_abc_g1
+            .len()
+            .checked_sub(public_inputs_count)
+            .and_then(|count| count.checked_sub(1))
+            .and_then(|count| u32::try_from(count).ok())
+            .ok_or(SerializationError::InvalidData)?;
+        Ok(Self {
+            alpha_g1,
+            beta_g2,
+            gamma_g2,
+            delta_g2,
+            gamma_abc_g1,
+            eta_gamma_inv_g1,
+            commit_witness_count,
+        })
+    }
+
+    /// Whether proofs for this key carry a hiding commitment `d` to the committed witnesses, i.e.
+    /// whether `eta_gamma_inv_g1` is not the identity. It is the identity for parameters generated
+    /// with `eta = 0`, such as a plain Groth16 setup, in which case `d` is a deterministic function of
+    /// the witnesses and the randomness `v` has no effect.
+    pub fn supports_commitment(&self) -> bool {
+        !self.eta_gamma_inv_g1.is_zero()
+    }
+
+    /// The bases in `gamma_abc_g1` of the first `commit_count` committed witnesses of a proof with
+    /// `public_inputs_count` public inputs, and `eta_gamma_inv_g1`, the base of the randomness `v`.
+    /// `proof.d` is the commitment to the witnesses and `v` under these bases. Returns
+    /// `Error::PublicInputCountTooLarge` if `public_inputs_count` leaves no room in `gamma_abc_g1`
+    /// for the committed block, `Error::CommittedWitnessesOverlapPublicInputs` if it is less than
+    /// [`Self::expected_public_input_count`] so the block would include bases of public inputs, and
+    /// `SynthesisError::MalformedVerifyingKey` if the block has fewer than `commit_count` bases.
+    pub fn commitment_bases(
+        &self,
+        public_inputs_count: usize,
+        commit_count: usize,
+    ) -> crate::Result<(&[E::G1Affine], E::G1Affine)> {
+        // The public inputs come after the constant `1` and before the committed block. Checked
+        // arithmetic as `public_inputs_count` is not bounded by the length of any slice.
+        let start = public_inputs_count
+            .checked_add(1)
+            .filter(|start| *start <= self.gamma_abc_g1.len())
+            .ok_or(Error::PublicInputCountTooLarge(
+                public_inputs_count,
+                self.gamma_abc_g1.len().saturating_sub(1),
+            ))?;
+        // A variable is either a public input or a committed witness, never both
+        let expected_public_inputs = self.expected_public_input_count();
+        if public_inputs_count < expected_public_inputs {
+            return Err(Error::CommittedWitnessesOverlapPublicInputs(
+                public_inputs_count,
+                expected_public_inputs,
+            ));
+        }
+        let end = start
+            .checked_add(commit_count)
+            .filter(|end| *end <= self.gamma_abc_g1.len())
+            .ok_or(SynthesisError::MalformedVerifyingKey)?;
+        Ok((&self.gamma_abc_g1[start..end], self.eta_gamma_inv_g1))
+    }
+
     pub fn num_public_inputs(&self) -> u32 {
         self.gamma_abc_g1.len() as u32
     }
 
+    /// Check that the key can be used for verification. Returns `Error::IdentityConstantTerm` if
+    /// `gamma_abc_g1` is empty or its constant term, the one for the input `1`, is the identity.
+    pub fn validate(&self) -> crate::Result<()> {
+        match self.gamma_abc_g1.first() {
+            Some(g) if !g.is_zero() => Ok(()),
+            _ => Err(Error::IdentityConstantTerm),
+        }
+    }
+
+    /// Number of public inputs a proof must be verified with, excluding the constant `1` and
+    /// the committed witnesses.
+    pub fn expected_public_input_count(&self) -> usize {
+        // `1 + commit_witness_count` could overflow on 32-bit targets
+        self.gamma_abc_g1
+            .len()
+            .saturating_sub(1)
+            .saturating_sub(self.commit_witness_count as usize)
+    }
+
+    /// Number of instance variables of the circuit, excluding the constant `1`, that the key has
+    /// bases for in `gamma_abc_g1`. This is the circuit's public inputs followed by the
+    /// `commit_witness_count` committed witnesses, which the generator moves to the instance so
+    /// they can be committed to in `d`, i.e. `expected_public_input_count() + commit_witness_count`.
+    /// It does not bound the number of constraints or the domain size, which the key does not
+    /// determine.
+    pub fn input_capacity(&self) -> usize {
+        self.gamma_abc_g1.len().saturating_sub(1)
+    }
+
     /// Get the commitment key used for the Pedersen commitment to witnesses in the proof
     pub fn get_commitment_key_for_witnesses(&self) -> Vec<E::G1Affine> {
         let num_inp = self.num_public_inputs();
@@ -201,4 +770,69 @@ impl<E: Pairing> VerifyingKey<E> {
         key.push(self.eta_gamma_inv_g1);
         key
     }
-}
\ No newline at end of file
+}
+impl<E: Pairing> VerifyingKeyWithLink<E> {
+    /// Version of the layout written by [`Self::serialize_versioned`].
+    pub const SERIALIZATION_VERSION: u8 = 1;
+
+    /// Serialize the key, compressed, after a byte with [`Self::SERIALIZATION_VERSION`] so that a
+    /// key with an older layout is rejected by [`Self::deserialize_versioned`] instead of being
+    /// misparsed. The derived `CanonicalSerialize` is unchanged and writes no version.
+    pub fn serialize_versioned<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        Self::SERIALIZATION_VERSION.serialize_compressed(&mut writer)?;
+        self.serialize_compressed(&mut writer)
+    }
+
+    /// Deserialize a key written by [`Self::serialize_versioned`]. Returns
+    /// `SerializationError::InvalidData` if the version byte isn't [`Self::SERIALIZATION_VERSION`].
+    pub fn deserialize_versioned<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        if u8::deserialize_compressed(&mut reader)? != Self::SERIALIZATION_VERSION {
+            return Err(SerializationError::InvalidData);
+        }
+        Self::deserialize_compressed(&mut reader)
+    }
+
+    /// Check that cp_link commits to as many witnesses as the Groth16 proof's `d`, i.e. the link
+    /// matrix has a column for each committed witness and the 2 randomness values `link_v` and `v`,
+    /// and `link_bases` has a base for each committed witness and `link_v`. Returns
+    /// `Error::LinkKeyMismatch(link_count, groth16_count)` otherwise, or
+    /// `Error::MatrixRowCountMismatch(2, rows)` if the link matrix doesn't have a row for each of
+    /// the two commitments.
+    pub fn check_link_consistency(&self) -> crate::Result<()> {
+        if self.link_pp.l != 2 {
+            return Err(Error::MatrixRowCountMismatch(2, self.link_pp.l));
+        }
+        let groth16_count = self.groth16_vk.commit_witness_count as usize;
+        let link_count = self.link_pp.t.saturating_sub(2);
+        if link_count != groth16_count || self.link_pp.t < 2 {
+            return Err(Error::LinkKeyMismatch(link_count, groth16_count));
+        }
+        if self.link_bases.len() != groth16_count + 1 {
+            return Err(Error::LinkKeyMismatch(
+                self.link_bases.len().saturating_sub(1),
+                groth16_count,
+            ));
+        }
+        Ok(())
+    }
+
+    /// The matrix the CP-link keys were generated for. Its first row holds `link_bases` and its
+    /// second row the bases of the committed witnesses in `groth16_vk.gamma_abc_g1` followed by
+    /// `eta_gamma_inv_g1` in the last column. Fails like [`Self::check_link_consistency`].
+    pub fn link_matrix(&self) -> crate::Result<SparseMatrix<E::G1Affine>> {
+        self.check_link_consistency()?;
+        let gamma_abc_g1 = &self.groth16_vk.gamma_abc_g1;
+        let committed = &gamma_abc_g1[gamma_abc_g1
+            .len()
+            .saturating_sub(self.groth16_vk.commit_witness_count as usize)..];
+        let mut link_m = SparseMatrix::new(self.link_pp.l, self.link_pp.t);
+        link_m.insert_row_slice(0, 0, &self.link_bases);
+        link_m.insert_row_slice(1, 0, committed);
+        link_m.insert_row_slice(
+            1,
+            self.link_pp.t.saturating_sub(1),
+            &[self.groth16_vk.eta_gamma_inv_g1],
+        );
+        Ok(link_m)
+    }
+}
@@ -1,5 +1,6 @@
 use crate::link::error::LinkError;
 use ark_relations::r1cs::SynthesisError;
+use ark_std::string::String;
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Error {
@@ -10,6 +11,49 @@ pub enum Error {
     InvalidLinkCommitment,
     InvalidWitnessCommitment,
     InsufficientWitnessesForCommitment(usize, usize),
+    MatrixRowCountMismatch(usize, usize),
+    EmptyPedersenBases,
+    UnsatisfiedConstraint(usize),
+    LinkKeyMismatch(usize, usize),
+    PublicInputCountTooLarge(usize, usize),
+    InvalidProofPoint,
+    InvalidPreparedVerifyingKey,
+    IdentityConstantTerm,
+    MissingPublicInput(String),
+    UnexpectedPublicInput(String),
+    InvalidMsmWindow(usize),
+    PublicInputIndexOutOfRange(usize, usize),
+    DuplicateProof(usize),
+    CommittedWitnessIndexOutOfRange(usize, usize),
+    /// The QAP needs an evaluation domain of `requested` elements but the scalar field only
+    /// supports domains of up to `max` elements.
+    DomainTooLarge {
+        requested: usize,
+        max: usize,
+    },
+    /// The randomness named by the field is zero: one of `r`, `s` or `v`, so the proof is not
+    /// zero-knowledge, or `delta_ratio` of a key update, which is not invertible.
+    ZeroRandomness(&'static str),
+    /// The verifying key element with this name, e.g. `gamma_abc_g1[2]`, failed to deserialize or is
+    /// not in the prime order subgroup.
+    InvalidVerifyingKeyElement(String),
+    /// The evaluation domain given to the generator is not the one of the circuit. Has the sizes of
+    /// the expected and of the given domains, which are the same if only the offset differs.
+    DomainMismatch(usize, usize),
+    /// The point at which the generator evaluates the QAP is in the evaluation domain.
+    EvaluationPointInDomain,
+    /// The committed witnesses would start at the bases of public inputs since fewer public inputs,
+    /// the first value, were given than the verifying key has, the second value.
+    CommittedWitnessesOverlapPublicInputs(usize, usize),
+    /// The witness at this index is past the committed block of a key shrunk with
+    /// `ProvingKeyWithLink::truncate_commitment` but is not zero, so the proof would not verify.
+    UncommittedWitnessNotZero(usize),
+    /// The hash function has an output of the first number of bytes but at least the second number
+    /// are needed.
+    DigestTooShort(usize, usize),
+    /// The zkey given to `ProvingKey::from_zkey_reader` is malformed or not a Groth16 key on Bn254,
+    /// for the given reason.
+    InvalidZkey(&'static str),
 }
 
 impl From<SynthesisError> for Error {
@@ -22,4 +66,15 @@ impl From<LinkError> for Error {
     fn from(e: LinkError) -> Self {
         Self::LinkError(e)
     }
-}
\ No newline at end of file
+}
+/// Error of [`verify_proof_strict`](crate::verify_proof_strict), separating malformed proofs and
+/// inputs from other failures.
+#[derive(Clone, Debug, PartialEq)]
+pub enum VerifyError {
+    /// An element of the proof is not on the curve or not in the prime order subgroup.
+    InvalidPoint,
+    /// The verifying key expects `expected` public inputs but `got` were given.
+    InputLengthMismatch { expected: usize, got: usize },
+    /// Any other failure, e.g. of a malformed verifying key.
+    Internal(Error),
+}
@@ -1,62 +1,90 @@
 use core::ops::Mul;
+use digest::Digest;
 
 use crate::{
+    error::Error,
     link::{PESubspaceSnark, SparseMatrix, SubspaceSnark, PP},
     r1cs_to_qap::R1CStoQAP,
-    ProvingKey, Vec, VerifyingKey, ProvingKeyWithLink, VerifyingKeyWithLink, ProvingKeyCommon,
+    CircuitStats, DeltaUpdateProof, ProvingKey, ProvingKeyCommon, ProvingKeyWithLink, QapDensity,
+    Vec, VerifyingKey, VerifyingKeyWithLink,
+};
+use ark_ec::{
+    pairing::Pairing,
+    scalar_mul::{fixed_base::FixedBase, ScalarMul},
+    AffineRepr, CurveGroup,
 };
-use ark_ec::{pairing::Pairing, scalar_mul::fixed_base::FixedBase, AffineRepr, CurveGroup};
 use ark_ff::{Field, PrimeField, UniformRand, Zero};
 use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
 use ark_relations::r1cs::{
-    ConstraintSynthesizer, ConstraintSystem, OptimizationGoal, Result as R1CSResult,
-    SynthesisError, SynthesisMode,
+    ConstraintMatrices, ConstraintSynthesizer, ConstraintSystem, OptimizationGoal,
+    Result as R1CSResult, SynthesisError, SynthesisMode,
+};
+use ark_serialize::CanonicalSerialize;
+use ark_std::rand::{Rng, SeedableRng};
+use ark_std::{
+    borrow::Cow, cfg_into_iter, cfg_iter, collections::BTreeMap, end_timer, start_timer, vec,
 };
-use ark_std::rand::Rng;
-use ark_std::{cfg_into_iter, cfg_iter, end_timer, start_timer};
+use rand_chacha::ChaCha20Rng;
 
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
 
 /// Generates a random common reference string for
-/// a circuit.
+/// a circuit. A circuit too large for the FFT domains of the scalar field gives
+/// `Error::DomainTooLarge`.
 #[inline]
-pub fn generate_random_parameters<E, C, R>(
-    circuit: C,
-    rng: &mut R,
-) -> R1CSResult<ProvingKey<E>>
+pub fn generate_random_parameters<E, C, R>(circuit: C, rng: &mut R) -> crate::Result<ProvingKey<E>>
 where
     E: Pairing,
     C: ConstraintSynthesizer<E::ScalarField>,
     R: Rng,
 {
+    let (alpha, beta, gamma, delta, eta) = generate_randomness::<E, R>(rng);
 
-    let (alpha, beta, gamma, delta, eta) =
-        generate_randomness::<E, R>(rng);
-
-    let (pk, _) = generate_parameters::<E, C, R>(circuit, alpha, beta, gamma, delta, eta, rng).unwrap();
+    let (pk, _) = generate_parameters::<E, C, R>(circuit, alpha, beta, gamma, delta, eta, rng)?;
     Ok(pk)
 }
 
 /// Generates a random common reference string for
-/// a circuit with CP-link.
+/// a circuit with CP-link. `pedersen_bases` must contain a base for each committed witness
+/// followed by the base for the randomness.
 #[inline]
 pub fn generate_random_parameters_with_link<E, C, R>(
     circuit: C,
     pedersen_bases: &[E::G1Affine],
     rng: &mut R,
-) -> R1CSResult<ProvingKeyWithLink<E>>
+) -> crate::Result<ProvingKeyWithLink<E>>
 where
     E: Pairing,
     C: ConstraintSynthesizer<E::ScalarField>,
     R: Rng,
 {
+    let (pk, _) = generate_random_parameters_with_link_matrix(circuit, pedersen_bases, rng)?;
+    Ok(pk)
+}
 
-    let (alpha, beta, gamma, delta, eta) =
-        generate_randomness::<E, R>(rng);
+/// Same as [`generate_random_parameters_with_link`] but also returns the matrix that the CP-link
+/// keys were generated for, e.g. to persist it for auditing the setup. Row 0 has `pedersen_bases`
+/// and row 1 the bases of the committed witnesses in `gamma_abc_g1` followed, in the last column, by
+/// `eta_gamma_inv_g1`.
+pub fn generate_random_parameters_with_link_matrix<E, C, R>(
+    circuit: C,
+    pedersen_bases: &[E::G1Affine],
+    rng: &mut R,
+) -> crate::Result<(ProvingKeyWithLink<E>, SparseMatrix<E::G1Affine>)>
+where
+    E: Pairing,
+    C: ConstraintSynthesizer<E::ScalarField>,
+    R: Rng,
+{
+    if pedersen_bases.is_empty() {
+        return Err(Error::EmptyPedersenBases);
+    }
 
-    let (groth16_pk, num_instance_variables) = generate_parameters::<E, C, R>(circuit, alpha, beta, gamma, delta, eta, rng).unwrap();
+    let (alpha, beta, gamma, delta, eta) = generate_randomness::<E, R>(rng);
 
+    let (groth16_pk, num_instance_variables) =
+        generate_parameters::<E, C, R>(circuit, alpha, beta, gamma, delta, eta, rng)?;
 
     let link_rows = 2; // we're comparirng two commitments
     let link_cols = pedersen_bases.len() + 1; // we have len witnesses and 1 hiding factor per row
@@ -69,16 +97,15 @@ where
 
     let mut link_m = SparseMatrix::<E::G1Affine>::new(link_rows, link_cols);
     let commit_witness_count = groth16_pk.vk.gamma_abc_g1[num_instance_variables..].len();
-    link_m.insert_row_slice(0, 0, &pedersen_bases);
+    link_m.insert_row_slice(0, 0, pedersen_bases);
+    link_m.insert_row_slice(1, 0, &groth16_pk.vk.gamma_abc_g1[num_instance_variables..]);
     link_m.insert_row_slice(
         1,
-        0,
-        &groth16_pk.vk.gamma_abc_g1[num_instance_variables..]
-            .to_vec(),
+        commit_witness_count + 1,
+        &[groth16_pk.vk.eta_gamma_inv_g1],
     );
-    link_m.insert_row_slice(1, commit_witness_count+1, &[groth16_pk.vk.eta_gamma_inv_g1]);
 
-    let (link_ek, link_vk) = PESubspaceSnark::<E>::keygen(rng, &link_pp, link_m);
+    let (link_ek, link_vk) = PESubspaceSnark::<E>::keygen(rng, &link_pp, link_m.clone());
     let vk = VerifyingKeyWithLink::<E> {
         groth16_vk: groth16_pk.vk,
         link_pp,
@@ -86,15 +113,46 @@ where
         link_vk,
     };
 
-    Ok(ProvingKeyWithLink {
-        vk,
-        common: groth16_pk.common,
-        link_ek,
-    })
+    Ok((
+        ProvingKeyWithLink {
+            vk,
+            common: groth16_pk.common,
+            link_ek,
+        },
+        link_m,
+    ))
+}
+
+/// Same as [`generate_random_parameters_with_link`] but the base for the randomness `link_v` of the
+/// CP-link commitment can be given separately, e.g. when it is a hash-to-curve point of an external
+/// commitment scheme. With `Some(base)`, `pedersen_bases` only contains the bases of the committed
+/// witnesses and `base` is used for the randomness. With `None`, `pedersen_bases` ends with the base
+/// for the randomness as for [`generate_random_parameters_with_link`].
+pub fn generate_random_parameters_with_link_blinding_base<E, C, R>(
+    circuit: C,
+    pedersen_bases: &[E::G1Affine],
+    link_blinding_base: Option<E::G1Affine>,
+    rng: &mut R,
+) -> crate::Result<ProvingKeyWithLink<E>>
+where
+    E: Pairing,
+    C: ConstraintSynthesizer<E::ScalarField>,
+    R: Rng,
+{
+    match link_blinding_base {
+        Some(base) => {
+            let mut bases = Vec::with_capacity(pedersen_bases.len() + 1);
+            bases.extend_from_slice(pedersen_bases);
+            bases.push(base);
+            generate_random_parameters_with_link(circuit, &bases, rng)
+        }
+        None => generate_random_parameters_with_link(circuit, pedersen_bases, rng),
+    }
 }
 
 // generate random params
 #[inline]
+#[allow(clippy::type_complexity)]
 fn generate_randomness<E, R>(
     rng: &mut R,
 ) -> (
@@ -132,9 +190,272 @@ where
     C: ConstraintSynthesizer<E::ScalarField>,
     R: Rng,
 {
-    type D<F> = GeneralEvaluationDomain<F>;
+    let (pk, num_instance, _) =
+        generate_parameters_with_stats::<E, C, R>(circuit, alpha, beta, gamma, delta, eta, rng)?;
+    Ok((pk, num_instance))
+}
 
-    let setup_time = start_timer!(|| "Groth16::Generator");
+/// Same as [`generate_parameters`] but also returns the [`QapDensity`] of the circuit.
+pub fn generate_parameters_with_stats<E, C, R>(
+    circuit: C,
+    alpha: E::ScalarField,
+    beta: E::ScalarField,
+    gamma: E::ScalarField,
+    delta: E::ScalarField,
+    eta: E::ScalarField,
+    rng: &mut R,
+) -> crate::Result<(ProvingKey<E>, usize, QapDensity)>
+where
+    E: Pairing,
+    C: ConstraintSynthesizer<E::ScalarField>,
+    R: Rng,
+{
+    let matrices = synthesize_setup_matrices(circuit)?;
+    let (pk, density) = generate_parameters_from_matrices_with_stats::<E, R>(
+        &matrices, alpha, beta, gamma, delta, eta, rng,
+    )?;
+    Ok((pk, matrices.num_instance_variables, density))
+}
+
+/// Same as [`generate_parameters`] but uses the given window size for the G1 fixed-base MSMs
+/// rather than the one picked by `FixedBase::get_mul_window_size`. The window can be calibrated
+/// for the current machine with [`tune_msm_window`].
+#[allow(clippy::too_many_arguments)]
+pub fn generate_parameters_with_window<E, C, R>(
+    circuit: C,
+    alpha: E::ScalarField,
+    beta: E::ScalarField,
+    gamma: E::ScalarField,
+    delta: E::ScalarField,
+    eta: E::ScalarField,
+    window: usize,
+    rng: &mut R,
+) -> crate::Result<(ProvingKey<E>, usize)>
+where
+    E: Pairing,
+    C: ConstraintSynthesizer<E::ScalarField>,
+    R: Rng,
+{
+    if window == 0 || window > MAX_MSM_WINDOW {
+        return Err(Error::InvalidMsmWindow(window));
+    }
+    let matrices = synthesize_setup_matrices(circuit)?;
+    let (pk, _) = generate_parameters_from_matrices_inner::<E, R>(
+        &matrices,
+        alpha,
+        beta,
+        gamma,
+        delta,
+        eta,
+        SetupOptions {
+            g1_window: Some(window),
+            ..SetupOptions::default()
+        },
+        rng,
+    )?;
+    Ok((pk, matrices.num_instance_variables))
+}
+
+/// Same as [`generate_parameters`] but with the evaluation domain `domain` and the point `t` at
+/// which the QAP is evaluated given rather than built and sampled, e.g. to build the domain once for
+/// many circuits of the same size or for a deterministic setup. `t` is toxic waste just like `alpha`
+/// and the others. Returns `Error::DomainMismatch` if `domain` is not the one that the prover builds
+/// for the circuit and `Error::EvaluationPointInDomain` if `t` is in `domain`.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_parameters_with_domain_and_t<E, C, R>(
+    circuit: C,
+    domain: GeneralEvaluationDomain<E::ScalarField>,
+    t: E::ScalarField,
+    alpha: E::ScalarField,
+    beta: E::ScalarField,
+    gamma: E::ScalarField,
+    delta: E::ScalarField,
+    eta: E::ScalarField,
+    rng: &mut R,
+) -> crate::Result<(ProvingKey<E>, usize)>
+where
+    E: Pairing,
+    C: ConstraintSynthesizer<E::ScalarField>,
+    R: Rng,
+{
+    let matrices = synthesize_setup_matrices(circuit)?;
+    let (pk, _) = generate_parameters_from_matrices_inner::<E, R>(
+        &matrices,
+        alpha,
+        beta,
+        gamma,
+        delta,
+        eta,
+        SetupOptions {
+            domain_and_t: Some((domain, t)),
+            ..SetupOptions::default()
+        },
+        rng,
+    )?;
+    Ok((pk, matrices.num_instance_variables))
+}
+
+/// Largest window size accepted by [`generate_parameters_with_window`].
+pub const MAX_MSM_WINDOW: usize = 24;
+
+/// Fixed-base window tables of the generator, memoized across calls to
+/// [`generate_parameters_with_cache`] so that parameters for many circuits of the same size are
+/// generated without recomputing them. Tables are only reusable for the same generator points, so
+/// the cache samples its own `g1_generator` and `g2_generator` and all parameters generated with it
+/// use them. The toxic waste is still sampled per call, so the keys are independent.
+///
+/// Memory: a table for a window of `w` bits holds `ceil(scalar_bits / w) * 2^w` affine points, and a
+/// table is kept for each `(window, scalar_bits)` pair used. For example a window of 15 over a
+/// 255-bit field is `17 * 2^15`, about 557 thousand, G1 points, which is over 50 MB on BLS12-381.
+/// Call [`GeneratorCache::clear`] to free them.
+#[derive(Clone, Debug)]
+pub struct GeneratorCache<E: Pairing> {
+    pub g1_generator: E::G1,
+    pub g2_generator: E::G2,
+    g1_tables: WindowTables<E::G1>,
+    g2_tables: WindowTables<E::G2>,
+}
+
+/// Window tables keyed by `(window, scalar_bits)`.
+type WindowTables<G> = BTreeMap<(usize, usize), Vec<Vec<<G as ScalarMul>::MulBase>>>;
+
+impl<E: Pairing> GeneratorCache<E> {
+    /// An empty cache with random generators.
+    pub fn new<R: Rng>(rng: &mut R) -> Self {
+        Self {
+            g1_generator: E::G1::rand(rng),
+            g2_generator: E::G2::rand(rng),
+            g1_tables: BTreeMap::new(),
+            g2_tables: BTreeMap::new(),
+        }
+    }
+
+    /// Number of window tables in the cache, in G1 and G2.
+    pub fn table_count(&self) -> (usize, usize) {
+        (self.g1_tables.len(), self.g2_tables.len())
+    }
+
+    /// Drop all window tables, keeping the generators.
+    pub fn clear(&mut self) {
+        self.g1_tables.clear();
+        self.g2_tables.clear();
+    }
+}
+
+/// The window table of `g`, from `tables` if it has one for `window` and `scalar_bits` and
+/// inserting it otherwise. Without `tables`, it is computed and returned.
+fn cached_window_table<G: ScalarMul>(
+    tables: Option<&mut WindowTables<G>>,
+    scalar_bits: usize,
+    window: usize,
+    g: G,
+) -> Cow<'_, [Vec<G::MulBase>]> {
+    match tables {
+        Some(tables) => Cow::Borrowed(
+            tables
+                .entry((window, scalar_bits))
+                .or_insert_with(|| FixedBase::get_window_table::<G>(scalar_bits, window, g))
+                .as_slice(),
+        ),
+        None => Cow::Owned(FixedBase::get_window_table::<G>(scalar_bits, window, g)),
+    }
+}
+
+/// Same as [`generate_parameters`] but with the generators of `cache` and the window tables it
+/// memoizes, adding any table it does not have yet.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_parameters_with_cache<E, C, R>(
+    circuit: C,
+    alpha: E::ScalarField,
+    beta: E::ScalarField,
+    gamma: E::ScalarField,
+    delta: E::ScalarField,
+    eta: E::ScalarField,
+    cache: &mut GeneratorCache<E>,
+    rng: &mut R,
+) -> crate::Result<(ProvingKey<E>, usize)>
+where
+    E: Pairing,
+    C: ConstraintSynthesizer<E::ScalarField>,
+    R: Rng,
+{
+    let matrices = synthesize_setup_matrices(circuit)?;
+    let (pk, _) = generate_parameters_from_matrices_inner::<E, R>(
+        &matrices,
+        alpha,
+        beta,
+        gamma,
+        delta,
+        eta,
+        SetupOptions {
+            cache: Some(cache),
+            ..SetupOptions::default()
+        },
+        rng,
+    )?;
+    Ok((pk, matrices.num_instance_variables))
+}
+
+/// Benchmark fixed-base G1 MSMs over `sample_size` random scalars for a range of window sizes and
+/// return the fastest one on the current machine. The result is in `1..=MAX_MSM_WINDOW` and is
+/// meant to be computed once and passed to [`generate_parameters_with_window`].
+#[cfg(feature = "std")]
+pub fn tune_msm_window<E: Pairing, R: Rng>(sample_size: usize, rng: &mut R) -> usize {
+    let scalar_bits = E::ScalarField::MODULUS_BIT_SIZE as usize;
+    let sample_size = sample_size.max(1);
+    let scalars = (0..sample_size)
+        .map(|_| E::ScalarField::rand(rng))
+        .collect::<Vec<_>>();
+    let generator = E::G1::rand(rng);
+
+    // Only try windows around the heuristic one, larger windows have exponentially larger tables.
+    let heuristic = FixedBase::get_mul_window_size(sample_size);
+    let max_window = (heuristic + 2).min(MAX_MSM_WINDOW);
+    let mut best = (heuristic.clamp(1, MAX_MSM_WINDOW), std::time::Duration::MAX);
+    for window in 1..=max_window {
+        let start = std::time::Instant::now();
+        let table = FixedBase::get_window_table::<E::G1>(scalar_bits, window, generator);
+        let res = FixedBase::msm::<E::G1>(scalar_bits, window, &table, &scalars);
+        let elapsed = start.elapsed();
+        core::hint::black_box(res);
+        if elapsed < best.1 {
+            best = (window, elapsed);
+        }
+    }
+    best.0
+}
+
+/// Synthesize `circuit` as the generator does and return the [`CircuitStats`] of its constraints,
+/// e.g. to find where a circuit spends constraints on long linear combinations.
+pub fn analyze_circuit<F, C>(circuit: C) -> R1CSResult<CircuitStats>
+where
+    F: PrimeField,
+    C: ConstraintSynthesizer<F>,
+{
+    let matrices = synthesize_setup_matrices(circuit)?;
+    let histogram = |m: &[Vec<(F, usize)>]| {
+        let mut histogram = BTreeMap::new();
+        for row in m {
+            *histogram.entry(row.len()).or_insert(0) += 1;
+        }
+        histogram
+    };
+    Ok(CircuitStats {
+        num_constraints: matrices.num_constraints,
+        num_instance_variables: matrices.num_instance_variables,
+        num_witness_variables: matrices.num_witness_variables,
+        a_histogram: histogram(&matrices.a),
+        b_histogram: histogram(&matrices.b),
+        c_histogram: histogram(&matrices.c),
+        a_num_non_zero: matrices.a_num_non_zero,
+        b_num_non_zero: matrices.b_num_non_zero,
+        c_num_non_zero: matrices.c_num_non_zero,
+    })
+}
+
+fn synthesize_setup_matrices<F: PrimeField, C: ConstraintSynthesizer<F>>(
+    circuit: C,
+) -> R1CSResult<ConstraintMatrices<F>> {
     let cs = ConstraintSystem::new_ref();
     cs.set_optimization_goal(OptimizationGoal::Constraints);
     cs.set_mode(SynthesisMode::Setup);
@@ -148,38 +469,149 @@ where
     cs.finalize();
     end_timer!(lc_time);
 
+    Ok(cs.to_matrices().unwrap())
+}
+
+/// Create parameters for a circuit given as its R1CS matrices, given some toxic waste.
+/// This skips circuit synthesis, so it can be used with matrices produced by another
+/// compiler. `matrices.num_instance_variables` includes the constant `1`.
+pub fn generate_parameters_from_matrices<E, R>(
+    matrices: &ConstraintMatrices<E::ScalarField>,
+    alpha: E::ScalarField,
+    beta: E::ScalarField,
+    gamma: E::ScalarField,
+    delta: E::ScalarField,
+    eta: E::ScalarField,
+    rng: &mut R,
+) -> crate::Result<ProvingKey<E>>
+where
+    E: Pairing,
+    R: Rng,
+{
+    let (pk, _) = generate_parameters_from_matrices_with_stats::<E, R>(
+        matrices, alpha, beta, gamma, delta, eta, rng,
+    )?;
+    Ok(pk)
+}
+
+/// Same as [`generate_parameters_from_matrices`] but also returns the [`QapDensity`] of the circuit.
+pub fn generate_parameters_from_matrices_with_stats<E, R>(
+    matrices: &ConstraintMatrices<E::ScalarField>,
+    alpha: E::ScalarField,
+    beta: E::ScalarField,
+    gamma: E::ScalarField,
+    delta: E::ScalarField,
+    eta: E::ScalarField,
+    rng: &mut R,
+) -> crate::Result<(ProvingKey<E>, QapDensity)>
+where
+    E: Pairing,
+    R: Rng,
+{
+    generate_parameters_from_matrices_inner::<E, R>(
+        matrices,
+        alpha,
+        beta,
+        gamma,
+        delta,
+        eta,
+        SetupOptions::default(),
+        rng,
+    )
+}
+
+/// Optional inputs of the generator that are otherwise picked or computed by it.
+struct SetupOptions<'a, E: Pairing> {
+    /// Window of the G1 fixed-base MSMs.
+    g1_window: Option<usize>,
+    /// Generators and window tables to use.
+    cache: Option<&'a mut GeneratorCache<E>>,
+    /// Evaluation domain, checked to be the one of the circuit, and point `t` outside of it.
+    domain_and_t: Option<(GeneralEvaluationDomain<E::ScalarField>, E::ScalarField)>,
+}
+
+impl<E: Pairing> Default for SetupOptions<'_, E> {
+    fn default() -> Self {
+        Self {
+            g1_window: None,
+            cache: None,
+            domain_and_t: None,
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn generate_parameters_from_matrices_inner<E, R>(
+    matrices: &ConstraintMatrices<E::ScalarField>,
+    alpha: E::ScalarField,
+    beta: E::ScalarField,
+    gamma: E::ScalarField,
+    delta: E::ScalarField,
+    eta: E::ScalarField,
+    options: SetupOptions<'_, E>,
+    rng: &mut R,
+) -> crate::Result<(ProvingKey<E>, QapDensity)>
+where
+    E: Pairing,
+    R: Rng,
+{
+    type D<F> = GeneralEvaluationDomain<F>;
+
+    let SetupOptions {
+        g1_window,
+        cache,
+        domain_and_t,
+    } = options;
+
+    check_matrix_rows(matrices)?;
+
+    let setup_time = start_timer!(|| "Groth16::Generator");
+
     ///////////////////////////////////////////////////////////////////////////
     let domain_time = start_timer!(|| "Constructing evaluation domain");
 
-    let domain_size = cs.num_constraints() + cs.num_instance_variables();
-    let domain = D::new(domain_size).ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
-    let t = domain.sample_element_outside_domain(rng);
+    let expected_domain = R1CStoQAP::checked_domain::<E::ScalarField, D<E::ScalarField>>(
+        matrices.num_constraints,
+        matrices.num_instance_variables,
+    )?;
+    let (domain, t) = match domain_and_t {
+        Some((domain, t)) => {
+            // The prover builds the domain from the circuit so any other one gives unusable keys
+            if domain != expected_domain {
+                return Err(Error::DomainMismatch(expected_domain.size(), domain.size()));
+            }
+            if domain.evaluate_vanishing_polynomial(t).is_zero() {
+                return Err(Error::EvaluationPointInDomain);
+            }
+            (domain, t)
+        }
+        None => {
+            let t = expected_domain.sample_element_outside_domain(rng);
+            (expected_domain, t)
+        }
+    };
 
     end_timer!(domain_time);
     ///////////////////////////////////////////////////////////////////////////
 
     let reduction_time = start_timer!(|| "R1CS to QAP Instance Map with Evaluation");
     // following line take into account the number of witness which will be included in the commitment
-    let num_instance_var = cs.num_instance_variables();
-    let num_instance_variables = num_instance_var + cs.num_witness_variables();
+    let num_instance_var = matrices.num_instance_variables;
+    let num_instance_variables = num_instance_var + matrices.num_witness_variables;
     let (a, b, c, zt, qap_num_variables, m_raw) =
-        R1CStoQAP::instance_map_with_evaluation::<E::ScalarField, D<E::ScalarField>>(cs, &t)?;
+        R1CStoQAP::instance_map_with_domain(matrices, &domain, &t)?;
     end_timer!(reduction_time);
 
-    // Compute query densities
-    let non_zero_a: usize = cfg_into_iter!(0..qap_num_variables)
-        .map(|i| usize::from(!a[i].is_zero()))
-        .sum();
+    // Compute query densities over all `qap_num_variables + 1` columns
+    let non_zero_a: usize = cfg_iter!(a).map(|a_i| usize::from(!a_i.is_zero())).sum();
 
-    let non_zero_b: usize = cfg_into_iter!(0..qap_num_variables)
-        .map(|i| usize::from(!b[i].is_zero()))
-        .sum();
+    let non_zero_b: usize = cfg_iter!(b).map(|b_i| usize::from(!b_i.is_zero())).sum();
 
     // Cast to usize
     let scalar_bits = E::ScalarField::MODULUS_BIT_SIZE as usize;
 
-    let gamma_inverse = gamma.inverse().ok_or(SynthesisError::UnexpectedIdentity)?;
-    let delta_inverse = delta.inverse().ok_or(SynthesisError::UnexpectedIdentity)?;
+    let inverses = batch_inverse(&[gamma, delta]).ok_or(SynthesisError::UnexpectedIdentity)?;
+    let (gamma_inverse, delta_inverse) = (inverses[0], inverses[1]);
 
     let gamma_abc = cfg_iter!(a[..num_instance_variables])
         .zip(&b[..num_instance_variables])
@@ -195,83 +627,110 @@ where
 
     drop(c);
 
-    let g1_generator = E::G1::rand(rng);
-    let g2_generator = E::G2::rand(rng);
-
-    // Compute B window table
-    let g2_time = start_timer!(|| "Compute G2 table");
-    let g2_window = FixedBase::get_mul_window_size(non_zero_b);
-    let g2_table = FixedBase::get_window_table::<E::G2>(scalar_bits, g2_window, g2_generator);
-    end_timer!(g2_time);
-
-    // Compute the B-query in G2
-    let b_g2_time = start_timer!(|| "Calculate B G2");
-    let b_g2_query = FixedBase::msm::<E::G2>(scalar_bits, g2_window, &g2_table, &b);
-    drop(g2_table);
-    end_timer!(b_g2_time);
-
-    // Compute G window table
-    let g1_window_time = start_timer!(|| "Compute G1 window table");
-    let g1_window =
-        FixedBase::get_mul_window_size(non_zero_a + non_zero_b + qap_num_variables + m_raw + 1);
-    let g1_table = FixedBase::get_window_table::<E::G1>(scalar_bits, g1_window, g1_generator);
-    end_timer!(g1_window_time);
-
-    // Generate the R1CS proving key
-    let proving_key_time = start_timer!(|| "Generate the R1CS proving key");
+    let (g1_generator, g2_generator) = match &cache {
+        Some(cache) => (cache.g1_generator, cache.g2_generator),
+        None => (E::G1::rand(rng), E::G2::rand(rng)),
+    };
 
     let alpha_g1 = g1_generator.mul(alpha);
     let beta_g1 = g1_generator.mul(beta);
     let beta_g2 = g2_generator.mul(beta);
     let delta_g1 = g1_generator.mul(delta);
     let delta_g2 = g2_generator.mul(delta);
+    let gamma_g2 = g2_generator.mul(gamma);
 
-    // Compute the A-query
-    let a_time = start_timer!(|| "Calculate A");
-    let a_query = FixedBase::msm::<E::G1>(scalar_bits, g1_window, &g1_table, &a);
-    drop(a);
-    end_timer!(a_time);
+    let (g1_tables, g2_tables) = match cache {
+        Some(cache) => (Some(&mut cache.g1_tables), Some(&mut cache.g2_tables)),
+        None => (None, None),
+    };
+    let g1_window = g1_window.unwrap_or_else(|| {
+        FixedBase::get_mul_window_size(non_zero_a + non_zero_b + qap_num_variables + m_raw + 1)
+    });
 
-    // Compute the B-query in G1
-    let b_g1_time = start_timer!(|| "Calculate B G1");
-    let b_g1_query = FixedBase::msm::<E::G1>(scalar_bits, g1_window, &g1_table, &b);
-    drop(b);
-    end_timer!(b_g1_time);
+    // The G2 and G1 queries are independent so they are computed concurrently
+    let (b_g2_query, (a_query, b_g1_query, h_query, l_query, gamma_abc_g1)) = join(
+        || {
+            // Compute B window table
+            let g2_time = start_timer!(|| "Compute G2 table");
+            let g2_window = FixedBase::get_mul_window_size(non_zero_b);
+            let g2_table = cached_window_table(g2_tables, scalar_bits, g2_window, g2_generator);
+            end_timer!(g2_time);
 
-    // Compute the H-query
-    let h_time = start_timer!(|| "Calculate H");
-    let h_query = FixedBase::msm::<E::G1>(
-        scalar_bits,
-        g1_window,
-        &g1_table,
-        &cfg_into_iter!(0..m_raw - 1)
-            .map(|i| zt * &delta_inverse * &t.pow([i as u64]))
-            .collect::<Vec<_>>(),
-    );
+            // Compute the B-query in G2
+            let b_g2_time = start_timer!(|| "Calculate B G2");
+            let b_g2_query = FixedBase::msm::<E::G2>(scalar_bits, g2_window, &g2_table, &b);
+            drop(g2_table);
+            end_timer!(b_g2_time);
 
-    end_timer!(h_time);
+            E::G2::normalize_batch(&b_g2_query)
+        },
+        || {
+            // Compute G window table
+            let g1_window_time = start_timer!(|| "Compute G1 window table");
+            let g1_table = cached_window_table(g1_tables, scalar_bits, g1_window, g1_generator);
+            end_timer!(g1_window_time);
 
-    // Compute the L-query
-    let l_time = start_timer!(|| "Calculate L");
-    let l_query = FixedBase::msm::<E::G1>(
-        scalar_bits,
-        g1_window,
-        &g1_table,
-        &l[num_instance_variables..],
-    );
-    drop(l);
-    end_timer!(l_time);
+            // Generate the R1CS proving key
+            let proving_key_time = start_timer!(|| "Generate the R1CS proving key");
 
-    end_timer!(proving_key_time);
+            // Each query is converted to affine while the next one is being computed.
+            // Compute the A-query
+            let a_time = start_timer!(|| "Calculate A");
+            let a_query = FixedBase::msm::<E::G1>(scalar_bits, g1_window, &g1_table, &a);
+            drop(a);
+            end_timer!(a_time);
 
-    // Generate R1CS verification key
-    let verifying_key_time = start_timer!(|| "Generate the R1CS verification key");
-    let gamma_g2 = g2_generator.mul(gamma);
-    let gamma_abc_g1 = FixedBase::msm::<E::G1>(scalar_bits, g1_window, &g1_table, &gamma_abc);
+            // Compute the B-query in G1
+            let b_g1_time = start_timer!(|| "Calculate B G1");
+            let (a_query, b_g1_query) = join(
+                || E::G1::normalize_batch(&a_query),
+                || FixedBase::msm::<E::G1>(scalar_bits, g1_window, &g1_table, &b),
+            );
+            end_timer!(b_g1_time);
+
+            // Compute the H-query
+            let h_time = start_timer!(|| "Calculate H");
+            let h_scalars = cfg_into_iter!(0..m_raw - 1)
+                .map(|i| zt * &delta_inverse * &t.pow([i as u64]))
+                .collect::<Vec<_>>();
+            let (b_g1_query, h_query) = join(
+                || E::G1::normalize_batch(&b_g1_query),
+                || FixedBase::msm::<E::G1>(scalar_bits, g1_window, &g1_table, &h_scalars),
+            );
+            drop(h_scalars);
+            end_timer!(h_time);
+
+            // Compute the L-query
+            let l_time = start_timer!(|| "Calculate L");
+            let (h_query, l_query) = join(
+                || E::G1::normalize_batch(&h_query),
+                || {
+                    FixedBase::msm::<E::G1>(
+                        scalar_bits,
+                        g1_window,
+                        &g1_table,
+                        &l[num_instance_variables..],
+                    )
+                },
+            );
+            drop(l);
+            end_timer!(l_time);
 
-    drop(g1_table);
+            end_timer!(proving_key_time);
 
-    end_timer!(verifying_key_time);
+            // Generate R1CS verification key
+            let verifying_key_time = start_timer!(|| "Generate the R1CS verification key");
+            let (l_query, gamma_abc_g1) = join(
+                || E::G1::normalize_batch(&l_query),
+                || FixedBase::msm::<E::G1>(scalar_bits, g1_window, &g1_table, &gamma_abc),
+            );
+            drop(g1_table);
+            end_timer!(verifying_key_time);
+
+            (a_query, b_g1_query, h_query, l_query, gamma_abc_g1)
+        },
+    );
+    drop(b);
 
     let eta_gamma_inv_g1 = g1_generator.mul(eta * &gamma_inverse);
 
@@ -282,15 +741,9 @@ where
         delta_g2: delta_g2.into_affine(),
         gamma_abc_g1: E::G1::normalize_batch(&gamma_abc_g1),
         eta_gamma_inv_g1: eta_gamma_inv_g1.into_affine(),
+        commit_witness_count: (num_instance_variables - num_instance_var) as u32,
     };
 
-    let batch_normalization_time = start_timer!(|| "Convert proving key elements to affine");
-    let a_query = E::G1::normalize_batch(&a_query);
-    let b_g1_query = E::G1::normalize_batch(&b_g1_query);
-    let b_g2_query = E::G2::normalize_batch(&b_g2_query);
-    let h_query = E::G1::normalize_batch(&h_query);
-    let l_query = E::G1::normalize_batch(&l_query);
-    end_timer!(batch_normalization_time);
     end_timer!(setup_time);
 
     let eta_delta_inv_g1 = g1_generator.mul(eta * &delta_inverse);
@@ -306,8 +759,299 @@ where
         l_query,
     };
 
-    Ok((ProvingKey {
-        vk,
-        common: pk_common,
-    }, num_instance_var))
+    let density = QapDensity {
+        non_zero_a,
+        non_zero_b,
+        qap_num_variables,
+        m_raw,
+    };
+
+    Ok((
+        ProvingKey {
+            vk,
+            common: pk_common,
+        },
+        density,
+    ))
+}
+
+/// Create only the verifying key of a circuit, with random toxic waste, for a party that never
+/// proves. Cheaper than [`generate_random_parameters`] as none of the prover's queries are computed,
+/// but the circuit is still reduced to a QAP for `gamma_abc_g1`.
+pub fn generate_verifying_key_only<E, C, R>(
+    circuit: C,
+    rng: &mut R,
+) -> crate::Result<VerifyingKey<E>>
+where
+    E: Pairing,
+    C: ConstraintSynthesizer<E::ScalarField>,
+    R: Rng,
+{
+    let (alpha, beta, gamma, delta, eta) = generate_randomness::<E, R>(rng);
+    generate_verifying_key::<E, C, R>(circuit, alpha, beta, gamma, delta, eta, rng)
+}
+
+/// Create only the verifying key of a circuit, given some toxic waste. `rng` is used in the same way
+/// as by [`generate_parameters`], so for the same toxic waste and `rng` state the key is the one in
+/// the proving key that [`generate_parameters`] returns.
+pub fn generate_verifying_key<E, C, R>(
+    circuit: C,
+    alpha: E::ScalarField,
+    beta: E::ScalarField,
+    gamma: E::ScalarField,
+    delta: E::ScalarField,
+    eta: E::ScalarField,
+    rng: &mut R,
+) -> crate::Result<VerifyingKey<E>>
+where
+    E: Pairing,
+    C: ConstraintSynthesizer<E::ScalarField>,
+    R: Rng,
+{
+    type D<F> = GeneralEvaluationDomain<F>;
+
+    let matrices = synthesize_setup_matrices(circuit)?;
+    check_matrix_rows(&matrices)?;
+
+    let domain = R1CStoQAP::checked_domain::<E::ScalarField, D<E::ScalarField>>(
+        matrices.num_constraints,
+        matrices.num_instance_variables,
+    )?;
+    let t = domain.sample_element_outside_domain(rng);
+    let (a, b, c, _, _, _) = R1CStoQAP::instance_map_with_domain(&matrices, &domain, &t)?;
+
+    // The committed witnesses are part of the instance, as in the full generator
+    let num_instance_variables = matrices.num_instance_variables + matrices.num_witness_variables;
+    let gamma_inverse = gamma.inverse().ok_or(SynthesisError::UnexpectedIdentity)?;
+    let gamma_abc = cfg_iter!(a[..num_instance_variables])
+        .zip(&b[..num_instance_variables])
+        .zip(&c[..num_instance_variables])
+        .map(|((a, b), c)| (beta * a + &(alpha * b) + c) * &gamma_inverse)
+        .collect::<Vec<_>>();
+
+    let g1_generator = E::G1::rand(rng);
+    let g2_generator = E::G2::rand(rng);
+
+    let scalar_bits = E::ScalarField::MODULUS_BIT_SIZE as usize;
+    let g1_window = FixedBase::get_mul_window_size(gamma_abc.len());
+    let g1_table = FixedBase::get_window_table(scalar_bits, g1_window, g1_generator);
+    let gamma_abc_g1 = FixedBase::msm::<E::G1>(scalar_bits, g1_window, &g1_table, &gamma_abc);
+
+    Ok(VerifyingKey::<E> {
+        alpha_g1: g1_generator.mul(alpha).into_affine(),
+        beta_g2: g2_generator.mul(beta).into_affine(),
+        gamma_g2: g2_generator.mul(gamma).into_affine(),
+        delta_g2: g2_generator.mul(delta).into_affine(),
+        gamma_abc_g1: E::G1::normalize_batch(&gamma_abc_g1),
+        eta_gamma_inv_g1: g1_generator.mul(eta * &gamma_inverse).into_affine(),
+        commit_witness_count: matrices.num_witness_variables as u32,
+    })
+}
+
+/// Check that each of the matrices `a`, `b` and `c` has `num_constraints` rows.
+fn check_matrix_rows<F: PrimeField>(matrices: &ConstraintMatrices<F>) -> crate::Result<()> {
+    for rows in [matrices.a.len(), matrices.b.len(), matrices.c.len()] {
+        if rows != matrices.num_constraints {
+            return Err(Error::MatrixRowCountMismatch(
+                matrices.num_constraints,
+                rows,
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Invert all of `elems` with a single field inversion using Montgomery's trick. Returns `None` if
+/// any of them is zero.
+pub fn batch_inverse<F: Field>(elems: &[F]) -> Option<Vec<F>> {
+    // prefix_products[i] = elems[0] * .. * elems[i-1]
+    let mut prefix_products = Vec::with_capacity(elems.len());
+    let mut acc = F::one();
+    for e in elems {
+        prefix_products.push(acc);
+        acc *= e;
+    }
+    // acc_inv = (elems[0] * .. * elems[i])^-1 while going backwards
+    let mut acc_inv = acc.inverse()?;
+    let mut inverses = vec![F::zero(); elems.len()];
+    for i in (0..elems.len()).rev() {
+        inverses[i] = acc_inv * prefix_products[i];
+        acc_inv *= elems[i];
+    }
+    Some(inverses)
+}
+
+impl<E: Pairing> ProvingKey<E> {
+    /// Apply a new contribution to `delta`, as done in the phase-2 of a Groth16 setup ceremony,
+    /// without redoing the QAP reduction. The new key has `delta * delta_ratio` in place of `delta`
+    /// so `delta_g1`, `delta_g2` are multiplied and `h_query`, `l_query` and `eta_delta_inv_g1` are
+    /// divided by `delta_ratio`. The returned [`DeltaUpdateProof`] can be checked with
+    /// [`DeltaUpdateProof::verify`] given the same hash function `D`, which must have an output of
+    /// at least 256 bits. Returns `Error::ZeroRandomness` if `delta_ratio` is zero and
+    /// `Error::DigestTooShort` if the output of `D` is shorter.
+    pub fn update_delta<D: Digest, R: Rng>(
+        &self,
+        delta_ratio: E::ScalarField,
+        rng: &mut R,
+    ) -> crate::Result<(ProvingKey<E>, DeltaUpdateProof<E>)> {
+        let delta_ratio_inv = delta_ratio
+            .inverse()
+            .ok_or(Error::ZeroRandomness("delta_ratio"))?;
+
+        let scale = |elems: &[E::G1Affine]| {
+            let scaled = cfg_iter!(elems)
+                .map(|e| e.mul(delta_ratio_inv))
+                .collect::<Vec<_>>();
+            E::G1::normalize_batch(&scaled)
+        };
+        let (h_query, l_query) = join(
+            || scale(&self.common.h_query),
+            || scale(&self.common.l_query),
+        );
+
+        let mut pk = self.clone();
+        pk.common.delta_g1 = self.common.delta_g1.mul(delta_ratio).into_affine();
+        pk.common.eta_delta_inv_g1 = self
+            .common
+            .eta_delta_inv_g1
+            .mul(delta_ratio_inv)
+            .into_affine();
+        pk.common.h_query = h_query;
+        pk.common.l_query = l_query;
+        pk.vk = self.vk.apply_delta_update(delta_ratio);
+
+        // Proof of knowledge of `delta_ratio` as in BGM17: `r_g2` is only determined once `s_g1`
+        // and `s_delta_ratio_g1` are, so `r_delta_ratio_g2` can't be computed without the ratio
+        let s_g1 = E::G1::rand(rng).into_affine();
+        let s_delta_ratio_g1 = s_g1.mul(delta_ratio).into_affine();
+        let r_g2 = delta_update_r_g2::<E, D>(self, &pk, &s_g1, &s_delta_ratio_g1)?;
+        let proof = DeltaUpdateProof {
+            s_g1,
+            s_delta_ratio_g1,
+            r_delta_ratio_g2: r_g2.mul(delta_ratio).into_affine(),
+        };
+        Ok((pk, proof))
+    }
+}
+
+/// Domain separation tag of the hash giving the `r_g2` of a [`DeltaUpdateProof`].
+const DELTA_UPDATE_DST: &[u8] = b"LegoGroth16-delta-update";
+
+/// The element `r_g2` of a [`DeltaUpdateProof`] from `old_pk` to `new_pk` with `s_g1` and
+/// `s_delta_ratio_g1`, a random point of `E::G2` sampled with a seed hashed with `D` from them and
+/// the `delta` elements of the keys, so that its discrete logarithm is unknown. The point is sampled
+/// with ChaCha20, whose output is fixed for a seed unlike that of `StdRng`, so that proofs stay
+/// verifiable across dependency updates. Returns `Error::DigestTooShort` if the output of `D` is
+/// shorter than the seed.
+pub(crate) fn delta_update_r_g2<E: Pairing, D: Digest>(
+    old_pk: &ProvingKey<E>,
+    new_pk: &ProvingKey<E>,
+    s_g1: &E::G1Affine,
+    s_delta_ratio_g1: &E::G1Affine,
+) -> crate::Result<E::G2> {
+    let mut seed = <ChaCha20Rng as SeedableRng>::Seed::default();
+    let output_size = <D as Digest>::output_size();
+    if output_size < seed.len() {
+        return Err(Error::DigestTooShort(output_size, seed.len()));
+    }
+
+    let mut bytes = Vec::new();
+    old_pk
+        .common
+        .delta_g1
+        .serialize_compressed(&mut bytes)
+        .unwrap();
+    new_pk
+        .common
+        .delta_g1
+        .serialize_compressed(&mut bytes)
+        .unwrap();
+    old_pk.vk.delta_g2.serialize_compressed(&mut bytes).unwrap();
+    new_pk.vk.delta_g2.serialize_compressed(&mut bytes).unwrap();
+    s_g1.serialize_compressed(&mut bytes).unwrap();
+    s_delta_ratio_g1.serialize_compressed(&mut bytes).unwrap();
+
+    let mut hasher = D::new();
+    hasher.update(DELTA_UPDATE_DST);
+    hasher.update(&bytes);
+    let digest = hasher.finalize();
+    let seed_len = seed.len();
+    seed.copy_from_slice(&digest[..seed_len]);
+    Ok(E::G2::rand(&mut ChaCha20Rng::from_seed(seed)))
+}
+
+impl<E: Pairing> VerifyingKey<E> {
+    /// The verifying key matching the proving key returned by [`ProvingKey::update_delta`] for the
+    /// same `delta_ratio`, i.e. with `delta_g2` multiplied by `delta_ratio`.
+    pub fn apply_delta_update(&self, delta_ratio: E::ScalarField) -> VerifyingKey<E> {
+        let mut vk = self.clone();
+        vk.delta_g2 = self.delta_g2.mul(delta_ratio).into_affine();
+        vk
+    }
+}
+
+impl<E: Pairing> ProvingKeyWithLink<E> {
+    /// Shrink the key to commit only to the first `new_count` of its committed witnesses, dropping
+    /// the bases of the others from `gamma_abc_g1` and `link_bases` and generating new CP-link keys
+    /// for the smaller matrix. The circuit keeps its variables, so the dropped witnesses must always
+    /// be zero, e.g. padding added with [`pad_committed_witnesses`](crate::pad_committed_witnesses),
+    /// which the provers check, giving `Error::UncommittedWitnessNotZero` otherwise. Returns
+    /// `Error::VectorLongerThanExpected` if `new_count` is more than the current
+    /// `commit_witness_count`.
+    pub fn truncate_commitment<R: Rng>(
+        &self,
+        new_count: usize,
+        rng: &mut R,
+    ) -> crate::Result<Self> {
+        self.vk.check_link_consistency()?;
+        let count = self.vk.groth16_vk.commit_witness_count as usize;
+        if new_count > count {
+            return Err(Error::VectorLongerThanExpected(new_count, count));
+        }
+
+        let mut groth16_vk = self.vk.groth16_vk.clone();
+        groth16_vk
+            .gamma_abc_g1
+            .truncate(groth16_vk.gamma_abc_g1.len() - (count - new_count));
+        groth16_vk.commit_witness_count = new_count as u32;
+
+        // The last base is for the randomness `link_v`
+        let mut link_bases = self.vk.link_bases[..new_count].to_vec();
+        link_bases.push(self.vk.link_bases[count]);
+
+        let mut vk = VerifyingKeyWithLink {
+            groth16_vk,
+            link_pp: PP::new(
+                self.vk.link_pp.l,
+                new_count + 2,
+                &self.vk.link_pp.g1,
+                &self.vk.link_pp.g2,
+            ),
+            link_bases,
+            link_vk: self.vk.link_vk.clone(),
+        };
+        let (link_ek, link_vk) = PESubspaceSnark::<E>::keygen(rng, &vk.link_pp, vk.link_matrix()?);
+        vk.link_vk = link_vk;
+
+        Ok(ProvingKeyWithLink {
+            vk,
+            common: self.common.clone(),
+            link_ek,
+        })
+    }
+}
+
+/// Run `oper_a` and `oper_b`, in parallel when the `parallel` feature is enabled.
+#[inline]
+pub(crate) fn join<A, B, RA, RB>(oper_a: A, oper_b: B) -> (RA, RB)
+where
+    A: FnOnce() -> RA + Send,
+    B: FnOnce() -> RB + Send,
+    RA: Send,
+    RB: Send,
+{
+    #[cfg(feature = "parallel")]
+    return rayon::join(oper_a, oper_b);
+    #[cfg(not(feature = "parallel"))]
+    return (oper_a(), oper_b());
 }
@@ -7,7 +7,7 @@
 #![forbid(unsafe_code)]
 
 /// Reduce an R1CS instance to a *Quadratic Arithmetic Program* instance.
-pub(crate) mod r1cs_to_qap;
+pub mod r1cs_to_qap;
 
 /// Data structures used by the prover, verifier, and generator.
 pub mod data_structures;
@@ -25,6 +25,10 @@ pub mod link;
 
 pub mod error;
 
+/// Import proving keys in the `.zkey` format of snarkjs.
+#[cfg(feature = "zkey")]
+pub mod zkey;
+
 /// Constraints for the Groth16 verifier.
 // Cannot yet create a LegoGroth16 gadget (for recursive proof) so commenting it out.
 // #[cfg(feature = "r1cs")]
@@ -35,6 +39,7 @@ pub type Result<T> = core::result::Result<T, error::Error>;
 mod test;
 
 pub use self::data_structures::*;
+pub use self::link::{PESubspaceSnark, SparseMatrix, SubspaceSnark, EK, PP, VK};
 pub use self::{generator::*, prover::*, verifier::*};
 
 use ark_std::vec::Vec;
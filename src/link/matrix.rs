@@ -6,9 +6,13 @@ use ark_std::vec;
 use ark_std::vec::Vec;
 
 use ark_ff::Zero;
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, Read, SerializationError, Valid, Validate,
+    Write,
+};
 
 /// CoeffPos: A struct to help build sparse matrices.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct CoeffPos<T> {
     val: T,
     pos: usize,
@@ -20,7 +24,7 @@ type Col<T> = Vec<CoeffPos<T>>;
 /* TODO: One could consider a cache-friendlier implementation for the 2-row case*/
 
 /// Column-Major Sparse Matrix
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct SparseMatrix<T> {
     cols: Vec<Col<T>>, // a vector of columns
     pub nr: usize,
@@ -53,15 +57,115 @@ impl<T: Copy> SparseMatrix<T> {
     pub fn get_col(&self, c: usize) -> &Col<T> {
         &self.cols[c]
     }
+
+    // materialize as a dense `nr x nc` grid (row-major) with `zero` in the empty cells
+    pub fn to_dense(&self, zero: T) -> Vec<Vec<T>> {
+        let mut dense = vec![vec![zero; self.nc]; self.nr];
+        for (c, col) in self.cols.iter().enumerate() {
+            for coeff_pos in col {
+                dense[coeff_pos.pos][c] = coeff_pos.val;
+            }
+        }
+        dense
+    }
 }
 
+// Serialized as `nr`, `nc` and the list of `(row, col, val)` triples, column by column. A matrix
+// with an empty column is rejected, as deserialization bounds `nc` by the number of entries.
+impl<T: CanonicalSerialize> CanonicalSerialize for SparseMatrix<T> {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        if self.cols.iter().any(|col| col.is_empty()) {
+            return Err(SerializationError::InvalidData);
+        }
+        (self.nr as u64).serialize_with_mode(&mut writer, compress)?;
+        (self.nc as u64).serialize_with_mode(&mut writer, compress)?;
+        let count = self.cols.iter().map(|col| col.len()).sum::<usize>();
+        (count as u64).serialize_with_mode(&mut writer, compress)?;
+        for (c, col) in self.cols.iter().enumerate() {
+            for coeff_pos in col {
+                (coeff_pos.pos as u64).serialize_with_mode(&mut writer, compress)?;
+                (c as u64).serialize_with_mode(&mut writer, compress)?;
+                coeff_pos.val.serialize_with_mode(&mut writer, compress)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        let entries = self
+            .cols
+            .iter()
+            .flatten()
+            .map(|coeff_pos| 16 + coeff_pos.val.serialized_size(compress))
+            .sum::<usize>();
+        24 + entries
+    }
+}
+
+impl<T: Valid> Valid for SparseMatrix<T> {
+    fn check(&self) -> Result<(), SerializationError> {
+        if self.cols.len() != self.nc {
+            return Err(SerializationError::InvalidData);
+        }
+        for coeff_pos in self.cols.iter().flatten() {
+            if coeff_pos.pos >= self.nr {
+                return Err(SerializationError::InvalidData);
+            }
+            coeff_pos.val.check()?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: CanonicalDeserialize + Copy> CanonicalDeserialize for SparseMatrix<T> {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let read_usize = |reader: &mut R| {
+            usize::try_from(u64::deserialize_with_mode(reader, compress, validate)?)
+                .map_err(|_| SerializationError::InvalidData)
+        };
+        let nr = read_usize(&mut reader)?;
+        let nc = read_usize(&mut reader)?;
+        let count = read_usize(&mut reader)?;
+        // Nothing is allocated from the untrusted `nc` and `count` before the entries are read, so
+        // the memory used is bounded by the length of the input
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            let pos = read_usize(&mut reader)?;
+            let c = read_usize(&mut reader)?;
+            let val = T::deserialize_with_mode(&mut reader, compress, validate)?;
+            if pos >= nr || c >= nc {
+                return Err(SerializationError::InvalidData);
+            }
+            entries.push((c, CoeffPos { val, pos }));
+        }
+        // Every column of a link matrix has an entry, so there can't be more columns than entries
+        if nc > entries.len() {
+            return Err(SerializationError::InvalidData);
+        }
+        let mut cols: Vec<Col<T>> = vec![vec![]; nc];
+        for (c, coeff_pos) in entries {
+            cols[c].push(coeff_pos);
+        }
+        Ok(SparseMatrix { cols, nr, nc })
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct SparseLinAlgebra<PE: Pairing> {
     pairing_engine_type: PhantomData<PE>,
 }
 
 impl<P: Pairing> SparseLinAlgebra<P> {
     // this is basically a multi-exp
-    pub fn sparse_inner_product(v: &Vec<P::ScalarField>, w: &Col<P::G1Affine>) -> P::G1Affine {
+    pub fn sparse_inner_product(v: &[P::ScalarField], w: &Col<P::G1Affine>) -> P::G1Affine {
         let mut res: P::G1 = P::G1::zero();
         for coeffpos in w {
             let g = coeffpos.val;
@@ -76,14 +180,14 @@ impl<P: Pairing> SparseLinAlgebra<P> {
     }
 
     pub fn sparse_vector_matrix_mult(
-        v: &Vec<P::ScalarField>,
+        v: &[P::ScalarField],
         m: &SparseMatrix<P::G1Affine>,
         t: usize,
     ) -> Vec<P::G1Affine> {
         // the result should contain every column of m multiplied by v
         let mut res: Vec<P::G1Affine> = Vec::with_capacity(t);
         for c in 0..m.nc {
-            res.push(Self::sparse_inner_product(&v, &m.get_col(c)));
+            res.push(Self::sparse_inner_product(v, m.get_col(c)));
         }
         res
     }
@@ -105,8 +209,8 @@ pub fn scalar_vector_mult<PE: Pairing>(
     l: usize,
 ) -> Vec<PE::ScalarField> {
     let mut res: Vec<PE::ScalarField> = Vec::with_capacity(l);
-    for i in 0..v.len() {
-        let x: PE::ScalarField = a.mul(&v[i]);
+    for v_i in v {
+        let x: PE::ScalarField = a.mul(v_i);
         res.push(x);
     }
     res
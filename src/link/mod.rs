@@ -6,7 +6,6 @@ mod snark;
 pub use matrix::*;
 pub use snark::*;
 
-
 #[cfg(test)]
 mod test {
     use super::{PESubspaceSnark, SparseMatrix, SubspaceSnark, PP};
@@ -14,7 +13,17 @@ mod test {
     use ark_ec::{AffineRepr, CurveGroup, Group};
     use ark_ff::{One, PrimeField, UniformRand, Zero};
     use ark_std::rand::{rngs::StdRng, SeedableRng};
-    use std::ops::Add;
+    use ark_std::{ops::Add, vec, vec::Vec};
+
+    #[test]
+    fn test_to_dense() {
+        let mut m = SparseMatrix::<u64>::new(2, 4);
+        m.insert_row_slice(0, 0, &[1, 2]);
+        m.insert_row_slice(1, 1, &[3, 4]);
+        m.insert_val(0, 3, &5);
+
+        assert_eq!(m.to_dense(0), vec![vec![1, 2, 0, 5], vec![0, 3, 4, 0]]);
+    }
 
     #[test]
     fn test_basic() {
@@ -23,7 +32,7 @@ mod test {
         let g1 = G1Projective::rand(&mut rng).into_affine();
         let g2 = G2Projective::rand(&mut rng).into_affine();
 
-        let mut pp = PP::<G1Affine, G2Affine> { l: 1, t: 2, g1, g2 };
+        let pp = PP::<G1Affine, G2Affine> { l: 1, t: 2, g1, g2 };
 
         let mut m = SparseMatrix::new(1, 2);
         m.insert_row_slice(0, 0, &[g1, g1]);
@@ -36,11 +45,26 @@ mod test {
 
         let (ek, vk) = PESubspaceSnark::<Bls12_381>::keygen(&mut rng, &pp, m);
 
-        let pi = PESubspaceSnark::<Bls12_381>::prove(&mut pp, &ek, &x);
-        let pi_bad = PESubspaceSnark::<Bls12_381>::prove(&mut pp, &ek, &x_bad);
+        let pi = PESubspaceSnark::<Bls12_381>::prove(&pp, &ek, &x);
+        let pi_bad = PESubspaceSnark::<Bls12_381>::prove(&pp, &ek, &x_bad);
 
         assert!(PESubspaceSnark::<Bls12_381>::verify(&pp, &vk, &y, &pi));
         assert!(!PESubspaceSnark::<Bls12_381>::verify(&pp, &vk, &y, &pi_bad));
+
+        // `y` with an element more than `vk.c`
+        let y_long: Vec<G1Affine> = vec![g1, G1Projective::rand(&mut rng).into_affine()];
+        assert!(!PESubspaceSnark::<Bls12_381>::verify(
+            &pp, &vk, &y_long, &pi
+        ));
+
+        // `pp` for more rows than `vk`, e.g. deserialized from untrusted input, doesn't panic
+        let pp_long = PP::<G1Affine, G2Affine> { l: 2, t: 2, g1, g2 };
+        assert!(!PESubspaceSnark::<Bls12_381>::verify(
+            &pp_long, &vk, &y, &pi
+        ));
+        assert!(!PESubspaceSnark::<Bls12_381>::verify(
+            &pp_long, &vk, &y_long, &pi
+        ));
     }
 
     #[test]
@@ -50,7 +74,7 @@ mod test {
         let g1 = G1Projective::rand(&mut rng).into_affine();
         let g2 = G2Projective::rand(&mut rng).into_affine();
 
-        let mut pp = PP::<G1Affine, G2Affine> { l: 1, t: 2, g1, g2 };
+        let pp = PP::<G1Affine, G2Affine> { l: 1, t: 2, g1, g2 };
 
         let h1 = G1Projective::rand(&mut rng).into_affine();
         let h2 = G1Projective::rand(&mut rng).into_affine();
@@ -74,8 +98,8 @@ mod test {
 
         let (ek, vk) = PESubspaceSnark::<Bls12_381>::keygen(&mut rng, &pp, m);
 
-        let pi = PESubspaceSnark::<Bls12_381>::prove(&mut pp, &ek, &w);
-        let pi_bad = PESubspaceSnark::<Bls12_381>::prove(&mut pp, &ek, &w_bad);
+        let pi = PESubspaceSnark::<Bls12_381>::prove(&pp, &ek, &w);
+        let pi_bad = PESubspaceSnark::<Bls12_381>::prove(&pp, &ek, &w_bad);
 
         assert!(PESubspaceSnark::<Bls12_381>::verify(&pp, &vk, &y, &pi));
         assert!(!PESubspaceSnark::<Bls12_381>::verify(&pp, &vk, &y, &pi_bad));
@@ -89,7 +113,7 @@ mod test {
         let g1 = G1Projective::rand(&mut rng).into_affine();
         let g2 = G2Projective::rand(&mut rng).into_affine();
 
-        let mut pp = PP::<G1Affine, G2Affine> { l: 2, t: 3, g1, g2 };
+        let pp = PP::<G1Affine, G2Affine> { l: 2, t: 3, g1, g2 };
 
         let bases1 = [G1Projective::rand(&mut rng), G1Projective::rand(&mut rng)]
             .iter()
@@ -118,7 +142,7 @@ mod test {
 
         let (ek, vk) = PESubspaceSnark::<Bls12_381>::keygen(&mut rng, &pp, m);
 
-        let pi = PESubspaceSnark::<Bls12_381>::prove(&mut pp, &ek, &w);
+        let pi = PESubspaceSnark::<Bls12_381>::prove(&pp, &ek, &w);
 
         PESubspaceSnark::<Bls12_381>::verify(&pp, &vk, &x, &pi);
     }
@@ -133,7 +157,7 @@ mod test {
 
         let l = 2;
         let t = 4;
-        let mut pp = PP::<G1Affine, G2Affine> { l, t, g1, g2 };
+        let pp = PP::<G1Affine, G2Affine> { l, t, g1, g2 };
 
         let bases1 = [
             G1Projective::rand(&mut rng),
@@ -152,7 +176,7 @@ mod test {
         .map(|p| p.into_affine())
         .collect::<Vec<_>>();
 
-        let mut m = SparseMatrix::new(l as usize, t as usize);
+        let mut m = SparseMatrix::new(l, t);
         m.insert_row_slice(0, 0, &bases1);
         m.insert_row_slice(1, 0, &bases2[0..2]);
         m.insert_row_slice(1, 3, &bases2[2..]);
@@ -178,7 +202,7 @@ mod test {
 
         let (ek, vk) = PESubspaceSnark::<Bls12_381>::keygen(&mut rng, &pp, m);
 
-        let pi = PESubspaceSnark::<Bls12_381>::prove(&mut pp, &ek, &w);
+        let pi = PESubspaceSnark::<Bls12_381>::prove(&pp, &ek, &w);
 
         PESubspaceSnark::<Bls12_381>::verify(&pp, &vk, &x, &pi);
     }
@@ -65,17 +65,49 @@ pub trait SubspaceSnark {
 
 fn vec_to_g2<P: Pairing>(
     pp: &PP<P::G1Affine, P::G2Affine>,
-    v: &Vec<P::ScalarField>,
+    v: &[P::ScalarField],
 ) -> Vec<P::G2Affine> {
     v.iter()
         .map(|x| pp.g2.mul(*x).into_affine())
         .collect::<Vec<_>>()
 }
 
+#[derive(Clone, Debug)]
 pub struct PESubspaceSnark<PE: Pairing> {
     pairing_engine_type: PhantomData<PE>,
 }
 
+impl<P: Pairing> PESubspaceSnark<P> {
+    /// Check that `ek` and `vk` were generated together by [`SubspaceSnark::keygen`] for the matrix
+    /// `m`, i.e. that they are for the same `k` and `a`. As `ek.p = k^T M`, `vk.c = a k` and
+    /// `vk.a = a g2`, each column `j` of `M` must satisfy `e(p_j, vk.a) = prod_i e(M_ij, c_i)`.
+    pub fn check_key_pair(
+        pp: &PP<P::G1Affine, P::G2Affine>,
+        ek: &EK<P::G1Affine>,
+        vk: &VK<P::G2Affine>,
+        m: &SparseMatrix<P::G1Affine>,
+    ) -> bool {
+        if m.nr != pp.l || m.nc != pp.t || ek.p.len() != pp.t || vk.c.len() != pp.l {
+            return false;
+        }
+        let neg_a = P::G2Prepared::from(vk.a.into_group().neg().into_affine());
+        let c =
+            vk.c.iter()
+                .map(|c_i| P::G2Prepared::from(*c_i))
+                .collect::<Vec<_>>();
+        let dense = m.to_dense(P::G1Affine::zero());
+        (0..pp.t).all(|j| {
+            let g1_elements = dense
+                .iter()
+                .map(|row| row[j])
+                .chain([ek.p[j]])
+                .collect::<Vec<_>>();
+            let g2_elements = c.iter().cloned().chain([neg_a.clone()]).collect::<Vec<_>>();
+            P::TargetField::one() == P::multi_pairing(g1_elements, g2_elements).0
+        })
+    }
+}
+
 // NB: Now the system is for y = Mx
 impl<P: Pairing> SubspaceSnark for PESubspaceSnark<P> {
     type KMtx = SparseMatrix<P::G1Affine>;
@@ -114,16 +146,19 @@ impl<P: Pairing> SubspaceSnark for PESubspaceSnark<P> {
     }
 
     fn verify(pp: &Self::PP, vk: &Self::VK, y: &[Self::OutVec], pi: &Self::Proof) -> bool {
-        assert_eq!(pp.l, y.len());
+        // Every element of `y` must be checked against its own `c_i`, so extra ones can't be ignored
+        if y.len() != vk.c.len() || y.len() != pp.l {
+            return false;
+        }
 
         // check that [x]1T · [C]2 = [π]1 · [a]2
 
         let mut g1_elements: Vec<<P as Pairing>::G1Prepared> = vec![];
         let mut g2_elements = vec![];
 
-        for i in 0..y.len() {
-            g1_elements.push(P::G1Prepared::from(y[i]));
-            g2_elements.push(P::G2Prepared::from(vk.c[i]));
+        for (y_i, c_i) in y.iter().zip(vk.c.iter()) {
+            g1_elements.push(P::G1Prepared::from(*y_i));
+            g2_elements.push(P::G2Prepared::from(*c_i));
         }
 
         g1_elements.push(P::G1Prepared::from(*pi));
@@ -1,41 +1,110 @@
 use crate::{
+    error::Error,
     link::{PESubspaceSnark, SubspaceSnark},
-    r1cs_to_qap::R1CStoQAP,
-    Proof, ProvingKey, ProvingKeyWithLink, ProofWithLink, ProvingKeyCommon, VerifyingKey,
+    r1cs_to_qap::{evaluate_constraint, R1CStoQAP},
+    verify_witness_commitment, CommitmentEqualityProof, PartialProof, PositionOpening,
+    PreparedLinkBases, Proof, ProofWithLink, ProverContext, ProvingKey, ProvingKeyCommon,
+    ProvingKeyWithLink, SharedCommitmentProof, VerifyingKey,
 };
-use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup, VariableBaseMSM};
-use ark_ff::{PrimeField, UniformRand, Zero};
-use ark_poly::GeneralEvaluationDomain;
+use ark_ec::{
+    pairing::Pairing, scalar_mul::fixed_base::FixedBase, AffineRepr, CurveGroup, VariableBaseMSM,
+};
+use ark_ff::{One, PrimeField, UniformRand, Zero};
+use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
 use ark_relations::r1cs::{
-    ConstraintSynthesizer, ConstraintSystem, OptimizationGoal, Result as R1CSResult,
+    ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef, OptimizationGoal,
+    Result as R1CSResult, SynthesisError,
 };
+use ark_serialize::CanonicalSerialize;
 use ark_std::rand::Rng;
-use ark_std::{cfg_into_iter, cfg_iter, end_timer, start_timer, vec::Vec};
+use ark_std::{cfg_iter, end_timer, start_timer, vec::Vec};
 use core::ops::{AddAssign, Mul};
+use digest::Digest;
 
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
 
 /// Create a LegoGroth16 proof that is zero-knowledge.
 /// This method samples randomness for zero knowledges via `rng`.
+///
+/// The commitment `proof.d` to the witnesses only hides them if `v` is uniformly random; with
+/// `v = 0` it is a deterministic function of the witnesses that anyone can check guesses against.
+/// A warning is emitted through `tracing` in that case, and [`create_random_proof_checked`] rejects
+/// it.
 #[inline]
 pub fn create_random_proof<E, C, R>(
     circuit: C,
     v: E::ScalarField,
     pk: &ProvingKey<E>,
     rng: &mut R,
-) -> R1CSResult<Proof<E>>
+) -> crate::Result<Proof<E>>
+where
+    E: Pairing,
+    C: ConstraintSynthesizer<E::ScalarField>,
+    R: Rng,
+{
+    if v.is_zero() {
+        tracing::warn!("creating a LegoGroth16 proof with v = 0, its commitment `d` is not hiding");
+    }
+
+    let r = E::ScalarField::rand(rng);
+    let s = E::ScalarField::rand(rng);
+
+    create_proof::<E, C>(circuit, &pk.common, &pk.vk, r, s, v)
+}
+
+/// Same as [`create_random_proof`] but returns `Error::ZeroRandomness` instead of a proof that is
+/// not zero-knowledge: if `v` is zero, `proof.d` does not hide the committed witnesses, and if the
+/// sampled `r` or `s` is zero, which only happens with a broken `rng`, `A`, `B` and `C` do not hide
+/// the witnesses either.
+pub fn create_random_proof_checked<E, C, R>(
+    circuit: C,
+    v: E::ScalarField,
+    pk: &ProvingKey<E>,
+    rng: &mut R,
+) -> crate::Result<Proof<E>>
 where
     E: Pairing,
     C: ConstraintSynthesizer<E::ScalarField>,
     R: Rng,
 {
+    if v.is_zero() {
+        return Err(Error::ZeroRandomness("v"));
+    }
     let r = E::ScalarField::rand(rng);
     let s = E::ScalarField::rand(rng);
+    if r.is_zero() {
+        return Err(Error::ZeroRandomness("r"));
+    }
+    if s.is_zero() {
+        return Err(Error::ZeroRandomness("s"));
+    }
 
     create_proof::<E, C>(circuit, &pk.common, &pk.vk, r, s, v)
 }
 
+/// Create a LegoGroth16 proof like [`create_random_proof`] and check that the commitment `proof.d`
+/// opens to `committed_witnesses` with randomness `v` before returning it. Since the check needs the
+/// committed witnesses, this is only meant to be called by the prover.
+pub fn create_and_self_check_proof<E, C, R>(
+    circuit: C,
+    committed_witnesses: &[E::ScalarField],
+    public_inputs: &[E::ScalarField],
+    v: E::ScalarField,
+    pk: &ProvingKey<E>,
+    rng: &mut R,
+) -> crate::Result<Proof<E>>
+where
+    E: Pairing,
+    C: ConstraintSynthesizer<E::ScalarField>,
+    R: Rng,
+{
+    let proof = create_random_proof(circuit, v, pk, rng)?;
+    verify_witness_commitment(&pk.vk, &proof, public_inputs.len(), committed_witnesses, &v)
+        .map_err(|_| Error::InvalidWitnessCommitment)?;
+    Ok(proof)
+}
+
 /// Create a LegoGroth16 proof with CP-link that is zero-knowledge.
 /// This method samples randomness for zero knowledges via `rng`.
 /// method take link_v for CP-link
@@ -47,7 +116,7 @@ pub fn create_random_proof_with_link<E, C, R>(
     pk: &ProvingKeyWithLink<E>,
     witnesses: &[E::ScalarField],
     rng: &mut R,
-) -> R1CSResult<ProofWithLink<E>>
+) -> crate::Result<ProofWithLink<E>>
 where
     E: Pairing,
     C: ConstraintSynthesizer<E::ScalarField>,
@@ -58,17 +127,62 @@ where
 
     let proof = create_proof::<E, C>(circuit, &pk.common, &pk.vk.groth16_vk, r, s, v)?;
 
-    // CP-link part 
+    // CP-link part
     let mut w_with_link_v = cfg_iter!(witnesses)
         .map(|w| w.into_bigint())
         .collect::<Vec<_>>();
     w_with_link_v.push(link_v.into_bigint());
 
     let g_d_link = E::G1::msm_bigint(&pk.vk.link_bases, &w_with_link_v);
+    drop(w_with_link_v);
 
-    let mut ss_snark_witness = cfg_iter!(witnesses)
-        .map(|w| w.clone())
-        .collect::<Vec<_>>();
+    Ok(add_link_proof(proof, g_d_link, v, link_v, pk, witnesses))
+}
+
+/// Same as [`create_random_proof_with_link`] but computes the link commitment `link_d` with the
+/// window tables of `prepared`, as returned by [`ProvingKeyWithLink::prepare_link_bases`] for `pk`,
+/// which is faster when creating many proofs. `witnesses` must have a value for each base but the
+/// last one.
+pub fn create_random_proof_with_link_prepared<E, C, R>(
+    circuit: C,
+    v: E::ScalarField,
+    link_v: E::ScalarField,
+    pk: &ProvingKeyWithLink<E>,
+    prepared: &PreparedLinkBases<E>,
+    witnesses: &[E::ScalarField],
+    rng: &mut R,
+) -> crate::Result<ProofWithLink<E>>
+where
+    E: Pairing,
+    C: ConstraintSynthesizer<E::ScalarField>,
+    R: Rng,
+{
+    if witnesses.len() + 1 != prepared.tables.len() {
+        return Err(SynthesisError::MalformedVerifyingKey.into());
+    }
+    let r = E::ScalarField::rand(rng);
+    let s = E::ScalarField::rand(rng);
+
+    let proof = create_proof::<E, C>(circuit, &pk.common, &pk.vk.groth16_vk, r, s, v)?;
+
+    let mut w_with_link_v = witnesses.to_vec();
+    w_with_link_v.push(link_v);
+    let g_d_link = windowed_commitment::<E>(prepared.window, &prepared.tables, &w_with_link_v);
+
+    Ok(add_link_proof(proof, g_d_link, v, link_v, pk, witnesses))
+}
+
+/// Complete `proof` into a [`ProofWithLink`] with the link commitment `g_d_link` to `witnesses` and
+/// randomness `link_v`.
+fn add_link_proof<E: Pairing>(
+    proof: Proof<E>,
+    g_d_link: E::G1,
+    v: E::ScalarField,
+    link_v: E::ScalarField,
+    pk: &ProvingKeyWithLink<E>,
+    witnesses: &[E::ScalarField],
+) -> ProofWithLink<E> {
+    let mut ss_snark_witness = cfg_iter!(witnesses).map(|w| *w).collect::<Vec<_>>();
     ss_snark_witness.push(link_v);
     ss_snark_witness.push(v);
 
@@ -77,15 +191,424 @@ where
 
     end_timer!(link_time);
 
-    drop(w_with_link_v);
     drop(ss_snark_witness);
 
-    Ok(ProofWithLink {
+    ProofWithLink {
         groth16_proof: proof,
         link_d: g_d_link.into_affine(),
         link_pi,
+    }
+}
+
+impl<E: Pairing> ProvingKeyWithLink<E> {
+    /// Build the window tables of the commitment key `vk.link_bases` of cp_link for
+    /// [`create_random_proof_with_link_prepared`].
+    pub fn prepare_link_bases(&self) -> PreparedLinkBases<E> {
+        let scalar_bits = E::ScalarField::MODULUS_BIT_SIZE as usize;
+        let tables = cfg_iter!(self.vk.link_bases)
+            .map(|b| {
+                FixedBase::get_window_table::<E::G1>(
+                    scalar_bits,
+                    PreparedLinkBases::<E>::WINDOW,
+                    b.into_group(),
+                )
+            })
+            .collect::<Vec<_>>();
+        PreparedLinkBases {
+            window: PreparedLinkBases::<E>::WINDOW,
+            tables,
+        }
+    }
+}
+
+impl<E: Pairing> PreparedLinkBases<E> {
+    /// Window size of the tables, larger than the one of [`LinkCommitmentVerifier`] as the tables
+    /// are meant to be kept for the lifetime of the proving key.
+    ///
+    /// [`LinkCommitmentVerifier`]: crate::LinkCommitmentVerifier
+    pub const WINDOW: usize = 8;
+}
+
+/// The commitment `sum_i scalars_i * base_i`, where `tables` are the window tables of the bases for
+/// `window`. Extra tables are ignored.
+pub(crate) fn windowed_commitment<E: Pairing>(
+    window: usize,
+    tables: &[Vec<Vec<E::G1Affine>>],
+    scalars: &[E::ScalarField],
+) -> E::G1 {
+    let scalar_bits = E::ScalarField::MODULUS_BIT_SIZE as usize;
+    let outerc = scalar_bits.div_ceil(window);
+    cfg_iter!(scalars)
+        .zip(cfg_iter!(tables))
+        .map(|(s, table)| FixedBase::windowed_mul::<E::G1>(outerc, window, table, s))
+        .sum::<E::G1>()
+}
+
+/// Compute the commitment `proof.d` to `committed_witnesses` with randomness `v` like
+/// [`expected_proof_d`](crate::expected_proof_d) but without normalizing it, so that the
+/// commitments of many proofs can be normalized together with [`batch_finalize_d`].
+pub fn create_d_projective<E: Pairing>(
+    vk: &VerifyingKey<E>,
+    public_inputs_count: usize,
+    committed_witnesses: &[E::ScalarField],
+    v: &E::ScalarField,
+) -> crate::Result<E::G1> {
+    create_d_projective_with_eta(
+        vk,
+        public_inputs_count,
+        committed_witnesses,
+        v,
+        &vk.eta_gamma_inv_g1,
+    )
+}
+
+/// Like [`create_d_projective`] but the randomness `v` is multiplied with `eta_gamma_inv_g1`
+/// rather than `vk.eta_gamma_inv_g1`.
+pub(crate) fn create_d_projective_with_eta<E: Pairing>(
+    vk: &VerifyingKey<E>,
+    public_inputs_count: usize,
+    committed_witnesses: &[E::ScalarField],
+    v: &E::ScalarField,
+    eta_gamma_inv_g1: &E::G1Affine,
+) -> crate::Result<E::G1> {
+    let (bases, _) = vk.commitment_bases(public_inputs_count, committed_witnesses.len())?;
+    let committed = cfg_iter!(committed_witnesses)
+        .map(|p| p.into_bigint())
+        .collect::<Vec<_>>();
+
+    let mut d = E::G1::msm_bigint(bases, &committed);
+    d.add_assign(&eta_gamma_inv_g1.mul_bigint(v.into_bigint()));
+    Ok(d)
+}
+
+/// Set the commitment `d` of each of `proofs` to the matching element of `ds`, e.g. as returned by
+/// [`create_d_projective`], normalizing all of them at once. Panics if the lengths differ.
+pub fn batch_finalize_d<E: Pairing>(proofs: &mut [Proof<E>], ds: &[E::G1]) {
+    assert_eq!(proofs.len(), ds.len());
+    for (proof, d) in proofs.iter_mut().zip(E::G1::normalize_batch(ds)) {
+        proof.d = d;
+    }
+}
+
+/// Domain separation tag used by [`derive_commitment_randomness`].
+const COMMITMENT_RANDOMNESS_DST: &[u8] = b"LegoGroth16-commitment-randomness";
+
+/// Derive the randomness `(v, link_v)` for the commitments `proof.d` and `link_d` deterministically
+/// from the statement, i.e. `public_inputs`, and `link_d_preimage`, the witnesses that `link_d`
+/// commits to. Useful when the same statement must always produce the same commitments.
+///
+/// Security: each output is `D(tag || index || block || public_inputs || link_d_preimage)` for the
+/// blocks `0, 1, ...` needed to get at least 128 bits more than the field modulus, concatenated and
+/// reduced modulo the field order, so `D` must behave like a random oracle but may have any output
+/// length. Reducing only as many bits as the modulus has would make some values much likelier than
+/// others. The commitments are only hiding as long as `link_d_preimage` has enough entropy to be
+/// unguessable by the verifier; if the committed witnesses can be brute-forced, a verifier can
+/// recompute `v` and `link_v` and check guesses, so sample the randomness with [`UniformRand`]
+/// instead.
+pub fn derive_commitment_randomness<E: Pairing, D: Digest>(
+    public_inputs: &[E::ScalarField],
+    link_d_preimage: &[E::ScalarField],
+) -> (E::ScalarField, E::ScalarField) {
+    // Slices are serialized with their length so the boundary between inputs and witnesses is fixed
+    let mut bytes = Vec::new();
+    public_inputs.serialize_compressed(&mut bytes).unwrap();
+    link_d_preimage.serialize_compressed(&mut bytes).unwrap();
+
+    let output_size = <D as Digest>::output_size();
+    assert!(output_size > 0, "the digest has an empty output");
+    // 128 bits more than the modulus keep the bias of the reduction below 2^-128
+    let wide_len = (E::ScalarField::MODULUS_BIT_SIZE as usize + 128).div_ceil(8);
+    let blocks = wide_len.div_ceil(output_size);
+    let derive = |index: u8| {
+        let mut wide = Vec::with_capacity(blocks * output_size);
+        for block in 0..blocks {
+            let mut hasher = D::new();
+            hasher.update(COMMITMENT_RANDOMNESS_DST);
+            hasher.update([index]);
+            hasher.update((block as u32).to_le_bytes());
+            hasher.update(&bytes);
+            wide.extend_from_slice(&hasher.finalize());
+        }
+        assert!(wide.len() * 8 >= E::ScalarField::MODULUS_BIT_SIZE as usize + 128);
+        E::ScalarField::from_le_bytes_mod_order(&wide)
+    };
+    (derive(0), derive(1))
+}
+
+/// Opening `(proof, committed_witnesses, v)` of the commitment `proof.d` of a proof.
+pub type CommitmentOpening<'a, E> = (
+    &'a Proof<E>,
+    &'a [<E as Pairing>::ScalarField],
+    <E as Pairing>::ScalarField,
+);
+
+/// Domain separation tag of the challenge of a [`SharedCommitmentProof`].
+const SHARED_COMMITMENT_DST: &[u8] = b"LegoGroth16-shared-commitment";
+
+/// Prove that the proofs of `opening1` and `opening2`, for the same verifying key `vk` and both with
+/// `public_inputs_count` public inputs, commit in `d` to the same witnesses at the positions
+/// `shared_indices` of the committed witnesses. The other witnesses and the randomness stay hidden.
+/// The proof is made non-interactive with the hash function `D`, which must be the one given to
+/// [`verify_shared_commitment`](crate::verify_shared_commitment).
+pub fn create_shared_commitment_proof<E: Pairing, D: Digest, R: Rng>(
+    vk: &VerifyingKey<E>,
+    public_inputs_count: usize,
+    shared_indices: &[usize],
+    opening1: CommitmentOpening<'_, E>,
+    opening2: CommitmentOpening<'_, E>,
+    rng: &mut R,
+) -> crate::Result<SharedCommitmentProof<E>> {
+    let (proof1, witnesses1, v1) = opening1;
+    let (proof2, witnesses2, v2) = opening2;
+    let (bases, non_shared) = shared_commitment_bases(vk, public_inputs_count, shared_indices)?;
+    let count = vk.commit_witness_count as usize;
+    for witnesses in [witnesses1, witnesses2] {
+        if witnesses.len() > count {
+            return Err(Error::VectorLongerThanExpected(witnesses.len(), count));
+        }
+        if witnesses.len() < count {
+            return Err(Error::InsufficientWitnessesForCommitment(
+                witnesses.len(),
+                count,
+            ));
+        }
+    }
+
+    // Opening of `d1 - d2` over `bases`
+    let mut differences = non_shared
+        .iter()
+        .map(|i| witnesses1[*i] - witnesses2[*i])
+        .collect::<Vec<_>>();
+    differences.push(v1 - v2);
+
+    let blindings = (0..bases.len())
+        .map(|_| E::ScalarField::rand(rng))
+        .collect::<Vec<_>>();
+    let t = E::G1::msm_unchecked(&bases, &blindings).into_affine();
+    let challenge = shared_commitment_challenge::<E, D>(
+        public_inputs_count,
+        shared_indices,
+        &bases,
+        &proof1.d,
+        &proof2.d,
+        &t,
+    );
+    let responses = blindings
+        .iter()
+        .zip(differences)
+        .map(|(blinding, difference)| *blinding + challenge * difference)
+        .collect();
+    Ok(SharedCommitmentProof { t, responses })
+}
+
+/// Bases of the witnesses of the committed block of `vk` not in `shared_indices`, followed by
+/// `eta_gamma_inv_g1`, together with the positions of these witnesses.
+pub(crate) fn shared_commitment_bases<E: Pairing>(
+    vk: &VerifyingKey<E>,
+    public_inputs_count: usize,
+    shared_indices: &[usize],
+) -> crate::Result<(Vec<E::G1Affine>, Vec<usize>)> {
+    let count = vk.commit_witness_count as usize;
+    if let Some(index) = shared_indices.iter().find(|i| **i >= count) {
+        return Err(Error::CommittedWitnessIndexOutOfRange(*index, count));
+    }
+    if public_inputs_count != vk.expected_public_input_count() {
+        return Err(SynthesisError::MalformedVerifyingKey.into());
+    }
+    let (committed, eta) = vk.commitment_bases(public_inputs_count, count)?;
+    let non_shared = (0..count)
+        .filter(|i| !shared_indices.contains(i))
+        .collect::<Vec<_>>();
+    let mut bases = non_shared.iter().map(|i| committed[*i]).collect::<Vec<_>>();
+    bases.push(eta);
+    Ok((bases, non_shared))
+}
+
+/// Challenge of a [`SharedCommitmentProof`] with blinding commitment `t` for the commitments `d1`
+/// and `d2`, whose difference is opened over `bases` as given by [`shared_commitment_bases`].
+pub(crate) fn shared_commitment_challenge<E: Pairing, D: Digest>(
+    public_inputs_count: usize,
+    shared_indices: &[usize],
+    bases: &[E::G1Affine],
+    d1: &E::G1Affine,
+    d2: &E::G1Affine,
+    t: &E::G1Affine,
+) -> E::ScalarField {
+    let mut bytes = Vec::new();
+    (public_inputs_count as u64)
+        .serialize_compressed(&mut bytes)
+        .unwrap();
+    shared_indices
+        .iter()
+        .map(|i| *i as u64)
+        .collect::<Vec<_>>()
+        .serialize_compressed(&mut bytes)
+        .unwrap();
+    bases.serialize_compressed(&mut bytes).unwrap();
+    d1.serialize_compressed(&mut bytes).unwrap();
+    d2.serialize_compressed(&mut bytes).unwrap();
+    t.serialize_compressed(&mut bytes).unwrap();
+
+    let mut hasher = D::new();
+    hasher.update(SHARED_COMMITMENT_DST);
+    hasher.update(&bytes);
+    E::ScalarField::from_le_bytes_mod_order(&hasher.finalize())
+}
+
+/// Domain separation tag of the challenge of a [`CommitmentEqualityProof`].
+const COMMITMENT_EQUALITY_DST: &[u8] = b"LegoGroth16-commitment-equality";
+
+/// Prove that the cp_link commitments `link_d1`, with randomness `link_v1`, and `link_d2`, with
+/// randomness `link_v2`, commit to the same witnesses under `bases`, which has a base for each
+/// committed witness followed by the base of the randomness, e.g. `vk.link_bases` of a
+/// [`VerifyingKeyWithLink`](crate::VerifyingKeyWithLink). The witnesses and the randomness stay
+/// hidden. The proof is made non-interactive with the hash function `D`, which must be the one given
+/// to [`verify_commitment_equality`](crate::verify_commitment_equality). Returns
+/// `Error::EmptyPedersenBases` if `bases` is empty.
+pub fn prove_commitment_equality<E: Pairing, D: Digest, R: Rng>(
+    link_d1: &E::G1Affine,
+    link_d2: &E::G1Affine,
+    link_v1: &E::ScalarField,
+    link_v2: &E::ScalarField,
+    bases: &[E::G1Affine],
+    rng: &mut R,
+) -> crate::Result<CommitmentEqualityProof<E>> {
+    let blinding_base = bases.last().ok_or(Error::EmptyPedersenBases)?;
+    let blinding = E::ScalarField::rand(rng);
+    let t = blinding_base
+        .mul_bigint(blinding.into_bigint())
+        .into_affine();
+    let challenge = commitment_equality_challenge::<E, D>(blinding_base, link_d1, link_d2, &t);
+    Ok(CommitmentEqualityProof {
+        t,
+        response: blinding + challenge * (*link_v1 - link_v2),
     })
+}
+
+/// Challenge of a [`CommitmentEqualityProof`] with blinding commitment `t` for the commitments
+/// `link_d1` and `link_d2` with randomness under `blinding_base`.
+pub(crate) fn commitment_equality_challenge<E: Pairing, D: Digest>(
+    blinding_base: &E::G1Affine,
+    link_d1: &E::G1Affine,
+    link_d2: &E::G1Affine,
+    t: &E::G1Affine,
+) -> E::ScalarField {
+    let mut bytes = Vec::new();
+    blinding_base.serialize_compressed(&mut bytes).unwrap();
+    link_d1.serialize_compressed(&mut bytes).unwrap();
+    link_d2.serialize_compressed(&mut bytes).unwrap();
+    t.serialize_compressed(&mut bytes).unwrap();
 
+    let mut hasher = D::new();
+    hasher.update(COMMITMENT_EQUALITY_DST);
+    hasher.update(&bytes);
+    E::ScalarField::from_le_bytes_mod_order(&hasher.finalize())
+}
+
+/// Domain separation tag of the challenge of a [`PositionOpening`].
+const POSITION_OPENING_DST: &[u8] = b"LegoGroth16-position-opening";
+
+/// Open the commitment `d` to `committed_witnesses` with randomness `v`, for `vk` and with
+/// `public_inputs_count` public inputs, at `position`, i.e. prove that the witness there is
+/// `committed_witnesses[position]` while the other witnesses and `v` stay hidden. The opening is
+/// made non-interactive with the hash function `D`, which must be the one given to
+/// [`verify_position_opening`](crate::verify_position_opening).
+pub fn open_position<E: Pairing, D: Digest, R: Rng>(
+    vk: &VerifyingKey<E>,
+    public_inputs_count: usize,
+    committed_witnesses: &[E::ScalarField],
+    position: usize,
+    v: &E::ScalarField,
+    rng: &mut R,
+) -> crate::Result<PositionOpening<E>> {
+    let count = vk.commit_witness_count as usize;
+    if committed_witnesses.len() > count {
+        return Err(Error::VectorLongerThanExpected(
+            committed_witnesses.len(),
+            count,
+        ));
+    }
+    if committed_witnesses.len() < count {
+        return Err(Error::InsufficientWitnessesForCommitment(
+            committed_witnesses.len(),
+            count,
+        ));
+    }
+    let (bases, others) = shared_commitment_bases(vk, public_inputs_count, &[position])?;
+    let (committed, _) = vk.commitment_bases(public_inputs_count, count)?;
+    let d = create_d_projective(vk, public_inputs_count, committed_witnesses, v)?.into_affine();
+
+    // Opening of `d - value * base` over `bases`
+    let mut hidden = others
+        .iter()
+        .map(|i| committed_witnesses[*i])
+        .collect::<Vec<_>>();
+    hidden.push(*v);
+
+    let blindings = (0..bases.len())
+        .map(|_| E::ScalarField::rand(rng))
+        .collect::<Vec<_>>();
+    let t = E::G1::msm_unchecked(&bases, &blindings).into_affine();
+    let challenge = position_opening_challenge::<E, D>(
+        public_inputs_count,
+        position,
+        &committed[position],
+        &bases,
+        &committed_witnesses[position],
+        &d,
+        &t,
+    );
+    let responses = blindings
+        .iter()
+        .zip(hidden)
+        .map(|(blinding, witness)| *blinding + challenge * witness)
+        .collect();
+    Ok(PositionOpening { t, responses })
+}
+
+/// Challenge of a [`PositionOpening`] of `value` at `position`, whose base is `position_base`, with
+/// blinding commitment `t` for the commitment `d`. The rest of `d` is opened over `bases` as given
+/// by [`shared_commitment_bases`].
+pub(crate) fn position_opening_challenge<E: Pairing, D: Digest>(
+    public_inputs_count: usize,
+    position: usize,
+    position_base: &E::G1Affine,
+    bases: &[E::G1Affine],
+    value: &E::ScalarField,
+    d: &E::G1Affine,
+    t: &E::G1Affine,
+) -> E::ScalarField {
+    let mut bytes = Vec::new();
+    (public_inputs_count as u64)
+        .serialize_compressed(&mut bytes)
+        .unwrap();
+    (position as u64).serialize_compressed(&mut bytes).unwrap();
+    position_base.serialize_compressed(&mut bytes).unwrap();
+    bases.serialize_compressed(&mut bytes).unwrap();
+    value.serialize_compressed(&mut bytes).unwrap();
+    d.serialize_compressed(&mut bytes).unwrap();
+    t.serialize_compressed(&mut bytes).unwrap();
+
+    let mut hasher = D::new();
+    hasher.update(POSITION_OPENING_DST);
+    hasher.update(&bytes);
+    E::ScalarField::from_le_bytes_mod_order(&hasher.finalize())
+}
+
+/// Pad `witnesses` with zeros to `block_size`, the number of witnesses committed by the proving key
+/// (`vk.commit_witness_count`). Padding with zeros does not change the meaning of the commitment, as
+/// zero witnesses add nothing to it, but the circuit must assign zero to the padded witnesses.
+pub fn pad_committed_witnesses<F: PrimeField>(
+    witnesses: &[F],
+    block_size: usize,
+) -> crate::Result<Vec<F>> {
+    if witnesses.len() > block_size {
+        return Err(Error::VectorLongerThanExpected(witnesses.len(), block_size));
+    }
+    let mut padded = Vec::with_capacity(block_size);
+    padded.extend_from_slice(witnesses);
+    padded.resize(block_size, F::zero());
+    Ok(padded)
 }
 
 /// Create a Groth16 proof using randomness `r` and `s`.
@@ -97,14 +620,11 @@ pub fn create_proof<E, C>(
     r: E::ScalarField,
     s: E::ScalarField,
     v: E::ScalarField,
-) -> R1CSResult<Proof<E>>
+) -> crate::Result<Proof<E>>
 where
     E: Pairing,
     C: ConstraintSynthesizer<E::ScalarField>,
 {
-    type D<F> = GeneralEvaluationDomain<F>;
-
-    let prover_time = start_timer!(|| "Groth16::Prover");
     let cs = ConstraintSystem::new_ref();
 
     // Set the optimization goal
@@ -120,68 +640,434 @@ where
     cs.finalize();
     end_timer!(lc_time);
 
-    let witness_map_time = start_timer!(|| "R1CS to QAP witness map");
+    create_proof_with_cs(cs, pk_common, vk, r, s, v)
+}
 
-    let h = R1CStoQAP::witness_map::<E::ScalarField, D<E::ScalarField>>(cs.clone())?;
-    end_timer!(witness_map_time);
+/// Same as [`create_proof`] but stops short of applying the commitment randomness `v`, so that
+/// [`finalize_proof`] can then create proofs for many values of `v` from a single synthesis, witness
+/// map and set of MSMs. All these proofs share `A` and `B`, and so are linkable to each other.
+pub fn create_proof_reusable<E, C>(
+    circuit: C,
+    pk_common: &ProvingKeyCommon<E>,
+    vk: &VerifyingKey<E>,
+    r: E::ScalarField,
+    s: E::ScalarField,
+) -> crate::Result<(PartialProof<E>, ProverContext<E>)>
+where
+    E: Pairing,
+    C: ConstraintSynthesizer<E::ScalarField>,
+{
+    // With `v = 0`, `C` and `D` are exactly the parts that do not depend on `v`
+    let proof = create_proof(circuit, pk_common, vk, r, s, E::ScalarField::zero())?;
+    Ok((
+        PartialProof {
+            a: proof.a,
+            b: proof.b,
+        },
+        ProverContext {
+            c_acc: proof.c.into_group(),
+            d_acc: proof.d.into_group(),
+            eta_delta_inv_g1: pk_common.eta_delta_inv_g1,
+            eta_gamma_inv_g1: vk.eta_gamma_inv_g1,
+        },
+    ))
+}
 
-    let h_assignment = cfg_into_iter!(h)
-        .map(|s| s.into())
-        .collect::<Vec<E::ScalarField>>();
+/// Complete the proof created by [`create_proof_reusable`] with the commitment randomness `v`. This
+/// costs a scalar multiplication for each of `C` and `D`.
+pub fn finalize_proof<E: Pairing>(
+    partial: &PartialProof<E>,
+    ctx: &ProverContext<E>,
+    v: E::ScalarField,
+) -> Proof<E> {
+    let c = ctx.c_acc - ctx.eta_delta_inv_g1.mul(v);
+    let d = ctx.d_acc + ctx.eta_gamma_inv_g1.mul(v);
+    let [c, d] = <[E::G1Affine; 2]>::try_from(E::G1::normalize_batch(&[c, d])).unwrap();
+    Proof {
+        a: partial.a,
+        b: partial.b,
+        c,
+        d,
+    }
+}
 
-    let c_acc_time = start_timer!(|| "Compute C");
+/// Same as [`create_proof`] but computes `C` with a single MSM over `g1_queries`, the output of
+/// [`ProvingKeyCommon::concat_g1_queries`] for `pk_common`, which suits accelerators that prefer
+/// one large MSM over several small ones. `A`, `B` and `D` are computed as in [`create_proof`].
+pub fn create_proof_with_concat_queries<E, C>(
+    circuit: C,
+    pk_common: &ProvingKeyCommon<E>,
+    vk: &VerifyingKey<E>,
+    g1_queries: &[E::G1Affine],
+    r: E::ScalarField,
+    s: E::ScalarField,
+    v: E::ScalarField,
+) -> crate::Result<Proof<E>>
+where
+    E: Pairing,
+    C: ConstraintSynthesizer<E::ScalarField>,
+{
+    let ranges = pk_common.g1_query_ranges();
+    if g1_queries.len() != ranges.l.end {
+        return Err(SynthesisError::MalformedVerifyingKey.into());
+    }
 
-    let h_acc = <<E as Pairing>::G1>::msm_unchecked(&pk_common.h_query, &h_assignment);
+    let cs = ConstraintSystem::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+    circuit.generate_constraints(cs.clone())?;
+    debug_assert!(cs.is_satisfied().unwrap());
+    cs.finalize();
 
-    drop(h_assignment);
+    let prover_time = start_timer!(|| "Groth16::Prover with concatenated queries");
+    let h = checked_witness_map(cs.clone(), pk_common)?;
 
-    // Compute C
     let prover = cs.borrow().unwrap();
-    let aux_assignment = cfg_iter!(prover.witness_assignment)
+    let num_inputs = prover.instance_assignment.len();
+    let num_witnesses = prover.witness_assignment.len();
+    let field_assignment = [
+        &prover.instance_assignment[1..],
+        prover.witness_assignment.as_slice(),
+    ]
+    .concat();
+    drop(prover);
+    drop(cs);
+
+    let assignment = cfg_iter!(field_assignment)
         .map(|s| s.into_bigint())
         .collect::<Vec<_>>();
+    // The witnesses are committed in `D`, except those dropped from a truncated key, which are zero
+    let committed_count = vk.commit_witness_count as usize;
+    if committed_count > num_witnesses {
+        return Err(SynthesisError::MalformedVerifyingKey.into());
+    }
+    check_uncommitted_witnesses(&field_assignment[num_inputs - 1..], committed_count)?;
+    let committed_witnesses = &assignment[num_inputs - 1..num_inputs - 1 + committed_count];
+    let (committed_bases, _) = vk.commitment_bases(num_inputs - 1, committed_count)?;
 
-    let committed_witnesses = &aux_assignment[..prover.witness_assignment.len() as usize];
-    let uncommitted_witnesses = &aux_assignment[prover.witness_assignment.len() as usize..];
+    let g_a = compute_a::<E>(
+        &pk_common.a_query,
+        vk.alpha_g1,
+        pk_common.delta_g1,
+        r,
+        &assignment,
+    );
+    let g2_b = compute_b_g2::<E>(
+        &pk_common.b_g2_query,
+        vk.beta_g2,
+        vk.delta_g2,
+        s,
+        &assignment,
+    );
+    let g_d = compute_d::<E>(committed_bases, vk.eta_gamma_inv_g1, v, committed_witnesses);
 
-    let l_aux_acc = <E::G1 as VariableBaseMSM>::msm_bigint(&pk_common.l_query, &uncommitted_witnesses);
+    // C = s * A + r * B_1 - r * s * delta + L + H - v * eta / delta. The query terms of all of them
+    // are in the MSM and the rest is what `compute_c` gives for `A` and `B_1` without query terms
+    let c_time = start_timer!(|| "Compute C with one MSM");
+    let scaled_assignment = |k: E::ScalarField, len: usize| {
+        let mut scalars = Vec::with_capacity(len);
+        scalars.push(k);
+        scalars.extend(field_assignment.iter().map(|x| k * x));
+        scalars.resize(len, E::ScalarField::zero());
+        scalars
+    };
+    let mut scalars = scaled_assignment(s, ranges.a.len());
+    scalars.append(&mut scaled_assignment(r, ranges.b_g1.len()));
+    scalars.extend(h.iter().take(ranges.h.len()));
+    scalars.resize(ranges.l.start, E::ScalarField::zero());
+    // No witness goes with `l_query` as they are all either committed in `D` or zero
+    scalars.resize(ranges.l.end, E::ScalarField::zero());
 
-    let r_s_delta_g1 = pk_common.delta_g1.into_group().mul(r).mul(s);
-    let v_eta_delta_inv = pk_common.eta_delta_inv_g1.into_group().mul(v);
+    let g_a_without_queries = vk.alpha_g1 + pk_common.delta_g1.mul(r);
+    let g1_b_without_queries = pk_common.beta_g1 + pk_common.delta_g1.mul(s);
+    let mut g_c = compute_c::<E>(
+        pk_common,
+        g_a_without_queries,
+        g1_b_without_queries,
+        r,
+        s,
+        v,
+        &[],
+        &[],
+    );
+    g_c += E::G1::msm_unchecked(g1_queries, &scalars);
+    end_timer!(c_time);
 
-    end_timer!(c_acc_time);
+    end_timer!(prover_time);
 
-    let num_inputs = prover.instance_assignment.len();
-    let input_assignment_with_one_field = prover.instance_assignment.clone();
+    Ok(Proof {
+        a: g_a.into_affine(),
+        b: g2_b.into_affine(),
+        c: g_c.into_affine(),
+        d: g_d.into_affine(),
+    })
+}
 
-    let input_assignment_with_one = input_assignment_with_one_field[0..num_inputs]
-        .into_iter()
-        .map(|s| s.into_bigint())
-        .collect::<Vec<_>>();
+/// Same as [`create_proof`] but checks that the circuit is satisfied, even in release builds, and
+/// returns the index of the first unsatisfied constraint as [`Error::UnsatisfiedConstraint`] if not.
+/// A circuit too large for the FFT domains of the scalar field gives [`Error::DomainTooLarge`].
+pub fn create_proof_with_report<E, C>(
+    circuit: C,
+    pk_common: &ProvingKeyCommon<E>,
+    vk: &VerifyingKey<E>,
+    r: E::ScalarField,
+    s: E::ScalarField,
+    v: E::ScalarField,
+) -> crate::Result<Proof<E>>
+where
+    E: Pairing,
+    C: ConstraintSynthesizer<E::ScalarField>,
+{
+    let cs = ConstraintSystem::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+
+    let synthesis_time = start_timer!(|| "Constraint synthesis");
+    circuit.generate_constraints(cs.clone())?;
+    end_timer!(synthesis_time);
+
+    let lc_time = start_timer!(|| "Inlining LCs");
+    cs.finalize();
+    end_timer!(lc_time);
+
+    if let Some(index) = first_unsatisfied_constraint(&cs)? {
+        return Err(Error::UnsatisfiedConstraint(index));
+    }
+    create_proof_with_cs(cs, pk_common, vk, r, s, v)
+}
+
+/// Check that the field has an evaluation domain large enough for the finalized constraint system
+/// `cs`, returning `Error::DomainTooLarge` if not.
+pub(crate) fn check_domain<F: PrimeField>(cs: &ConstraintSystemRef<F>) -> crate::Result<()> {
+    R1CStoQAP::checked_domain::<F, GeneralEvaluationDomain<F>>(
+        cs.num_constraints(),
+        cs.num_instance_variables(),
+    )?;
+    Ok(())
+}
 
-    let input_assignment = input_assignment_with_one[1..].to_vec();
+/// Index of the first constraint of the finalized constraint system `cs` that is not satisfied, if any.
+fn first_unsatisfied_constraint<F: PrimeField>(
+    cs: &ConstraintSystemRef<F>,
+) -> R1CSResult<Option<usize>> {
+    let matrices = cs.to_matrices().ok_or(SynthesisError::AssignmentMissing)?;
+    let prover = cs.borrow().ok_or(SynthesisError::AssignmentMissing)?;
+    let full_assignment = [
+        prover.instance_assignment.as_slice(),
+        prover.witness_assignment.as_slice(),
+    ]
+    .concat();
+
+    Ok((0..matrices.num_constraints).find(|&i| {
+        let a: F = evaluate_constraint(&matrices.a[i], &full_assignment);
+        let b: F = evaluate_constraint(&matrices.b[i], &full_assignment);
+        let c: F = evaluate_constraint(&matrices.c[i], &full_assignment);
+        a * b != c
+    }))
+}
+
+/// Same as [`create_proof`] but also returns the commitment `sum_i h_i * h_bases_i` to the
+/// coefficients `h` of the quotient polynomial that the proof was computed with. The proof itself is
+/// unchanged. The commitment is binding to `h` as long as no discrete log relation between the
+/// `h_bases` is known, e.g. when they are hashed to the curve, but it has no randomness so it is not
+/// hiding and reveals whether two proofs were made for the same assignment. `h_bases` needs at least
+/// as many elements as `pk_common.h_query`; the last coefficient of `h` is always zero.
+pub fn create_proof_with_h_commitment<E, C>(
+    circuit: C,
+    pk_common: &ProvingKeyCommon<E>,
+    vk: &VerifyingKey<E>,
+    r: E::ScalarField,
+    s: E::ScalarField,
+    v: E::ScalarField,
+    h_bases: &[E::G1Affine],
+) -> crate::Result<(Proof<E>, E::G1)>
+where
+    E: Pairing,
+    C: ConstraintSynthesizer<E::ScalarField>,
+{
+    if h_bases.len() < pk_common.h_query.len() {
+        return Err(SynthesisError::MalformedVerifyingKey.into());
+    }
+
+    let cs = ConstraintSystem::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+    circuit.generate_constraints(cs.clone())?;
+    debug_assert!(cs.is_satisfied().unwrap());
+    cs.finalize();
+
+    let h = checked_witness_map(cs.clone(), pk_common)?;
+    let h_commitment = E::G1::msm_unchecked(h_bases, &h);
+    let proof = create_proof_with_h(cs, h, pk_common, vk, r, s, v)?;
+    Ok((proof, h_commitment))
+}
+
+/// Compute `h` for the finalized constraint system `cs`, checking that it fits the proving key.
+/// A circuit too large for the FFT domains of the scalar field gives `Error::DomainTooLarge`.
+fn checked_witness_map<E: Pairing>(
+    cs: ConstraintSystemRef<E::ScalarField>,
+    pk_common: &ProvingKeyCommon<E>,
+) -> crate::Result<Vec<E::ScalarField>> {
+    type D<F> = GeneralEvaluationDomain<F>;
+
+    check_domain(&cs)?;
+    let witness_map_time = start_timer!(|| "R1CS to QAP witness map");
+    let h = R1CStoQAP::witness_map::<E::ScalarField, D<E::ScalarField>>(cs)?;
+    end_timer!(witness_map_time);
+    // The proving key was generated for a circuit with a different domain
+    if h.len() != pk_common.h_query.len() + 1 {
+        return Err(SynthesisError::MalformedVerifyingKey.into());
+    }
+    Ok(h)
+}
+
+/// Create a Groth16 proof using randomness `r` and `s` for the finalized constraint system `cs`.
+#[inline]
+fn create_proof_with_cs<E>(
+    cs: ConstraintSystemRef<E::ScalarField>,
+    pk_common: &ProvingKeyCommon<E>,
+    vk: &VerifyingKey<E>,
+    r: E::ScalarField,
+    s: E::ScalarField,
+    v: E::ScalarField,
+) -> crate::Result<Proof<E>>
+where
+    E: Pairing,
+{
+    let h = checked_witness_map(cs.clone(), pk_common)?;
+    create_proof_with_h(cs, h, pk_common, vk, r, s, v)
+}
 
+/// Same as [`create_proof_with_cs`] with `h` already computed by [`checked_witness_map`].
+fn create_proof_with_h<E>(
+    cs: ConstraintSystemRef<E::ScalarField>,
+    h: Vec<E::ScalarField>,
+    pk_common: &ProvingKeyCommon<E>,
+    vk: &VerifyingKey<E>,
+    r: E::ScalarField,
+    s: E::ScalarField,
+    v: E::ScalarField,
+) -> crate::Result<Proof<E>>
+where
+    E: Pairing,
+{
+    // Only the assignment is needed from now on so the rest of the constraint system is freed
+    let mut prover = cs.borrow_mut().unwrap();
+    let instance_assignment = core::mem::take(&mut prover.instance_assignment);
+    let witness_assignment = core::mem::take(&mut prover.witness_assignment);
     drop(prover);
     drop(cs);
 
+    prove_from_assignment(
+        &instance_assignment,
+        &witness_assignment,
+        &h,
+        pk_common,
+        vk,
+        r,
+        s,
+        v,
+    )
+}
+
+/// Create a LegoGroth16 proof from an assignment computed elsewhere, e.g. by a witness generator in
+/// another language, without a [`ConstraintSynthesizer`]. `instance_assignment` starts with the
+/// constant `1` followed by the public inputs, `witness_assignment` has all the witnesses, in the
+/// order of the circuit the keys were generated for, and `h` are the coefficients of the quotient
+/// polynomial, as computed by [`R1CStoQAP::witness_map`], of which the first `h_query.len()` are used.
+///
+/// Only the MSMs and group operations are done here: nothing checks that the assignment satisfies
+/// the circuit or that `h` matches it, so a wrong assignment just gives a proof that doesn't verify.
+/// Only the lengths are checked against `pk_common`, giving `SynthesisError::MalformedVerifyingKey`
+/// on a mismatch, and that the witnesses past the committed block of a truncated key are zero,
+/// giving `Error::UncommittedWitnessNotZero` otherwise.
+#[allow(clippy::too_many_arguments)]
+pub fn create_proof_from_assignment<E: Pairing>(
+    instance_assignment: &[E::ScalarField],
+    witness_assignment: &[E::ScalarField],
+    h: &[E::ScalarField],
+    pk_common: &ProvingKeyCommon<E>,
+    vk: &VerifyingKey<E>,
+    r: E::ScalarField,
+    s: E::ScalarField,
+    v: E::ScalarField,
+) -> crate::Result<Proof<E>> {
+    if instance_assignment.first() != Some(&E::ScalarField::one())
+        || instance_assignment.len() + witness_assignment.len() != pk_common.a_query.len()
+        || instance_assignment.len() + vk.commit_witness_count as usize > vk.gamma_abc_g1.len()
+        || h.len() < pk_common.h_query.len()
+    {
+        return Err(SynthesisError::MalformedVerifyingKey.into());
+    }
+    prove_from_assignment(
+        instance_assignment,
+        witness_assignment,
+        h,
+        pk_common,
+        vk,
+        r,
+        s,
+        v,
+    )
+}
+
+/// Create the proof for an assignment that was checked against the keys.
+#[allow(clippy::too_many_arguments)]
+fn prove_from_assignment<E: Pairing>(
+    instance_assignment: &[E::ScalarField],
+    witness_assignment: &[E::ScalarField],
+    h: &[E::ScalarField],
+    pk_common: &ProvingKeyCommon<E>,
+    vk: &VerifyingKey<E>,
+    r: E::ScalarField,
+    s: E::ScalarField,
+    v: E::ScalarField,
+) -> crate::Result<Proof<E>> {
+    let prover_time = start_timer!(|| "Groth16::Prover");
+
+    // Witnesses past the committed block of `vk` go with `l_query`, which keys generated by this
+    // crate leave empty but imported plain Groth16 keys don't. Those past both, as left by
+    // `ProvingKeyWithLink::truncate_commitment`, must be zero
+    let committed_count = (vk.commit_witness_count as usize).min(witness_assignment.len());
+    let uncommitted_count = pk_common
+        .l_query
+        .len()
+        .min(witness_assignment.len() - committed_count);
+    check_uncommitted_witnesses(witness_assignment, committed_count + uncommitted_count)?;
+
+    let aux_assignment = cfg_iter!(witness_assignment)
+        .map(|s| s.into_bigint())
+        .collect::<Vec<_>>();
+    let committed_witnesses = &aux_assignment[..committed_count];
+    let uncommitted_witnesses =
+        &aux_assignment[committed_count..committed_count + uncommitted_count];
+
+    let num_inputs = instance_assignment.len();
+    let input_assignment = instance_assignment[1..]
+        .iter()
+        .map(|s| s.into_bigint())
+        .collect::<Vec<_>>();
+
     let assignment = [&input_assignment[..], &aux_assignment[..]].concat();
-    // drop(aux_assignment);
 
     // Compute A
     let a_acc_time = start_timer!(|| "Compute A");
-    let r_g1 = pk_common.delta_g1.mul(r);
-
-    let g_a = calculate_coeff(r_g1, &pk_common.a_query, vk.alpha_g1, &assignment);
-
-    let s_g_a = g_a.mul(s);
+    let g_a = compute_a::<E>(
+        &pk_common.a_query,
+        vk.alpha_g1,
+        pk_common.delta_g1,
+        r,
+        &assignment,
+    );
     end_timer!(a_acc_time);
 
     // Compute B in G1 if needed
     let g1_b = if !r.is_zero() {
         let b_g1_acc_time = start_timer!(|| "Compute B in G1");
-        let s_g1 = pk_common.delta_g1.mul(s);
-        let g1_b = calculate_coeff(s_g1, &pk_common.b_g1_query, pk_common.beta_g1, &assignment);
-
+        let g1_b = compute_b_g1::<E>(
+            &pk_common.b_g1_query,
+            pk_common.beta_g1,
+            pk_common.delta_g1,
+            s,
+            &assignment,
+        );
         end_timer!(b_g1_acc_time);
 
         g1_b
@@ -191,36 +1077,28 @@ where
 
     // Compute B in G2
     let b_g2_acc_time = start_timer!(|| "Compute B in G2");
-    let s_g2 = vk.delta_g2.mul(s);
-    let g2_b = calculate_coeff(s_g2, &pk_common.b_g2_query, vk.beta_g2, &assignment);
-    let r_g1_b = g1_b.mul(r);
+    let g2_b = compute_b_g2::<E>(
+        &pk_common.b_g2_query,
+        vk.beta_g2,
+        vk.delta_g2,
+        s,
+        &assignment,
+    );
     drop(assignment);
-
     end_timer!(b_g2_acc_time);
 
-    let c_time = start_timer!(|| "Finish C");
-    let mut g_c = s_g_a;
-    g_c += &r_g1_b;
-    g_c -= &r_s_delta_g1;
-    g_c += &l_aux_acc;
-    g_c += &h_acc;
-    g_c -= &v_eta_delta_inv;
+    let c_time = start_timer!(|| "Compute C");
+    let g_c = compute_c::<E>(pk_common, g_a, g1_b, r, s, v, h, uncommitted_witnesses);
     end_timer!(c_time);
 
     // Compute D
     let d_acc_time = start_timer!(|| "Compute D");
-
-    let gamma_abc_inputs_source = &vk.gamma_abc_g1[input_assignment_with_one_field.len()
-    ..input_assignment_with_one_field.len() + committed_witnesses.len()];
-    let gamma_abc_inputs_acc = <<E as Pairing>::G1 as VariableBaseMSM>::msm_bigint(
-        gamma_abc_inputs_source,
-        &committed_witnesses,
+    let g_d = compute_d::<E>(
+        &vk.gamma_abc_g1[num_inputs..num_inputs + committed_witnesses.len()],
+        vk.eta_gamma_inv_g1,
+        v,
+        committed_witnesses,
     );
-
-    let v_eta_gamma_inv = vk.eta_gamma_inv_g1.into_group().mul(v);
-
-    let mut g_d = gamma_abc_inputs_acc;
-    g_d += &v_eta_gamma_inv;
     end_timer!(d_acc_time);
 
     end_timer!(prover_time);
@@ -233,6 +1111,130 @@ where
     })
 }
 
+/// Check that the witnesses after the first `used_count`, which are neither committed in `D` nor
+/// have a base in `l_query`, as with a key shrunk with `ProvingKeyWithLink::truncate_commitment`,
+/// are zero.
+fn check_uncommitted_witnesses<F: PrimeField>(
+    witnesses: &[F],
+    used_count: usize,
+) -> crate::Result<()> {
+    match witnesses.iter().skip(used_count).position(|w| !w.is_zero()) {
+        Some(index) => Err(Error::UncommittedWitnessNotZero(used_count + index)),
+        None => Ok(()),
+    }
+}
+
+/// Compute the proof element `A = alpha + sum_i a_i(t) * assignment_i + r * delta` in G1, where
+/// `assignment` is the full assignment without the leading constant `1` and `r` is the same
+/// randomness as given to [`compute_c`].
+pub fn compute_a<E: Pairing>(
+    a_query: &[E::G1Affine],
+    alpha_g1: E::G1Affine,
+    delta_g1: E::G1Affine,
+    r: E::ScalarField,
+    assignment: &[<E::ScalarField as PrimeField>::BigInt],
+) -> E::G1 {
+    calculate_coeff(delta_g1.mul(r), a_query, alpha_g1, assignment)
+}
+
+/// Compute the proof element `B = beta + sum_i b_i(t) * assignment_i + s * delta` in G1, which is
+/// only needed to compute `C`. `assignment` is as for [`compute_a`] and `s` is the same randomness
+/// as given to [`compute_b_g2`] and [`compute_c`].
+pub fn compute_b_g1<E: Pairing>(
+    b_g1_query: &[E::G1Affine],
+    beta_g1: E::G1Affine,
+    delta_g1: E::G1Affine,
+    s: E::ScalarField,
+    assignment: &[<E::ScalarField as PrimeField>::BigInt],
+) -> E::G1 {
+    calculate_coeff(delta_g1.mul(s), b_g1_query, beta_g1, assignment)
+}
+
+/// Compute the proof element `B = beta + sum_i b_i(t) * assignment_i + s * delta` in G2.
+/// `assignment` is as for [`compute_a`] and `s` is the same randomness as given to [`compute_c`].
+pub fn compute_b_g2<E: Pairing>(
+    b_g2_query: &[E::G2Affine],
+    beta_g2: E::G2Affine,
+    delta_g2: E::G2Affine,
+    s: E::ScalarField,
+    assignment: &[<E::ScalarField as PrimeField>::BigInt],
+) -> E::G2 {
+    calculate_coeff(delta_g2.mul(s), b_g2_query, beta_g2, assignment)
+}
+
+/// Compute the proof element `C` from `g_a`, the output of [`compute_a`] for randomness `r`, and
+/// `g1_b`, the output of [`compute_b_g1`] for randomness `s` (may be zero if `r` is zero). `h` are
+/// the coefficients of the quotient polynomial given by `R1CStoQAP::witness_map`,
+/// `uncommitted_witnesses` are the witnesses not committed in `D` and `v` is the randomness given to
+/// [`compute_d`].
+#[allow(clippy::too_many_arguments)]
+pub fn compute_c<E: Pairing>(
+    pk_common: &ProvingKeyCommon<E>,
+    g_a: E::G1,
+    g1_b: E::G1,
+    r: E::ScalarField,
+    s: E::ScalarField,
+    v: E::ScalarField,
+    h: &[E::ScalarField],
+    uncommitted_witnesses: &[<E::ScalarField as PrimeField>::BigInt],
+) -> E::G1 {
+    let h_acc = E::G1::msm_unchecked(&pk_common.h_query, h);
+    let l_aux_acc = E::G1::msm_bigint(&pk_common.l_query, uncommitted_witnesses);
+
+    let r_s_delta_g1 = pk_common.delta_g1.into_group().mul(r).mul(s);
+    let v_eta_delta_inv = pk_common.eta_delta_inv_g1.into_group().mul(v);
+
+    let mut g_c = g_a.mul(s);
+    // `g1_b` is not computed when `r` is zero and its term vanishes anyway
+    if !r.is_zero() {
+        g_c += &g1_b.mul(r);
+    }
+    g_c -= &r_s_delta_g1;
+    g_c += &l_aux_acc;
+    g_c += &h_acc;
+    g_c -= &v_eta_delta_inv;
+    g_c
+}
+
+/// Compute the proof element `D`, the commitment to `committed_witnesses` with randomness `v`.
+/// `committed_gamma_abc` is the part of `vk.gamma_abc_g1` for the committed witnesses, i.e. the one
+/// right after the public inputs.
+pub fn compute_d<E: Pairing>(
+    committed_gamma_abc: &[E::G1Affine],
+    eta_gamma_inv_g1: E::G1Affine,
+    v: E::ScalarField,
+    committed_witnesses: &[<E::ScalarField as PrimeField>::BigInt],
+) -> E::G1 {
+    let mut g_d = E::G1::msm_bigint(committed_gamma_abc, committed_witnesses);
+    g_d += &eta_gamma_inv_g1.into_group().mul(v);
+    g_d
+}
+
+/// Estimate in bytes the peak memory used by [`create_proof`] for the vectors it allocates, for a circuit
+/// with `num_constraints` constraints, `num_instance` instance variables (including the constant `1`)
+/// and `num_witness` witness variables. The memory used by the constraint system itself is not included.
+pub fn estimate_prover_memory<E: Pairing>(
+    num_constraints: usize,
+    num_instance: usize,
+    num_witness: usize,
+) -> usize {
+    let field_size = core::mem::size_of::<E::ScalarField>();
+    let bigint_size = core::mem::size_of::<<E::ScalarField as PrimeField>::BigInt>();
+    let domain_size = GeneralEvaluationDomain::<E::ScalarField>::compute_size_of_domain(
+        num_constraints + num_instance,
+    )
+    .unwrap_or(num_constraints + num_instance);
+    let num_variables = num_instance + num_witness;
+
+    // While computing `h`, the full assignment and the evaluations of `a`, `b` and their product are alive
+    let witness_map = (num_variables + 3 * domain_size) * field_size;
+    // While computing the MSMs, `h`, `aux_assignment`, the input assignment and `assignment` are alive
+    let msm =
+        domain_size * field_size + (num_witness + 2 * num_instance + num_variables) * bigint_size;
+
+    witness_map.max(msm)
+}
+
 fn calculate_coeff<G: AffineRepr>(
     initial: G::Group,
     query: &[G],
@@ -250,4 +1252,4 @@ fn calculate_coeff<G: AffineRepr>(
     res.add_assign(&vk_param);
 
     res
-}
\ No newline at end of file
+}
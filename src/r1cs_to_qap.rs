@@ -2,8 +2,11 @@ use ark_ff::{One, PrimeField, Zero};
 use ark_poly::EvaluationDomain;
 use ark_std::{cfg_iter, cfg_iter_mut, end_timer, start_timer, vec};
 
+use crate::error::Error;
 use crate::r1cs_to_qap::vec::Vec;
-use ark_relations::r1cs::{ConstraintSystemRef, Result as R1CSResult, SynthesisError};
+use ark_relations::r1cs::{
+    ConstraintMatrices, ConstraintSystemRef, Result as R1CSResult, SynthesisError,
+};
 use core::ops::{AddAssign, Deref};
 
 #[cfg(feature = "parallel")]
@@ -42,18 +45,124 @@ where
     return res;
 }
 
-pub(crate) struct R1CStoQAP;
+/// Block of variables of the constraint system, as laid out in the QAP.
+#[cfg(feature = "debug-mapping")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VariableBlock {
+    /// The constant `1`, instance variable 0.
+    One,
+    /// The public inputs.
+    Instance,
+    /// The witnesses committed in the proof's `d`.
+    CommittedWitness,
+    /// The witnesses not committed in `d`, whose QAP columns go to `l_query`.
+    Witness,
+}
+
+/// QAP columns of a block of variables, i.e. indices in the `a`, `b` and `c` vectors returned by
+/// [`R1CStoQAP::instance_map_from_matrices`].
+#[cfg(feature = "debug-mapping")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VariableMapping {
+    pub block: VariableBlock,
+    /// Index of the first variable of the block among the R1CS instance or witness variables.
+    pub first_variable: usize,
+    pub qap_columns: core::ops::Range<usize>,
+}
+
+/// Reduction from R1CS to QAP as used by the generator and the prover.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct R1CStoQAP;
 
 impl R1CStoQAP {
+    /// Size of the evaluation domain of the QAP for the finalized constraint system `cs`. The
+    /// generator and the prover both use this domain, and the proving key has `domain_size - 1`
+    /// elements in its `h_query`.
+    pub fn domain_size<F: PrimeField, D: EvaluationDomain<F>>(
+        cs: &ConstraintSystemRef<F>,
+    ) -> R1CSResult<usize> {
+        Ok(Self::domain::<F, D>(cs.num_constraints(), cs.num_instance_variables())?.size())
+    }
+
+    /// Evaluation domain for `num_constraints` constraints and `num_instance_variables` instance
+    /// variables, including the constant `1`. Each instance variable adds a constraint `x * 0 = 0`.
+    pub(crate) fn domain<F: PrimeField, D: EvaluationDomain<F>>(
+        num_constraints: usize,
+        num_instance_variables: usize,
+    ) -> R1CSResult<D> {
+        D::new(num_constraints + num_instance_variables)
+            .ok_or(SynthesisError::PolynomialDegreeTooLarge)
+    }
+
+    /// Same as [`Self::domain`] but a domain larger than the field supports is reported as
+    /// `Error::DomainTooLarge` with the requested size and the largest domain of `F`, see
+    /// [`Self::max_domain_size`].
+    pub(crate) fn checked_domain<F: PrimeField, D: EvaluationDomain<F>>(
+        num_constraints: usize,
+        num_instance_variables: usize,
+    ) -> crate::Result<D> {
+        let requested = num_constraints.saturating_add(num_instance_variables);
+        Self::domain::<F, D>(num_constraints, num_instance_variables).map_err(|_| {
+            Error::DomainTooLarge {
+                requested,
+                max: Self::max_domain_size::<F>(),
+            }
+        })
+    }
+
+    /// Size of the largest evaluation domain of `F`: `2^TWO_ADICITY`, times
+    /// `SMALL_SUBGROUP_BASE^SMALL_SUBGROUP_BASE_ADICITY` for fields with mixed-radix domains.
+    /// Saturates at `usize::MAX`.
+    pub(crate) fn max_domain_size<F: PrimeField>() -> usize {
+        let two_adic = 1usize.checked_shl(F::TWO_ADICITY).unwrap_or(usize::MAX);
+        let small_subgroup = match (F::SMALL_SUBGROUP_BASE, F::SMALL_SUBGROUP_BASE_ADICITY) {
+            (Some(base), Some(adicity)) => {
+                (base as usize).checked_pow(adicity).unwrap_or(usize::MAX)
+            }
+            _ => 1,
+        };
+        two_adic.saturating_mul(small_subgroup)
+    }
+
+    /// Evaluate the QAP polynomials of the finalized constraint system `cs` at the given point `t`.
+    /// Returns `(a, b, c, zt, qap_num_variables, domain_size)` where `a`, `b` and `c` are the evaluations
+    /// of the per-variable polynomials and `zt` is the evaluation of the vanishing polynomial.
+    /// This is deterministic in `t` so a setup can be audited against a published `t`.
     #[inline]
     #[allow(clippy::type_complexity)]
-    pub(crate) fn instance_map_with_evaluation<F: PrimeField, D: EvaluationDomain<F>>(
+    pub fn instance_map_at_point<F: PrimeField, D: EvaluationDomain<F>>(
         cs: ConstraintSystemRef<F>,
         t: &F,
     ) -> R1CSResult<(Vec<F>, Vec<F>, Vec<F>, F, usize, usize)> {
         let matrices = cs.to_matrices().unwrap();
-        let domain_size = cs.num_constraints() + cs.num_instance_variables();
-        let domain = D::new(domain_size).ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+        Self::instance_map_from_matrices::<F, D>(&matrices, t)
+    }
+
+    /// Same as [`Self::instance_map_at_point`] but works directly on the R1CS matrices rather than
+    /// a constraint system. The matrices `a`, `b` and `c` are expected to have `num_constraints` rows.
+    #[inline]
+    #[allow(clippy::type_complexity)]
+    pub fn instance_map_from_matrices<F: PrimeField, D: EvaluationDomain<F>>(
+        matrices: &ConstraintMatrices<F>,
+        t: &F,
+    ) -> R1CSResult<(Vec<F>, Vec<F>, Vec<F>, F, usize, usize)> {
+        let domain =
+            Self::domain::<F, D>(matrices.num_constraints, matrices.num_instance_variables)?;
+        Self::instance_map_with_domain::<F, D>(matrices, &domain, t)
+    }
+
+    /// Same as [`Self::instance_map_from_matrices`] but with the evaluation domain already built,
+    /// e.g. once for many circuits of the same size. `domain` must be the one built for the
+    /// matrices, which is not checked here.
+    #[inline]
+    #[allow(clippy::type_complexity)]
+    pub fn instance_map_with_domain<F: PrimeField, D: EvaluationDomain<F>>(
+        matrices: &ConstraintMatrices<F>,
+        domain: &D,
+        t: &F,
+    ) -> R1CSResult<(Vec<F>, Vec<F>, Vec<F>, F, usize, usize)> {
+        let num_constraints = matrices.num_constraints;
+        let num_instance_variables = matrices.num_instance_variables;
         let domain_size = domain.size();
 
         let zt = domain.evaluate_vanishing_polynomial(*t);
@@ -63,7 +172,7 @@ impl R1CStoQAP {
         let u = domain.evaluate_all_lagrange_coefficients(*t);
         end_timer!(coefficients_time);
 
-        let qap_num_variables = (cs.num_instance_variables() - 1) + cs.num_witness_variables();
+        let qap_num_variables = (num_instance_variables - 1) + matrices.num_witness_variables;
 
         let mut a = vec![F::zero(); qap_num_variables + 1];
         let mut b = vec![F::zero(); qap_num_variables + 1];
@@ -71,19 +180,24 @@ impl R1CStoQAP {
 
         {
             let start = 0;
-            let end = cs.num_instance_variables();
-            let num_constraints = cs.num_constraints();
+            let end = num_instance_variables;
             a[start..end].copy_from_slice(&u[(start + num_constraints)..(end + num_constraints)]);
         }
 
-        for (i, u_i) in u.iter().enumerate().take(cs.num_constraints()) {
-            for &(ref coeff, index) in &matrices.a[i] {
+        for (((u_i, a_i), b_i), c_i) in u
+            .iter()
+            .zip(&matrices.a)
+            .zip(&matrices.b)
+            .zip(&matrices.c)
+            .take(num_constraints)
+        {
+            for &(ref coeff, index) in a_i {
                 a[index] += &(*u_i * coeff);
             }
-            for &(ref coeff, index) in &matrices.b[i] {
+            for &(ref coeff, index) in b_i {
                 b[index] += &(*u_i * coeff);
             }
-            for &(ref coeff, index) in &matrices.c[i] {
+            for &(ref coeff, index) in c_i {
                 c[index] += &(*u_i * coeff);
             }
         }
@@ -91,8 +205,50 @@ impl R1CStoQAP {
         Ok((a, b, c, zt, qap_num_variables, domain_size))
     }
 
+    /// Same as [`Self::instance_map_from_matrices`] but also returns which QAP columns belong to
+    /// which variables. All witnesses are committed in `d`, so the `Witness` block is empty.
+    #[cfg(feature = "debug-mapping")]
+    #[allow(clippy::type_complexity)]
+    pub fn instance_map_from_matrices_with_mapping<F: PrimeField, D: EvaluationDomain<F>>(
+        matrices: &ConstraintMatrices<F>,
+        t: &F,
+    ) -> R1CSResult<(
+        (Vec<F>, Vec<F>, Vec<F>, F, usize, usize),
+        Vec<VariableMapping>,
+    )> {
+        let map = Self::instance_map_from_matrices::<F, D>(matrices, t)?;
+
+        let num_instance = matrices.num_instance_variables;
+        let num_witness = matrices.num_witness_variables;
+        let mapping = vec![
+            VariableMapping {
+                block: VariableBlock::One,
+                first_variable: 0,
+                qap_columns: 0..1,
+            },
+            VariableMapping {
+                block: VariableBlock::Instance,
+                first_variable: 1,
+                qap_columns: 1..num_instance,
+            },
+            VariableMapping {
+                block: VariableBlock::CommittedWitness,
+                first_variable: 0,
+                qap_columns: num_instance..num_instance + num_witness,
+            },
+            VariableMapping {
+                block: VariableBlock::Witness,
+                first_variable: num_witness,
+                qap_columns: num_instance + num_witness..num_instance + num_witness,
+            },
+        ];
+        Ok((map, mapping))
+    }
+
+    /// Coefficients of the quotient polynomial `h` for the finalized constraint system `prover`,
+    /// as needed by the prover to compute `C`.
     #[inline]
-    pub(crate) fn witness_map<F: PrimeField, D: EvaluationDomain<F>>(
+    pub fn witness_map<F: PrimeField, D: EvaluationDomain<F>>(
         prover: ConstraintSystemRef<F>,
     ) -> R1CSResult<Vec<F>> {
         let matrices = prover.to_matrices().unwrap();
@@ -108,8 +264,7 @@ impl R1CStoQAP {
         ]
         .concat();
 
-        let domain =
-            D::new(num_constraints + num_inputs).ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+        let domain = Self::domain::<F, D>(num_constraints, num_inputs)?;
         let domain_size = domain.size();
 
         let mut a = vec![zero; domain_size];
@@ -120,8 +275,8 @@ impl R1CStoQAP {
             .zip(cfg_iter!(&matrices.a))
             .zip(cfg_iter!(&matrices.b))
             .for_each(|(((a, b), at_i), bt_i)| {
-                *a = evaluate_constraint(&at_i, &full_assignment);
-                *b = evaluate_constraint(&bt_i, &full_assignment);
+                *a = evaluate_constraint(at_i, &full_assignment);
+                *b = evaluate_constraint(bt_i, &full_assignment);
             });
 
         {
@@ -1,17 +1,61 @@
 use crate::{
-    create_random_proof, generate_random_parameters, prepare_verifying_key, verify_commitments, verify_witness_commitment,
-    verify_proof, Vec, generate_random_parameters_with_link, create_random_proof_with_link, verify_proof_with_link,
+    analyze_circuit, batch_finalize_d, batch_inverse, calculate_d, compute_a, compute_alpha_beta,
+    compute_b_g1, compute_b_g2, compute_c, compute_d, create_and_self_check_proof,
+    create_d_projective, create_proof, create_proof_from_assignment, create_proof_reusable,
+    create_proof_with_concat_queries, create_proof_with_h_commitment, create_proof_with_report,
+    create_random_proof_checked, create_random_proof_with_link_prepared,
+    create_shared_commitment_proof, derive_commitment_randomness,
+    error::{Error, VerifyError},
+    estimate_prover_memory, expected_proof_d, finalize_proof, generate_parameters,
+    generate_parameters_from_matrices, generate_parameters_with_cache,
+    generate_parameters_with_domain_and_t, generate_parameters_with_stats,
+    generate_random_parameters_with_link_blinding_base,
+    generate_random_parameters_with_link_matrix, pad_committed_witnesses, prepare_inputs,
+    prepare_inputs_sparse, prepare_verifying_key_checked, prover_self_verify,
+    r1cs_to_qap::R1CStoQAP,
+    verify_d_only, verify_groth16_proof, verify_groth16_proof_with_prepared_b,
+    verify_link_commitment, verify_link_commitments_batch, verify_link_commitments_individually,
+    verify_link_relation, verify_proof_by_name, verify_proof_checked, verify_proof_from_raw,
+    verify_proof_strict, verify_proof_with_disclosed_commitment,
+    verify_proof_with_link_prepared_inputs, verify_proof_with_link_single_pairing,
+    verify_proof_with_prepared_inputs, verify_proofs_batch, verify_shared_commitment,
+    verify_vanilla_groth16_proof, verify_witness_commitment_with_eta, DualPreparedVerifyingKey,
+    DualProof, GeneratorCache, LinkCommitmentVerifier, PESubspaceSnark, Proof, ProofCoords,
+    ProofWithLink, SparseMatrix, SubspaceSnark, UpdatableInputs, VerifyingKeyWithLink, EK, PP, VK,
+};
+use crate::{
+    check_v, create_random_proof, create_random_proof_with_link, generate_random_parameters,
+    generate_random_parameters_with_link, generate_verifying_key, generate_verifying_key_only,
+    open_position, prepare_verifying_key, prove_commitment_equality, verify_against_either,
+    verify_all, verify_commitment_equality, verify_commitments, verify_position_opening,
+    verify_prepared_proof, verify_proof, verify_proof_with_link, verify_proofs_same_statement,
+    verify_witness_commitment, DeltaUpdateProof, ProvingKey, Vec, VerifyingKey,
+};
+#[cfg(feature = "std")]
+use crate::{generate_parameters_with_window, MAX_MSM_WINDOW};
+use ark_ec::{
+    pairing::Pairing,
+    short_weierstrass::{Affine as SWAffine, SWCurveConfig},
+    AffineRepr, CurveGroup, VariableBaseMSM,
 };
-use ark_ec::pairing::Pairing;
 use ark_ff::UniformRand;
-use ark_std::rand::{rngs::StdRng, SeedableRng};
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, SerializationError, Valid, Validate,
+};
+use ark_std::rand::{rngs::StdRng, Rng, SeedableRng};
+use ark_std::{collections::BTreeMap, string::String, vec};
+use blake2::{Blake2b512, Blake2s256, Digest};
 
-use core::ops::MulAssign;
+use core::ops::{Mul, MulAssign};
 
-use ark_ff::Field;
+use ark_ff::{Field, One, PrimeField, Zero};
+use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
 use ark_relations::{
     lc,
-    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+    r1cs::{
+        ConstraintMatrices, ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef,
+        OptimizationGoal, SynthesisError, SynthesisMode, Variable,
+    },
 };
 
 struct MySillyCircuit<F: Field> {
@@ -45,7 +89,60 @@ impl<ConstraintF: Field> ConstraintSynthesizer<ConstraintF> for MySillyCircuit<C
     }
 }
 
-// tests prove and verify for both with and without CP-link using MySillyCircuit. 
+// `MySillyCircuit` with `padding` additional witnesses that are all zero
+struct MyPaddedCircuit<F: Field> {
+    a: Option<F>,
+    b: Option<F>,
+    padding: usize,
+}
+
+impl<ConstraintF: Field> ConstraintSynthesizer<ConstraintF> for MyPaddedCircuit<ConstraintF> {
+    fn generate_constraints(
+        self,
+        cs: ConstraintSystemRef<ConstraintF>,
+    ) -> Result<(), SynthesisError> {
+        MySillyCircuit {
+            a: self.a,
+            b: self.b,
+        }
+        .generate_constraints(cs.clone())?;
+        for _ in 0..self.padding {
+            let p = cs.new_witness_variable(|| Ok(ConstraintF::zero()))?;
+            cs.enforce_constraint(lc!() + p, lc!() + Variable::One, lc!())?;
+        }
+
+        Ok(())
+    }
+}
+
+// Same relation as `MySillyCircuit` but with all variables public, so nothing is committed in `d`
+struct MyPublicCircuit<F: Field> {
+    a: Option<F>,
+    b: Option<F>,
+}
+
+impl<ConstraintF: Field> ConstraintSynthesizer<ConstraintF> for MyPublicCircuit<ConstraintF> {
+    fn generate_constraints(
+        self,
+        cs: ConstraintSystemRef<ConstraintF>,
+    ) -> Result<(), SynthesisError> {
+        let a = cs.new_input_variable(|| self.a.ok_or(SynthesisError::AssignmentMissing))?;
+        let b = cs.new_input_variable(|| self.b.ok_or(SynthesisError::AssignmentMissing))?;
+        let c = cs.new_input_variable(|| {
+            let mut a = self.a.ok_or(SynthesisError::AssignmentMissing)?;
+            let b = self.b.ok_or(SynthesisError::AssignmentMissing)?;
+
+            a.mul_assign(&b);
+            Ok(a)
+        })?;
+
+        cs.enforce_constraint(lc!() + a, lc!() + b, lc!() + c)?;
+
+        Ok(())
+    }
+}
+
+// tests prove and verify for both with and without CP-link using MySillyCircuit.
 fn test_prove_and_verify<E>(n_iters: usize)
 where
     E: Pairing,
@@ -56,11 +153,9 @@ where
         .map(|_| E::G1::rand(&mut rng).into())
         .collect::<Vec<_>>();
 
-    let params = generate_random_parameters::<E, _, _>(
-        MySillyCircuit { a: None, b: None },
-        &mut rng,
-    )
-    .unwrap();
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
 
     let params_with_link = generate_random_parameters_with_link::<E, _, _>(
         MySillyCircuit { a: None, b: None },
@@ -81,7 +176,7 @@ where
         // Create commitment randomness
         let v = E::ScalarField::rand(&mut rng); // Randomness for the committed witness in proof.d
         let link_v = E::ScalarField::rand(&mut rng); // Randomness for the committed witness in CP_link
-        // Create a LegoGro16 proof with our parameters.
+                                                     // Create a LegoGro16 proof with our parameters.
         let proof = create_random_proof(
             MySillyCircuit {
                 a: Some(a),
@@ -101,46 +196,5541 @@ where
             v,
             link_v,
             &params_with_link,
-            &[a,b],
+            &[a, b],
             &mut rng,
         )
         .unwrap();
 
-        // verify commitment just to check proof is correctly constructed. 
+        // verify commitment just to check proof is correctly constructed.
         // this is done by the prover NOT the verifier
         // since we assume all input to the circuit are private witnesses.
-        assert!(verify_commitments(&params_with_link.vk, &proof_link, 1, &[a,b], &v, &link_v).unwrap());
-        assert!(verify_commitments(&params_with_link.vk, &proof_link, 1, &[a], &v, &link_v).is_err());
-        assert!(verify_commitments(&params_with_link.vk, &proof_link, 1, &[c], &a, &link_v).is_err());
-        
-        assert!(verify_witness_commitment(&params.vk, &proof, 1, &[a,b], &v).unwrap());
+        assert!(
+            verify_commitments(&params_with_link.vk, &proof_link, 1, &[a, b], &v, &link_v).unwrap()
+        );
+        assert!(
+            verify_commitments(&params_with_link.vk, &proof_link, 1, &[a], &v, &link_v).is_err()
+        );
+        assert!(
+            verify_commitments(&params_with_link.vk, &proof_link, 1, &[c], &a, &link_v).is_err()
+        );
+
+        assert!(verify_witness_commitment(&params.vk, &proof, 1, &[a, b], &v).unwrap());
         assert!(verify_witness_commitment(&params.vk, &proof, 1, &[a], &v).is_err());
         assert!(verify_witness_commitment(&params.vk, &proof, 1, &[c], &a).is_err());
-        
+
         // verify proofs by verifier
         assert!(verify_proof(&pvk, &proof, &[c]).unwrap());
-        assert!(verify_proof_with_link(&pvk_with_link, &params_with_link.vk, &proof_link, &[c]).unwrap());
+        assert!(
+            verify_proof_with_link(&pvk_with_link, &params_with_link.vk, &proof_link, &[c])
+                .unwrap()
+        );
         assert!(!verify_proof(&pvk_with_link, &proof, &[c]).unwrap());
     }
 }
 
-mod bls12_377 {
-    use super::test_prove_and_verify;
-    use ark_bls12_377::Bls12_377;
+fn test_split_proof_with_link_serialization<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
 
-    #[test]
-    fn prove_and_verify() {
-        test_prove_and_verify::<Bls12_377>(1);
-    }
+    let pedersen_bases = (0..3)
+        .map(|_| E::G1::rand(&mut rng).into())
+        .collect::<Vec<_>>();
+
+    let params_with_link = generate_random_parameters_with_link::<E, _, _>(
+        MySillyCircuit { a: None, b: None },
+        &pedersen_bases,
+        &mut rng,
+    )
+    .unwrap();
+    let pvk_with_link = prepare_verifying_key::<E>(&params_with_link.vk.groth16_vk);
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let mut c = a;
+    c.mul_assign(&b);
+
+    let v = E::ScalarField::rand(&mut rng);
+    let link_v = E::ScalarField::rand(&mut rng);
+    let proof_link = create_random_proof_with_link(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        v,
+        link_v,
+        &params_with_link,
+        &[a, b],
+        &mut rng,
+    )
+    .unwrap();
+
+    let mut groth16_bytes = Vec::new();
+    proof_link
+        .serialize_groth16_part(&mut groth16_bytes)
+        .unwrap();
+    let mut link_bytes = Vec::new();
+    proof_link.serialize_link_part(&mut link_bytes).unwrap();
+    assert_eq!(
+        groth16_bytes.len() + link_bytes.len(),
+        proof_link.compressed_size()
+    );
+
+    let reassembled = ProofWithLink::<E>::reassemble(&groth16_bytes, &link_bytes).unwrap();
+    assert_eq!(reassembled, proof_link);
+    assert!(
+        verify_proof_with_link(&pvk_with_link, &params_with_link.vk, &reassembled, &[c]).unwrap()
+    );
+
+    // Truncated link part must not deserialize
+    assert!(ProofWithLink::<E>::reassemble(&groth16_bytes, &link_bytes[1..]).is_err());
+
+    // Nor may either part have trailing bytes
+    let padded = |bytes: &[u8]| [bytes, &[0u8]].concat();
+    assert!(matches!(
+        ProofWithLink::<E>::reassemble(&padded(&groth16_bytes), &link_bytes),
+        Err(SerializationError::InvalidData)
+    ));
+    assert!(matches!(
+        ProofWithLink::<E>::reassemble(&groth16_bytes, &padded(&link_bytes)),
+        Err(SerializationError::InvalidData)
+    ));
 }
 
-mod cp6_782 {
-    use super::test_prove_and_verify;
+fn test_instance_map_at_point<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
 
-    use ark_cp6_782::CP6_782;
+    let cs = ConstraintSystem::<E::ScalarField>::new_ref();
+    cs.set_mode(SynthesisMode::Setup);
+    MySillyCircuit { a: None, b: None }
+        .generate_constraints(cs.clone())
+        .unwrap();
+    cs.finalize();
 
-    #[test]
-    fn prove_and_verify() {
-        test_prove_and_verify::<CP6_782>(1);
+    let t = E::ScalarField::rand(&mut rng);
+    let first = R1CStoQAP::instance_map_at_point::<E::ScalarField, GeneralEvaluationDomain<_>>(
+        cs.clone(),
+        &t,
+    )
+    .unwrap();
+    let second = R1CStoQAP::instance_map_at_point::<E::ScalarField, GeneralEvaluationDomain<_>>(
+        cs.clone(),
+        &t,
+    )
+    .unwrap();
+    assert_eq!(first, second);
+
+    let other_t = E::ScalarField::rand(&mut rng);
+    let third = R1CStoQAP::instance_map_at_point::<E::ScalarField, GeneralEvaluationDomain<_>>(
+        cs, &other_t,
+    )
+    .unwrap();
+    assert_ne!(first.0, third.0);
+}
+
+fn test_public_input_count_checked_before_pairing<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    // 1 public input and 2 committed witnesses
+    assert_eq!(params.vk.expected_public_input_count(), 1);
+    assert_eq!(params.vk.commit_witness_count, 2);
+
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let mut c = a;
+    c.mul_assign(&b);
+    let v = E::ScalarField::rand(&mut rng);
+
+    let proof = create_random_proof(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        v,
+        &params,
+        &mut rng,
+    )
+    .unwrap();
+
+    assert!(verify_proof(&pvk, &proof, &[c]).unwrap());
+    assert_eq!(
+        verify_proof(&pvk, &proof, &[]),
+        Err(SynthesisError::MalformedVerifyingKey)
+    );
+    assert_eq!(
+        verify_proof(&pvk, &proof, &[c, a]),
+        Err(SynthesisError::MalformedVerifyingKey)
+    );
+    // The count is checked before the proof is even looked at
+    assert_eq!(
+        verify_proof(&pvk, &Proof::default(), &[c, a, b]),
+        Err(SynthesisError::MalformedVerifyingKey)
+    );
+}
+
+fn silly_circuit_matrices<F: PrimeField>() -> ConstraintMatrices<F> {
+    let cs = ConstraintSystem::<F>::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+    cs.set_mode(SynthesisMode::Setup);
+    MySillyCircuit { a: None, b: None }
+        .generate_constraints(cs.clone())
+        .unwrap();
+    cs.finalize();
+    cs.to_matrices().unwrap()
+}
+
+fn test_parameters_from_matrices<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let matrices = silly_circuit_matrices::<E::ScalarField>();
+    let alpha = E::ScalarField::rand(&mut rng);
+    let beta = E::ScalarField::rand(&mut rng);
+    let gamma = E::ScalarField::rand(&mut rng);
+    let delta = E::ScalarField::rand(&mut rng);
+    let eta = E::ScalarField::rand(&mut rng);
+
+    let params = generate_parameters_from_matrices::<E, _>(
+        &matrices, alpha, beta, gamma, delta, eta, &mut rng,
+    )
+    .unwrap();
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let mut c = a;
+    c.mul_assign(&b);
+    let v = E::ScalarField::rand(&mut rng);
+
+    let proof = create_random_proof(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        v,
+        &params,
+        &mut rng,
+    )
+    .unwrap();
+    assert!(verify_proof(&pvk, &proof, &[c]).unwrap());
+    assert!(verify_witness_commitment(&params.vk, &proof, 1, &[a, b], &v).unwrap());
+
+    let mut bad_matrices = matrices;
+    bad_matrices.b.pop();
+    assert_eq!(
+        generate_parameters_from_matrices::<E, _>(
+            &bad_matrices,
+            alpha,
+            beta,
+            gamma,
+            delta,
+            eta,
+            &mut rng,
+        ),
+        Err(Error::MatrixRowCountMismatch(6, 5))
+    );
+}
+
+fn test_create_and_self_check_proof<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let mut c = a;
+    c.mul_assign(&b);
+    let v = E::ScalarField::rand(&mut rng);
+
+    let proof = create_and_self_check_proof(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        &[a, b],
+        &[c],
+        v,
+        &params,
+        &mut rng,
+    )
+    .unwrap();
+    assert!(verify_proof(&pvk, &proof, &[c]).unwrap());
+
+    // Witnesses in the wrong order
+    assert_eq!(
+        create_and_self_check_proof(
+            MySillyCircuit {
+                a: Some(a),
+                b: Some(b),
+            },
+            &[b, a],
+            &[c],
+            v,
+            &params,
+            &mut rng,
+        ),
+        Err(Error::InvalidWitnessCommitment)
+    );
+}
+
+fn test_verify_vanilla_groth16_proof<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    // Without eta and committed witnesses, the key and proof have the same shape as vanilla Groth16's
+    let (params, _) = generate_parameters::<E, _, _>(
+        MyPublicCircuit { a: None, b: None },
+        E::ScalarField::rand(&mut rng),
+        E::ScalarField::rand(&mut rng),
+        E::ScalarField::rand(&mut rng),
+        E::ScalarField::rand(&mut rng),
+        E::ScalarField::zero(),
+        &mut rng,
+    )
+    .unwrap();
+    assert!(params.vk.eta_gamma_inv_g1.is_zero());
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let mut c = a;
+    c.mul_assign(&b);
+
+    let proof = create_random_proof(
+        MyPublicCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        E::ScalarField::zero(),
+        &params,
+        &mut rng,
+    )
+    .unwrap();
+    assert!(proof.d.is_zero());
+
+    assert!(verify_vanilla_groth16_proof(&pvk, proof.a, proof.b, proof.c, &[a, b, c]).unwrap());
+    assert!(!verify_vanilla_groth16_proof(&pvk, proof.a, proof.b, proof.c, &[a, b, a]).unwrap());
+    assert!(verify_vanilla_groth16_proof(&pvk, proof.a, proof.b, proof.c, &[a, b]).is_err());
+}
+
+// Generating with the same toxic waste and rng must give the same key whether or not the
+// normalization of the queries runs in parallel
+fn test_parameters_are_deterministic<E>()
+where
+    E: Pairing,
+{
+    let generate = || {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let (alpha, beta, gamma, delta, eta) = (
+            E::ScalarField::rand(&mut rng),
+            E::ScalarField::rand(&mut rng),
+            E::ScalarField::rand(&mut rng),
+            E::ScalarField::rand(&mut rng),
+            E::ScalarField::rand(&mut rng),
+        );
+        generate_parameters::<E, _, _>(
+            MySillyCircuit { a: None, b: None },
+            alpha,
+            beta,
+            gamma,
+            delta,
+            eta,
+            &mut rng,
+        )
+        .unwrap()
+        .0
+    };
+    let params = generate();
+    assert_eq!(params, generate());
+
+    let mut rng = StdRng::seed_from_u64(1u64);
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let mut c = a;
+    c.mul_assign(&b);
+    let proof = create_random_proof(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        E::ScalarField::rand(&mut rng),
+        &params,
+        &mut rng,
+    )
+    .unwrap();
+    assert!(verify_proof(&pvk, &proof, &[c]).unwrap());
+}
+
+fn test_expected_proof_d<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let v = E::ScalarField::rand(&mut rng);
+    let proof = create_random_proof(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        v,
+        &params,
+        &mut rng,
+    )
+    .unwrap();
+
+    assert_eq!(
+        expected_proof_d(&params.vk, 1, &[a, b], &v).unwrap(),
+        proof.d
+    );
+    assert_ne!(
+        expected_proof_d(&params.vk, 1, &[b, a], &v).unwrap(),
+        proof.d
+    );
+    assert!(expected_proof_d(&params.vk, 2, &[a, b], &v).is_err());
+}
+
+fn test_empty_pedersen_bases<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    assert_eq!(
+        generate_random_parameters_with_link::<E, _, _>(
+            MySillyCircuit { a: None, b: None },
+            &[],
+            &mut rng,
+        ),
+        Err(Error::EmptyPedersenBases)
+    );
+}
+
+fn test_estimate_prover_memory<E>()
+where
+    E: Pairing,
+{
+    let small = estimate_prover_memory::<E>(6, 2, 2);
+    // `h` alone has as many field elements as the domain
+    assert!(small >= 8 * core::mem::size_of::<E::ScalarField>());
+    assert!(estimate_prover_memory::<E>(100, 2, 2) > small);
+    assert!(estimate_prover_memory::<E>(6, 10, 2) > small);
+    assert!(estimate_prover_memory::<E>(6, 2, 10) > small);
+}
+
+fn test_dual_prepared_verifying_key<E1, E2>()
+where
+    E1: Pairing,
+    E2: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params1 =
+        generate_random_parameters::<E1, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    let params2 =
+        generate_random_parameters::<E2, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    let dual_pvk = DualPreparedVerifyingKey::new(&params1.vk, &params2.vk);
+
+    let a1 = E1::ScalarField::rand(&mut rng);
+    let b1 = E1::ScalarField::rand(&mut rng);
+    let mut c1 = a1;
+    c1.mul_assign(&b1);
+    let proof1 = create_random_proof(
+        MySillyCircuit {
+            a: Some(a1),
+            b: Some(b1),
+        },
+        E1::ScalarField::rand(&mut rng),
+        &params1,
+        &mut rng,
+    )
+    .unwrap();
+
+    let a2 = E2::ScalarField::rand(&mut rng);
+    let b2 = E2::ScalarField::rand(&mut rng);
+    let mut c2 = a2;
+    c2.mul_assign(&b2);
+    let proof2 = create_random_proof(
+        MySillyCircuit {
+            a: Some(a2),
+            b: Some(b2),
+        },
+        E2::ScalarField::rand(&mut rng),
+        &params2,
+        &mut rng,
+    )
+    .unwrap();
+
+    assert!(dual_pvk.verify(DualProof::First(&proof1, &[c1])).unwrap());
+    assert!(dual_pvk.verify(DualProof::Second(&proof2, &[c2])).unwrap());
+    assert!(!dual_pvk.verify(DualProof::First(&proof1, &[a1])).unwrap());
+    assert!(!dual_pvk.verify(DualProof::Second(&proof2, &[a2])).unwrap());
+}
+
+fn test_verify_groth16_proof_with_prepared_b<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let mut c = a;
+    c.mul_assign(&b);
+    let proof = create_random_proof(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        E::ScalarField::rand(&mut rng),
+        &params,
+        &mut rng,
+    )
+    .unwrap();
+
+    let d = calculate_d(&pvk, &proof, &[c]).unwrap();
+    assert!(verify_groth16_proof(&pvk, proof.a, proof.b, proof.c, d).unwrap());
+    assert!(
+        verify_groth16_proof_with_prepared_b(&pvk, proof.a, proof.b.into(), proof.c, d).unwrap()
+    );
+
+    let wrong_d = calculate_d(&pvk, &proof, &[a]).unwrap();
+    assert!(
+        !verify_groth16_proof_with_prepared_b(&pvk, proof.a, proof.b.into(), proof.c, wrong_d)
+            .unwrap()
+    );
+}
+
+fn test_pad_committed_witnesses<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    // 2 witnesses of `MySillyCircuit` and 2 zero witnesses, plus a base for the randomness
+    let pedersen_bases = (0..5)
+        .map(|_| E::G1::rand(&mut rng).into())
+        .collect::<Vec<_>>();
+    let params_with_link = generate_random_parameters_with_link::<E, _, _>(
+        MyPaddedCircuit {
+            a: None,
+            b: None,
+            padding: 2,
+        },
+        &pedersen_bases,
+        &mut rng,
+    )
+    .unwrap();
+    let pvk_with_link = prepare_verifying_key::<E>(&params_with_link.vk.groth16_vk);
+    let block_size = params_with_link.vk.groth16_vk.commit_witness_count as usize;
+    assert_eq!(block_size, 4);
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let mut c = a;
+    c.mul_assign(&b);
+    let v = E::ScalarField::rand(&mut rng);
+    let link_v = E::ScalarField::rand(&mut rng);
+
+    let witnesses = pad_committed_witnesses(&[a, b], block_size).unwrap();
+    assert_eq!(
+        witnesses,
+        vec![a, b, E::ScalarField::zero(), E::ScalarField::zero()]
+    );
+
+    let proof_link = create_random_proof_with_link(
+        MyPaddedCircuit {
+            a: Some(a),
+            b: Some(b),
+            padding: 2,
+        },
+        v,
+        link_v,
+        &params_with_link,
+        &witnesses,
+        &mut rng,
+    )
+    .unwrap();
+    assert!(verify_commitments(
+        &params_with_link.vk,
+        &proof_link,
+        1,
+        &witnesses,
+        &v,
+        &link_v
+    )
+    .unwrap());
+    assert!(
+        verify_proof_with_link(&pvk_with_link, &params_with_link.vk, &proof_link, &[c]).unwrap()
+    );
+
+    assert_eq!(
+        pad_committed_witnesses(&[a, b, c], 2),
+        Err(Error::VectorLongerThanExpected(3, 2))
+    );
+}
+
+fn test_link_commitment_verifier<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let bases = (0..4)
+        .map(|_| E::G1::rand(&mut rng).into())
+        .collect::<Vec<E::G1Affine>>();
+    let verifier = LinkCommitmentVerifier::<E>::new(&bases);
+
+    for _ in 0..5 {
+        let witnesses = (0..3)
+            .map(|_| E::ScalarField::rand(&mut rng))
+            .collect::<Vec<_>>();
+        let link_v = E::ScalarField::rand(&mut rng);
+        let mut scalars = witnesses.clone();
+        scalars.push(link_v);
+        let link_d = bases
+            .iter()
+            .zip(scalars.iter())
+            .map(|(b, s)| b.mul(*s))
+            .sum::<E::G1>()
+            .into_affine();
+
+        assert_eq!(
+            verifier.open_eq(&witnesses, &link_v, &link_d),
+            verify_link_commitment::<E>(&bases, &link_d, &witnesses, &link_v)
+        );
+        assert!(verifier.open_eq(&witnesses, &link_v, &link_d).unwrap());
+
+        let wrong_v = E::ScalarField::rand(&mut rng);
+        assert_eq!(
+            verifier.open_eq(&witnesses, &wrong_v, &link_d),
+            verify_link_commitment::<E>(&bases, &link_d, &witnesses, &wrong_v)
+        );
+        assert!(verifier.open_eq(&witnesses, &wrong_v, &link_d).is_err());
+    }
+}
+
+// `MySillyCircuit` where only the constraint at `broken` uses a wrong public input
+struct MyBrokenCircuit<F: Field> {
+    a: Option<F>,
+    b: Option<F>,
+    broken: usize,
+}
+
+impl<ConstraintF: Field> ConstraintSynthesizer<ConstraintF> for MyBrokenCircuit<ConstraintF> {
+    fn generate_constraints(
+        self,
+        cs: ConstraintSystemRef<ConstraintF>,
+    ) -> Result<(), SynthesisError> {
+        let a = cs.new_witness_variable(|| self.a.ok_or(SynthesisError::AssignmentMissing))?;
+        let b = cs.new_witness_variable(|| self.b.ok_or(SynthesisError::AssignmentMissing))?;
+        let c = cs.new_input_variable(|| {
+            let mut a = self.a.ok_or(SynthesisError::AssignmentMissing)?;
+            let b = self.b.ok_or(SynthesisError::AssignmentMissing)?;
+
+            a.mul_assign(&b);
+            Ok(a)
+        })?;
+        let wrong_c = cs.new_input_variable(|| Ok(ConstraintF::zero()))?;
+
+        for i in 0..6 {
+            let out = if i == self.broken { wrong_c } else { c };
+            cs.enforce_constraint(lc!() + a, lc!() + b, lc!() + out)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn test_create_proof_with_report<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    // With `broken` out of range all constraints use the right public input
+    let params = generate_random_parameters::<E, _, _>(
+        MyBrokenCircuit {
+            a: None,
+            b: None,
+            broken: 6,
+        },
+        &mut rng,
+    )
+    .unwrap();
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let (r, s, v) = (
+        E::ScalarField::rand(&mut rng),
+        E::ScalarField::rand(&mut rng),
+        E::ScalarField::rand(&mut rng),
+    );
+
+    assert_eq!(
+        create_proof_with_report(
+            MyBrokenCircuit {
+                a: Some(a),
+                b: Some(b),
+                broken: 3,
+            },
+            &params.common,
+            &params.vk,
+            r,
+            s,
+            v,
+        ),
+        Err(Error::UnsatisfiedConstraint(3))
+    );
+
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+    let mut c = a;
+    c.mul_assign(&b);
+    let proof = create_proof_with_report(
+        MyBrokenCircuit {
+            a: Some(a),
+            b: Some(b),
+            broken: 6,
+        },
+        &params.common,
+        &params.vk,
+        r,
+        s,
+        v,
+    )
+    .unwrap();
+    assert!(verify_proof(&pvk, &proof, &[c, E::ScalarField::zero()]).unwrap());
+}
+
+fn test_compact_proof_serialization<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let mut c = a;
+    c.mul_assign(&b);
+
+    // Proof committing to witnesses in `d`
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+    let proof = create_random_proof(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        E::ScalarField::rand(&mut rng),
+        &params,
+        &mut rng,
+    )
+    .unwrap();
+
+    let mut bytes = Vec::new();
+    proof.serialize_compact(&mut bytes).unwrap();
+    assert_eq!(bytes.len(), proof.compressed_size() + 1);
+    let deserialized = Proof::<E>::deserialize_compact(bytes.as_slice()).unwrap();
+    assert_eq!(deserialized, proof);
+    assert!(verify_proof(&pvk, &deserialized, &[c]).unwrap());
+
+    // Proof without a commitment, so `d` is the identity and omitted
+    let (plain_params, _) = generate_parameters::<E, _, _>(
+        MyPublicCircuit { a: None, b: None },
+        E::ScalarField::rand(&mut rng),
+        E::ScalarField::rand(&mut rng),
+        E::ScalarField::rand(&mut rng),
+        E::ScalarField::rand(&mut rng),
+        E::ScalarField::zero(),
+        &mut rng,
+    )
+    .unwrap();
+    let plain_pvk = prepare_verifying_key::<E>(&plain_params.vk);
+    let plain_proof = create_random_proof(
+        MyPublicCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        E::ScalarField::zero(),
+        &plain_params,
+        &mut rng,
+    )
+    .unwrap();
+
+    let mut plain_bytes = Vec::new();
+    plain_proof.serialize_compact(&mut plain_bytes).unwrap();
+    assert_eq!(
+        plain_bytes.len(),
+        plain_proof.compressed_size() + 1 - plain_proof.d.compressed_size()
+    );
+    let deserialized = Proof::<E>::deserialize_compact(plain_bytes.as_slice()).unwrap();
+    assert_eq!(deserialized, plain_proof);
+    assert!(verify_proof(&plain_pvk, &deserialized, &[a, b, c]).unwrap());
+
+    // Unknown flag
+    let flag_pos = plain_bytes.len() - 1;
+    plain_bytes[flag_pos] = 2;
+    assert!(Proof::<E>::deserialize_compact(plain_bytes.as_slice()).is_err());
+}
+
+fn test_link_commitments_batch<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let bases = (0..4)
+        .map(|_| E::G1::rand(&mut rng).into())
+        .collect::<Vec<E::G1Affine>>();
+
+    let openings = (0..5)
+        .map(|_| {
+            let witnesses = (0..3)
+                .map(|_| E::ScalarField::rand(&mut rng))
+                .collect::<Vec<_>>();
+            let link_v = E::ScalarField::rand(&mut rng);
+            let mut scalars = witnesses.clone();
+            scalars.push(link_v);
+            let link_d = bases
+                .iter()
+                .zip(scalars.iter())
+                .map(|(b, s)| b.mul(*s))
+                .sum::<E::G1>()
+                .into_affine();
+            (link_d, witnesses, link_v)
+        })
+        .collect::<Vec<_>>();
+
+    let mut items = openings
+        .iter()
+        .map(|(link_d, witnesses, link_v)| (*link_d, witnesses.as_slice(), *link_v))
+        .collect::<Vec<_>>();
+    assert!(verify_link_commitments_batch::<E, _>(&bases, &items, &mut rng).unwrap());
+    assert_eq!(
+        verify_link_commitments_individually::<E>(&bases, &items),
+        vec![true; 5]
+    );
+
+    // Wrong randomness for the 4th opening
+    items[3].2 = E::ScalarField::rand(&mut rng);
+    assert!(!verify_link_commitments_batch::<E, _>(&bases, &items, &mut rng).unwrap());
+    assert_eq!(
+        verify_link_commitments_individually::<E>(&bases, &items),
+        vec![true, true, true, false, true]
+    );
+
+    // More witnesses than bases
+    let too_many = [E::ScalarField::zero(); 4];
+    items[0].1 = &too_many;
+    assert!(verify_link_commitments_batch::<E, _>(&bases, &items, &mut rng).is_err());
+}
+
+fn test_derive_commitment_randomness<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+    let inputs = vec![E::ScalarField::rand(&mut rng); 2];
+    let witnesses = vec![E::ScalarField::rand(&mut rng); 3];
+
+    let (v, link_v) = derive_commitment_randomness::<E, Blake2b512>(&inputs, &witnesses);
+    assert_ne!(v, link_v);
+    assert_eq!(
+        (v, link_v),
+        derive_commitment_randomness::<E, Blake2b512>(&inputs, &witnesses)
+    );
+
+    let other_inputs = vec![inputs[0], inputs[1] + E::ScalarField::one()];
+    let (v_1, link_v_1) = derive_commitment_randomness::<E, Blake2b512>(&other_inputs, &witnesses);
+    assert_ne!(v, v_1);
+    assert_ne!(link_v, link_v_1);
+
+    // Moving an element from the inputs to the witnesses changes the statement
+    let (v_2, _) = derive_commitment_randomness::<E, Blake2b512>(
+        &inputs[..1],
+        &[&inputs[1..], witnesses.as_slice()].concat(),
+    );
+    assert_ne!(v, v_2);
+
+    // A digest shorter than the modulus plus 128 bits is expanded over several blocks rather than
+    // reduced on its own, which would be biased
+    let (v_3, link_v_3) = derive_commitment_randomness::<E, Blake2s256>(&inputs, &witnesses);
+    assert_ne!(v_3, link_v_3);
+    let mut hasher = Blake2s256::new();
+    hasher.update(b"LegoGroth16-commitment-randomness");
+    hasher.update([0u8]);
+    hasher.update(0u32.to_le_bytes());
+    let mut bytes = Vec::new();
+    inputs.serialize_compressed(&mut bytes).unwrap();
+    witnesses.serialize_compressed(&mut bytes).unwrap();
+    hasher.update(&bytes);
+    assert_ne!(
+        v_3,
+        E::ScalarField::from_le_bytes_mod_order(&hasher.finalize())
+    );
+    assert_eq!(
+        (v_3, link_v_3),
+        derive_commitment_randomness::<E, Blake2s256>(&inputs, &witnesses)
+    );
+}
+
+fn test_update_delta<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+
+    let delta_ratio = E::ScalarField::rand(&mut rng);
+    let (new_params, update_proof) = params
+        .update_delta::<Blake2b512, _>(delta_ratio, &mut rng)
+        .unwrap();
+    assert_ne!(params.vk.delta_g2, new_params.vk.delta_g2);
+    assert!(update_proof.verify::<Blake2b512, _>(&params, &new_params, &mut rng));
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let c = a * b;
+    let v = E::ScalarField::rand(&mut rng);
+    let proof = create_random_proof(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        v,
+        &new_params,
+        &mut rng,
+    )
+    .unwrap();
+
+    let pvk = prepare_verifying_key::<E>(&new_params.vk);
+    assert!(verify_proof(&pvk, &proof, &[c]).unwrap());
+    let old_pvk = prepare_verifying_key::<E>(&params.vk);
+    assert!(!verify_proof(&old_pvk, &proof, &[c]).unwrap());
+
+    // Proof for a different ratio
+    let (_, other_proof) = params
+        .update_delta::<Blake2b512, _>(E::ScalarField::rand(&mut rng), &mut rng)
+        .unwrap();
+    assert!(!other_proof.verify::<Blake2b512, _>(&params, &new_params, &mut rng));
+
+    // Proof made of the public `delta` elements of the keys, without knowing the ratio
+    let forged_proof = DeltaUpdateProof::<E> {
+        s_g1: params.common.delta_g1,
+        s_delta_ratio_g1: new_params.common.delta_g1,
+        r_delta_ratio_g2: new_params.vk.delta_g2,
+    };
+    assert!(!forged_proof.verify::<Blake2b512, _>(&params, &new_params, &mut rng));
+
+    // Key with one element not rescaled
+    let mut bad_params = new_params.clone();
+    bad_params.common.h_query[0] = params.common.h_query[0];
+    assert!(!update_proof.verify::<Blake2b512, _>(&params, &bad_params, &mut rng));
+
+    assert_eq!(
+        params
+            .update_delta::<Blake2b512, _>(E::ScalarField::zero(), &mut rng)
+            .map(|_| ()),
+        Err(Error::ZeroRandomness("delta_ratio"))
+    );
+
+    // The seed of `r_g2` would be zero-padded with a 128-bit digest
+    type Blake2b128 = blake2::Blake2b<blake2::digest::consts::U16>;
+    let ratio = E::ScalarField::rand(&mut rng);
+    assert_eq!(
+        params
+            .update_delta::<Blake2b128, _>(ratio, &mut rng)
+            .map(|_| ()),
+        Err(Error::DigestTooShort(16, 32))
+    );
+    assert!(!update_proof.verify::<Blake2b128, _>(&params, &new_params, &mut rng));
+}
+
+fn test_check_link_consistency<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let pedersen_bases = (0..3)
+        .map(|_| E::G1::rand(&mut rng).into())
+        .collect::<Vec<_>>();
+    let params_with_link = generate_random_parameters_with_link::<E, _, _>(
+        MySillyCircuit { a: None, b: None },
+        &pedersen_bases,
+        &mut rng,
+    )
+    .unwrap();
+    assert!(params_with_link.vk.check_link_consistency().is_ok());
+
+    // Link commitment key with one base too many
+    let mut vk = params_with_link.vk.clone();
+    vk.link_bases.push(E::G1::rand(&mut rng).into());
+    assert_eq!(
+        vk.check_link_consistency(),
+        Err(Error::LinkKeyMismatch(3, 2))
+    );
+
+    // Link matrix for fewer witnesses than the Groth16 key commits to
+    let mut vk = params_with_link.vk.clone();
+    vk.link_pp.t -= 1;
+    assert_eq!(
+        vk.check_link_consistency(),
+        Err(Error::LinkKeyMismatch(1, 2))
+    );
+
+    // Groth16 key from a circuit with a different number of witnesses
+    let params = generate_random_parameters::<E, _, _>(
+        MyPaddedCircuit {
+            a: None,
+            b: None,
+            padding: 2,
+        },
+        &mut rng,
+    )
+    .unwrap();
+    let mut vk = params_with_link.vk.clone();
+    vk.groth16_vk = params.vk;
+    assert_eq!(
+        vk.check_link_consistency(),
+        Err(Error::LinkKeyMismatch(2, 4))
+    );
+
+    // Link matrix with a row for a third commitment
+    let mut vk = params_with_link.vk;
+    vk.link_pp.l = 3;
+    assert_eq!(
+        vk.check_link_consistency(),
+        Err(Error::MatrixRowCountMismatch(2, 3))
+    );
+    assert_eq!(vk.link_matrix(), Err(Error::MatrixRowCountMismatch(2, 3)));
+}
+
+fn test_batch_inverse<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let elems = (0..10)
+        .map(|_| E::ScalarField::rand(&mut rng))
+        .collect::<Vec<_>>();
+    let inverses = batch_inverse(&elems).unwrap();
+    assert_eq!(inverses.len(), elems.len());
+    for (e, inv) in elems.iter().zip(inverses.iter()) {
+        assert_eq!(e.inverse().unwrap(), *inv);
+    }
+
+    assert_eq!(batch_inverse::<E::ScalarField>(&[]), Some(vec![]));
+
+    let mut with_zero = elems;
+    with_zero[4] = E::ScalarField::zero();
+    assert!(batch_inverse(&with_zero).is_none());
+}
+
+fn test_verify_with_delta_updated_vk<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+
+    let delta_ratio = E::ScalarField::rand(&mut rng);
+    let (new_params, _) = params
+        .update_delta::<Blake2b512, _>(delta_ratio, &mut rng)
+        .unwrap();
+    let new_vk = params.vk.apply_delta_update(delta_ratio);
+    assert_eq!(new_vk, new_params.vk);
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let v = E::ScalarField::rand(&mut rng);
+    let proof = create_random_proof(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        v,
+        &new_params,
+        &mut rng,
+    )
+    .unwrap();
+
+    let new_pvk = prepare_verifying_key::<E>(&new_vk);
+    assert!(verify_proof(&new_pvk, &proof, &[a * b]).unwrap());
+    let old_pvk = prepare_verifying_key::<E>(&params.vk);
+    assert!(!verify_proof(&old_pvk, &proof, &[a * b]).unwrap());
+}
+
+fn test_proof_from_separate_elements<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let cs = ConstraintSystem::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+    MySillyCircuit {
+        a: Some(a),
+        b: Some(b),
+    }
+    .generate_constraints(cs.clone())
+    .unwrap();
+    cs.finalize();
+
+    let h = R1CStoQAP::witness_map::<E::ScalarField, GeneralEvaluationDomain<E::ScalarField>>(
+        cs.clone(),
+    )
+    .unwrap();
+    let prover = cs.borrow().unwrap();
+    let num_inputs = prover.instance_assignment.len();
+    let witnesses = prover
+        .witness_assignment
+        .iter()
+        .map(|w| w.into_bigint())
+        .collect::<Vec<_>>();
+    let assignment = prover.instance_assignment[1..]
+        .iter()
+        .chain(prover.witness_assignment.iter())
+        .map(|w| w.into_bigint())
+        .collect::<Vec<_>>();
+
+    let (r, s, v) = (
+        E::ScalarField::rand(&mut rng),
+        E::ScalarField::rand(&mut rng),
+        E::ScalarField::rand(&mut rng),
+    );
+    let pk = &params.common;
+    let g_a = compute_a::<E>(&pk.a_query, params.vk.alpha_g1, pk.delta_g1, r, &assignment);
+    let g1_b = compute_b_g1::<E>(&pk.b_g1_query, pk.beta_g1, pk.delta_g1, s, &assignment);
+    let g2_b = compute_b_g2::<E>(
+        &pk.b_g2_query,
+        params.vk.beta_g2,
+        params.vk.delta_g2,
+        s,
+        &assignment,
+    );
+    let g_c = compute_c::<E>(pk, g_a, g1_b, r, s, v, &h, &[]);
+    let g_d = compute_d::<E>(
+        &params.vk.gamma_abc_g1[num_inputs..num_inputs + witnesses.len()],
+        params.vk.eta_gamma_inv_g1,
+        v,
+        &witnesses,
+    );
+    let proof = Proof::<E> {
+        a: g_a.into_affine(),
+        b: g2_b.into_affine(),
+        c: g_c.into_affine(),
+        d: g_d.into_affine(),
+    };
+
+    assert!(verify_proof(&pvk, &proof, &[a * b]).unwrap());
+    assert!(verify_witness_commitment(&params.vk, &proof, 1, &[a, b], &v).is_ok());
+}
+
+fn test_witness_commitment_public_input_count<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let v = E::ScalarField::rand(&mut rng);
+    let proof = create_random_proof(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        v,
+        &params,
+        &mut rng,
+    )
+    .unwrap();
+
+    // `gamma_abc_g1` has the constant `1`, 1 public input and 2 committed witnesses
+    for count in [4, 5, usize::MAX] {
+        assert_eq!(
+            verify_witness_commitment(&params.vk, &proof, count, &[], &v),
+            Err(Error::PublicInputCountTooLarge(count, 3))
+        );
+        assert_eq!(
+            expected_proof_d(&params.vk, count, &[a, b], &v),
+            Err(Error::PublicInputCountTooLarge(count, 3))
+        );
+    }
+    assert_eq!(
+        expected_proof_d(&params.vk, 3, &[a], &v),
+        Err(Error::SynthesisError(SynthesisError::MalformedVerifyingKey))
+    );
+    assert!(verify_witness_commitment(&params.vk, &proof, 1, &[a, b], &v).unwrap());
+}
+
+fn test_proof_eq_normalized<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let (r, s, v) = (
+        E::ScalarField::rand(&mut rng),
+        E::ScalarField::rand(&mut rng),
+        E::ScalarField::rand(&mut rng),
+    );
+    let circuit = MySillyCircuit {
+        a: Some(a),
+        b: Some(b),
+    };
+    let proof = create_proof(circuit, &params.common, &params.vk, r, s, v).unwrap();
+
+    // Same proof through another prover and with `d` computed from the opening
+    let circuit = MySillyCircuit {
+        a: Some(a),
+        b: Some(b),
+    };
+    let other = create_proof_with_report(circuit, &params.common, &params.vk, r, s, v).unwrap();
+    let other = Proof::<E> {
+        d: expected_proof_d(&params.vk, 1, &[a, b], &v).unwrap(),
+        ..other
+    };
+    assert!(proof.eq_normalized(&other));
+    assert!(other.eq_normalized(&proof));
+
+    let mut different = proof.clone();
+    different.c = proof.a;
+    assert!(!proof.eq_normalized(&different));
+}
+
+fn test_verify_proof_checked<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let v = E::ScalarField::rand(&mut rng);
+    let proof = create_random_proof(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        v,
+        &params,
+        &mut rng,
+    )
+    .unwrap();
+
+    assert!(verify_proof_checked(&pvk, &proof, &[a * b]).unwrap());
+    assert!(!verify_proof_checked(&pvk, &proof, &[a]).unwrap());
+    assert!(verify_proof_checked(&pvk, &proof, &[a * b, a]).is_err());
+
+    // A point on the curve but outside the prime order subgroup
+    let off_subgroup = (0u8..)
+        .filter_map(|i| E::G1Affine::from_random_bytes(&[i; 64]))
+        .find(|p| p.check().is_err())
+        .unwrap();
+    let mut bad_proof = proof.clone();
+    bad_proof.a = off_subgroup;
+    assert_eq!(
+        verify_proof_checked(&pvk, &bad_proof, &[a * b]),
+        Err(Error::InvalidProofPoint)
+    );
+}
+
+fn test_batch_finalize_d<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+
+    let mut proofs = Vec::new();
+    let mut ds = Vec::new();
+    for _ in 0..4 {
+        let a = E::ScalarField::rand(&mut rng);
+        let b = E::ScalarField::rand(&mut rng);
+        let v = E::ScalarField::rand(&mut rng);
+        let proof = create_random_proof(
+            MySillyCircuit {
+                a: Some(a),
+                b: Some(b),
+            },
+            v,
+            &params,
+            &mut rng,
+        )
+        .unwrap();
+        let d = create_d_projective(&params.vk, 1, &[a, b], &v).unwrap();
+        assert_eq!(d.into_affine(), proof.d);
+        proofs.push(proof);
+        ds.push(d);
+    }
+
+    let mut finalized = proofs.clone();
+    for proof in finalized.iter_mut() {
+        proof.d = E::G1Affine::zero();
+    }
+    batch_finalize_d(&mut finalized, &ds);
+    assert_eq!(finalized, proofs);
+}
+
+fn test_compute_alpha_beta<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+    assert_eq!(compute_alpha_beta(&params.vk), pvk.alpha_g1_beta_g2);
+    assert!(pvk.validate().is_ok());
+
+    let mut vk = params.vk.clone();
+    vk.alpha_g1 = E::G1::rand(&mut rng).into_affine();
+    assert_ne!(compute_alpha_beta(&vk), pvk.alpha_g1_beta_g2);
+
+    // Prepared key claiming the tampered `alpha_g1`
+    let mut bad_pvk = pvk.clone();
+    bad_pvk.vk = vk;
+    assert_eq!(bad_pvk.validate(), Err(Error::InvalidPreparedVerifyingKey));
+
+    let mut bad_pvk = pvk.clone();
+    bad_pvk.delta_g2_neg_pc = params.vk.delta_g2.into();
+    assert_eq!(bad_pvk.validate(), Err(Error::InvalidPreparedVerifyingKey));
+}
+
+fn test_create_proof_with_concat_queries<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+
+    let queries = params.common.concat_g1_queries();
+    let ranges = params.common.g1_query_ranges();
+    assert_eq!(queries.len(), ranges.l.end);
+    assert_eq!(queries[ranges.a], params.common.a_query[..]);
+    assert_eq!(queries[ranges.b_g1], params.common.b_g1_query[..]);
+    assert_eq!(queries[ranges.h], params.common.h_query[..]);
+    assert_eq!(queries[ranges.l], params.common.l_query[..]);
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let v = E::ScalarField::rand(&mut rng);
+    let s = E::ScalarField::rand(&mut rng);
+    for r in [E::ScalarField::rand(&mut rng), E::ScalarField::zero()] {
+        let circuit = || MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        };
+        let proof = create_proof(circuit(), &params.common, &params.vk, r, s, v).unwrap();
+        let combined_proof = create_proof_with_concat_queries(
+            circuit(),
+            &params.common,
+            &params.vk,
+            &queries,
+            r,
+            s,
+            v,
+        )
+        .unwrap();
+        assert_eq!(proof, combined_proof);
+        assert!(verify_proof(&pvk, &combined_proof, &[a * b]).unwrap());
+    }
+
+    assert!(create_proof_with_concat_queries(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        &params.common,
+        &params.vk,
+        &queries[1..],
+        E::ScalarField::rand(&mut rng),
+        s,
+        v,
+    )
+    .is_err());
+
+    // A key without a base for every witness it commits to is rejected instead of slicing past it
+    let mut short_vk = params.vk.clone();
+    short_vk.gamma_abc_g1.pop();
+    assert!(create_proof_with_concat_queries(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        &params.common,
+        &short_vk,
+        &queries,
+        E::ScalarField::rand(&mut rng),
+        s,
+        v,
+    )
+    .is_err());
+}
+
+fn test_verify_d_only<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let v = E::ScalarField::rand(&mut rng);
+    let proof = create_random_proof(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        v,
+        &params,
+        &mut rng,
+    )
+    .unwrap();
+
+    assert!(verify_d_only(&params.vk, &proof, 1, &[a, b], &v).unwrap());
+    assert!(!verify_d_only(&params.vk, &proof, 1, &[a, a], &v).unwrap());
+    assert!(!verify_d_only(&params.vk, &proof, 1, &[a, b], &a).unwrap());
+    assert!(verify_d_only(&params.vk, &proof, 4, &[a, b], &v).is_err());
+}
+
+fn test_domain_size<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let circuit = |padding| MyPaddedCircuit {
+        a: Some(E::ScalarField::from(3u64)),
+        b: Some(E::ScalarField::from(5u64)),
+        padding,
+    };
+    for padding in [0, 1, 5, 30] {
+        let params = generate_random_parameters::<E, _, _>(circuit(padding), &mut rng).unwrap();
+
+        let cs = ConstraintSystem::new_ref();
+        cs.set_optimization_goal(OptimizationGoal::Constraints);
+        circuit(padding).generate_constraints(cs.clone()).unwrap();
+        cs.finalize();
+        let domain_size =
+            R1CStoQAP::domain_size::<E::ScalarField, GeneralEvaluationDomain<_>>(&cs).unwrap();
+        let h = R1CStoQAP::witness_map::<E::ScalarField, GeneralEvaluationDomain<_>>(cs).unwrap();
+        assert_eq!(params.common.h_query.len() + 1, domain_size);
+        assert_eq!(h.len(), domain_size);
+    }
+
+    // Proving with a key for a circuit of another domain size
+    let params = generate_random_parameters::<E, _, _>(circuit(0), &mut rng).unwrap();
+    let v = E::ScalarField::rand(&mut rng);
+    assert!(create_random_proof(circuit(30), v, &params, &mut rng).is_err());
+}
+
+#[cfg(feature = "std")]
+fn test_verify_with_shared_pvk<E>()
+where
+    E: Pairing,
+{
+    use std::{sync::Arc, thread};
+
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    let pvk = Arc::new(prepare_verifying_key::<E>(&params.vk));
+
+    let handles = (0..4)
+        .map(|_| {
+            let a = E::ScalarField::rand(&mut rng);
+            let b = E::ScalarField::rand(&mut rng);
+            let v = E::ScalarField::rand(&mut rng);
+            let proof = create_random_proof(
+                MySillyCircuit {
+                    a: Some(a),
+                    b: Some(b),
+                },
+                v,
+                &params,
+                &mut rng,
+            )
+            .unwrap();
+            let pvk = Arc::clone(&pvk);
+            thread::spawn(move || {
+                verify_proof(&pvk, &proof, &[a * b]).unwrap()
+                    && !verify_proof(&pvk, &proof, &[a]).unwrap()
+            })
+        })
+        .collect::<Vec<_>>();
+    for handle in handles {
+        assert!(handle.join().unwrap());
+    }
+    // Only the workers' handles were dropped, the key itself was never cloned
+    assert_eq!(Arc::strong_count(&pvk), 1);
+}
+
+#[cfg(feature = "debug-mapping")]
+fn test_variable_mapping<E>()
+where
+    E: Pairing,
+{
+    use crate::r1cs_to_qap::VariableBlock;
+
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let cs = ConstraintSystem::<E::ScalarField>::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+    cs.set_mode(SynthesisMode::Setup);
+    MySillyCircuit::<E::ScalarField> { a: None, b: None }
+        .generate_constraints(cs.clone())
+        .unwrap();
+    cs.finalize();
+    let matrices = cs.to_matrices().unwrap();
+
+    let t = E::ScalarField::rand(&mut rng);
+    let ((a, _, _, _, qap_num_variables, _), mapping) =
+        R1CStoQAP::instance_map_from_matrices_with_mapping::<_, GeneralEvaluationDomain<_>>(
+            &matrices, &t,
+        )
+        .unwrap();
+
+    let num_instance = cs.num_instance_variables();
+    let num_witness = cs.num_witness_variables();
+    assert_eq!((num_instance, num_witness), (2, 2));
+    let blocks = mapping
+        .iter()
+        .map(|m| (m.block, m.qap_columns.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        blocks,
+        vec![
+            (VariableBlock::One, 0..1),
+            (VariableBlock::Instance, 1..num_instance),
+            (
+                VariableBlock::CommittedWitness,
+                num_instance..num_instance + num_witness
+            ),
+            (VariableBlock::Witness, 4..4),
+        ]
+    );
+    assert_eq!(
+        mapping.last().unwrap().qap_columns.end,
+        qap_num_variables + 1
+    );
+    assert_eq!(mapping.last().unwrap().qap_columns.end, a.len());
+}
+
+fn test_create_proof_with_h_commitment<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+    let h_bases = (0..params.common.h_query.len())
+        .map(|_| E::G1::rand(&mut rng).into_affine())
+        .collect::<Vec<_>>();
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let (r, s, v) = (
+        E::ScalarField::rand(&mut rng),
+        E::ScalarField::rand(&mut rng),
+        E::ScalarField::rand(&mut rng),
+    );
+    let circuit = |a, b| MySillyCircuit {
+        a: Some(a),
+        b: Some(b),
+    };
+
+    let (proof, h_comm) = create_proof_with_h_commitment(
+        circuit(a, b),
+        &params.common,
+        &params.vk,
+        r,
+        s,
+        v,
+        &h_bases,
+    )
+    .unwrap();
+    assert_eq!(
+        proof,
+        create_proof(circuit(a, b), &params.common, &params.vk, r, s, v).unwrap()
+    );
+    assert!(verify_proof(&pvk, &proof, &[a * b]).unwrap());
+
+    // Same assignment with other proof randomness
+    let (_, h_comm_1) = create_proof_with_h_commitment(
+        circuit(a, b),
+        &params.common,
+        &params.vk,
+        s,
+        r,
+        v,
+        &h_bases,
+    )
+    .unwrap();
+    assert_eq!(h_comm, h_comm_1);
+
+    let (_, h_comm_2) = create_proof_with_h_commitment(
+        circuit(a, b + E::ScalarField::one()),
+        &params.common,
+        &params.vk,
+        r,
+        s,
+        v,
+        &h_bases,
+    )
+    .unwrap();
+    assert_ne!(h_comm, h_comm_2);
+
+    assert!(create_proof_with_h_commitment(
+        circuit(a, b),
+        &params.common,
+        &params.vk,
+        r,
+        s,
+        v,
+        &h_bases[1..],
+    )
+    .is_err());
+}
+
+fn test_identity_constant_term<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    assert!(params.vk.validate().is_ok());
+    assert_eq!(
+        prepare_verifying_key_checked(&params.vk).unwrap().vk,
+        params.vk
+    );
+
+    let mut vk = params.vk.clone();
+    vk.gamma_abc_g1[0] = E::G1Affine::zero();
+    assert_eq!(vk.validate(), Err(Error::IdentityConstantTerm));
+    assert_eq!(
+        prepare_verifying_key_checked(&vk).err(),
+        Some(Error::IdentityConstantTerm)
+    );
+
+    vk.gamma_abc_g1.clear();
+    assert_eq!(
+        prepare_verifying_key_checked(&vk).err(),
+        Some(Error::IdentityConstantTerm)
+    );
+}
+
+fn test_verify_proof_by_name<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MyPublicCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let v = E::ScalarField::rand(&mut rng);
+    let proof = create_random_proof(
+        MyPublicCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        v,
+        &params,
+        &mut rng,
+    )
+    .unwrap();
+
+    let ordering = ["a", "b", "c"].map(String::from);
+    let mut inputs = BTreeMap::new();
+    inputs.insert(String::from("c"), a * b);
+    inputs.insert(String::from("a"), a);
+    inputs.insert(String::from("b"), b);
+    assert!(verify_proof_by_name(&pvk, &proof, &inputs, &ordering).unwrap());
+
+    // Misordered
+    let swapped = ["b", "a", "c"].map(String::from);
+    assert!(!verify_proof_by_name(&pvk, &proof, &inputs, &swapped).unwrap());
+
+    inputs.insert(String::from("d"), a);
+    assert_eq!(
+        verify_proof_by_name(&pvk, &proof, &inputs, &ordering),
+        Err(Error::UnexpectedPublicInput(String::from("d")))
+    );
+
+    inputs.remove("d");
+    inputs.remove("b");
+    assert_eq!(
+        verify_proof_by_name(&pvk, &proof, &inputs, &ordering),
+        Err(Error::MissingPublicInput(String::from("b")))
+    );
+}
+
+fn test_standalone_subspace_snark<E>()
+where
+    E: Pairing,
+{
+    // Prove that `y = M * x` for a 2x5 matrix `M` of group elements, with no Groth16 involved
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let pp = PP::<E::G1Affine, E::G2Affine> {
+        l: 2,
+        t: 5,
+        g1: E::G1::rand(&mut rng).into_affine(),
+        g2: E::G2::rand(&mut rng).into_affine(),
+    };
+    let bases = (0..6)
+        .map(|_| E::G1::rand(&mut rng).into_affine())
+        .collect::<Vec<_>>();
+    let mut m = SparseMatrix::new(2, 5);
+    m.insert_row_slice(0, 0, &bases[..3]);
+    m.insert_row_slice(1, 2, &bases[3..]);
+
+    let x = (0..5)
+        .map(|_| E::ScalarField::rand(&mut rng))
+        .collect::<Vec<_>>();
+    let y = [
+        (bases[0] * x[0] + bases[1] * x[1] + bases[2] * x[2]).into_affine(),
+        (bases[3] * x[2] + bases[4] * x[3] + bases[5] * x[4]).into_affine(),
+    ];
+
+    let (ek, vk): (EK<E::G1Affine>, VK<E::G2Affine>) =
+        PESubspaceSnark::<E>::keygen(&mut rng, &pp, m);
+    let pi = PESubspaceSnark::<E>::prove(&pp, &ek, &x);
+    assert!(PESubspaceSnark::<E>::verify(&pp, &vk, &y, &pi));
+
+    let mut x_bad = x.clone();
+    x_bad[4] += E::ScalarField::one();
+    let pi_bad = PESubspaceSnark::<E>::prove(&pp, &ek, &x_bad);
+    assert!(!PESubspaceSnark::<E>::verify(&pp, &vk, &y, &pi_bad));
+}
+
+fn test_large_counts<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let v = E::ScalarField::rand(&mut rng);
+    let proof = create_random_proof(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        v,
+        &params,
+        &mut rng,
+    )
+    .unwrap();
+
+    // Counts that wrap around when 1 or the number of committed witnesses is added to them
+    for count in [
+        usize::MAX,
+        usize::MAX - 1,
+        usize::MAX - 2,
+        u32::MAX as usize,
+    ] {
+        assert_eq!(
+            create_d_projective(&params.vk, count, &[a, b], &v),
+            Err(Error::PublicInputCountTooLarge(count, 3))
+        );
+        assert!(verify_witness_commitment(&params.vk, &proof, count, &[a, b], &v).is_err());
+    }
+
+    let mut vk = params.vk.clone();
+    vk.commit_witness_count = u32::MAX;
+    assert_eq!(vk.expected_public_input_count(), 0);
+    let pvk = prepare_verifying_key::<E>(&vk);
+    assert!(verify_proof(&pvk, &proof, &[a * b]).is_err());
+    assert!(!verify_proof(&pvk, &proof, &[]).unwrap());
+}
+
+fn test_qap_density<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let rand = |rng: &mut StdRng| E::ScalarField::rand(rng);
+    let (alpha, beta, gamma, delta, eta) = (
+        rand(&mut rng),
+        rand(&mut rng),
+        rand(&mut rng),
+        rand(&mut rng),
+        rand(&mut rng),
+    );
+    let (pk, num_instance, density) = generate_parameters_with_stats::<E, _, _>(
+        MySillyCircuit { a: None, b: None },
+        alpha,
+        beta,
+        gamma,
+        delta,
+        eta,
+        &mut rng,
+    )
+    .unwrap();
+
+    assert_eq!(num_instance, 2);
+    // 1 public input and 2 witnesses
+    assert_eq!(density.qap_num_variables, 3);
+    assert!(density.non_zero_a > 0 && density.non_zero_a <= density.qap_num_variables);
+    assert!(density.non_zero_b > 0 && density.non_zero_b <= density.qap_num_variables);
+    assert_eq!(density.m_raw, pk.common.h_query.len() + 1);
+}
+
+fn test_verify_proof_with_disclosed_commitment<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let v = E::ScalarField::rand(&mut rng);
+    let proof = create_random_proof(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        v,
+        &params,
+        &mut rng,
+    )
+    .unwrap();
+
+    assert!(verify_proof_with_disclosed_commitment(&pvk, &proof, &[a * b], &[a, b], &v).is_ok());
+    assert_eq!(
+        verify_proof_with_disclosed_commitment(&pvk, &proof, &[a * b], &[b, a], &v),
+        Err(Error::InvalidWitnessCommitment)
+    );
+    assert_eq!(
+        verify_proof_with_disclosed_commitment(&pvk, &proof, &[a * b], &[a, b], &a),
+        Err(Error::InvalidWitnessCommitment)
+    );
+    assert_eq!(
+        verify_proof_with_disclosed_commitment(&pvk, &proof, &[a], &[a, b], &v),
+        Err(Error::InvalidProof)
+    );
+}
+
+fn test_versioned_verifying_key_with_link<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let pedersen_bases = (0..3)
+        .map(|_| E::G1::rand(&mut rng).into())
+        .collect::<Vec<_>>();
+    let params_with_link = generate_random_parameters_with_link::<E, _, _>(
+        MySillyCircuit { a: None, b: None },
+        &pedersen_bases,
+        &mut rng,
+    )
+    .unwrap();
+    let vk = params_with_link.vk;
+
+    let mut bytes = Vec::new();
+    vk.serialize_versioned(&mut bytes).unwrap();
+    assert_eq!(bytes[0], VerifyingKeyWithLink::<E>::SERIALIZATION_VERSION);
+    assert_eq!(
+        VerifyingKeyWithLink::<E>::deserialize_versioned(bytes.as_slice()).unwrap(),
+        vk
+    );
+
+    // The rest is the unversioned encoding
+    let mut raw = Vec::new();
+    vk.serialize_compressed(&mut raw).unwrap();
+    assert_eq!(bytes[1..], raw[..]);
+
+    bytes[0] = 0;
+    assert!(matches!(
+        VerifyingKeyWithLink::<E>::deserialize_versioned(bytes.as_slice()),
+        Err(SerializationError::InvalidData)
+    ));
+    assert!(VerifyingKeyWithLink::<E>::deserialize_versioned(raw.as_slice()).is_err());
+}
+
+#[cfg(feature = "std")]
+fn test_msm_window_tuning<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let window = crate::tune_msm_window::<E, _>(64, &mut rng);
+    assert!((1..=MAX_MSM_WINDOW).contains(&window));
+
+    let (pk, _) = generate_parameters_with_window::<E, _, _>(
+        MySillyCircuit { a: None, b: None },
+        E::ScalarField::rand(&mut rng),
+        E::ScalarField::rand(&mut rng),
+        E::ScalarField::rand(&mut rng),
+        E::ScalarField::rand(&mut rng),
+        E::ScalarField::rand(&mut rng),
+        window,
+        &mut rng,
+    )
+    .unwrap();
+    let pvk = prepare_verifying_key::<E>(&pk.vk);
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let v = E::ScalarField::rand(&mut rng);
+    let proof = create_random_proof(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        v,
+        &pk,
+        &mut rng,
+    )
+    .unwrap();
+    assert!(verify_proof(&pvk, &proof, &[a * b]).unwrap());
+
+    assert_eq!(
+        generate_parameters_with_window::<E, _, _>(
+            MySillyCircuit { a: None, b: None },
+            E::ScalarField::rand(&mut rng),
+            E::ScalarField::rand(&mut rng),
+            E::ScalarField::rand(&mut rng),
+            E::ScalarField::rand(&mut rng),
+            E::ScalarField::rand(&mut rng),
+            0,
+            &mut rng,
+        )
+        .err(),
+        Some(Error::InvalidMsmWindow(0))
+    );
+}
+
+fn test_verify_witness_commitment_with_eta<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let v = E::ScalarField::rand(&mut rng);
+    let mut proof = create_random_proof(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        v,
+        &params,
+        &mut rng,
+    )
+    .unwrap();
+
+    // Rebuild `d` as if the proof was created under a key with another eta
+    let old_eta_gamma_inv_g1 = E::G1::rand(&mut rng).into_affine();
+    proof.d = (proof.d.into_group() - params.vk.eta_gamma_inv_g1.mul(v)
+        + old_eta_gamma_inv_g1.mul(v))
+    .into_affine();
+
+    assert!(verify_witness_commitment_with_eta(
+        &params.vk,
+        &proof,
+        1,
+        &[a, b],
+        &v,
+        &old_eta_gamma_inv_g1
+    )
+    .unwrap());
+    assert!(verify_witness_commitment(&params.vk, &proof, 1, &[a, b], &v).is_err());
+    assert!(verify_witness_commitment_with_eta(
+        &params.vk,
+        &proof,
+        1,
+        &[a, b],
+        &v,
+        &params.vk.eta_gamma_inv_g1
+    )
+    .is_err());
+    assert!(verify_witness_commitment_with_eta(
+        &params.vk,
+        &proof,
+        1,
+        &[b, a],
+        &v,
+        &old_eta_gamma_inv_g1
+    )
+    .is_err());
+}
+
+fn test_verify_proof_with_link_single_pairing<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let pedersen_bases = (0..3)
+        .map(|_| E::G1::rand(&mut rng).into())
+        .collect::<Vec<_>>();
+    let params_with_link = generate_random_parameters_with_link::<E, _, _>(
+        MySillyCircuit { a: None, b: None },
+        &pedersen_bases,
+        &mut rng,
+    )
+    .unwrap();
+    let vk = &params_with_link.vk;
+    let pvk = prepare_verifying_key::<E>(&vk.groth16_vk);
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let v = E::ScalarField::rand(&mut rng);
+    let link_v = E::ScalarField::rand(&mut rng);
+    let proof = create_random_proof_with_link(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        v,
+        link_v,
+        &params_with_link,
+        &[a, b],
+        &mut rng,
+    )
+    .unwrap();
+
+    let both_agree = |proof: &ProofWithLink<E>, rng: &mut StdRng| {
+        let two_calls = verify_proof_with_link(&pvk, vk, proof, &[a * b]).unwrap();
+        let single = verify_proof_with_link_single_pairing(&pvk, vk, proof, &[a * b], rng).unwrap();
+        assert_eq!(two_calls, single);
+        single
+    };
+
+    assert!(both_agree(&proof, &mut rng));
+
+    // Tampered CP-link proof
+    let mut tampered = proof.clone();
+    tampered.link_pi = (tampered.link_pi + E::G1Affine::generator()).into_affine();
+    assert!(!both_agree(&tampered, &mut rng));
+
+    // Tampered Groth16 proof
+    let mut tampered = proof.clone();
+    tampered.groth16_proof.c = (tampered.groth16_proof.c + E::G1Affine::generator()).into_affine();
+    assert!(!both_agree(&tampered, &mut rng));
+
+    // Wrong public input
+    assert!(!verify_proof_with_link_single_pairing(&pvk, vk, &proof, &[a], &mut rng).unwrap());
+}
+
+fn test_domain_too_large<E>(max_domain_size: usize)
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    let (r, s, v) = (
+        E::ScalarField::rand(&mut rng),
+        E::ScalarField::rand(&mut rng),
+        E::ScalarField::rand(&mut rng),
+    );
+
+    // With the constant `1` the inputs need a larger domain than the field has, whichever prover
+    // is used
+    let too_large = Err(Error::DomainTooLarge {
+        requested: max_domain_size + 1,
+        max: max_domain_size,
+    });
+    let circuit = || MyUnconstrainedInputsCircuit {
+        count: max_domain_size,
+    };
+    assert_eq!(
+        create_proof_with_report(circuit(), &params.common, &params.vk, r, s, v),
+        too_large
+    );
+    assert_eq!(
+        create_proof(circuit(), &params.common, &params.vk, r, s, v),
+        too_large
+    );
+    assert_eq!(
+        create_random_proof(circuit(), v, &params, &mut rng),
+        too_large
+    );
+
+    // The generator reports it too rather than panicking
+    assert_eq!(
+        generate_random_parameters::<E, _, _>(circuit(), &mut rng).err(),
+        too_large.err()
+    );
+}
+
+fn test_proof_with_link_from_parts<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let pedersen_bases = (0..3)
+        .map(|_| E::G1::rand(&mut rng).into())
+        .collect::<Vec<_>>();
+    let params_with_link = generate_random_parameters_with_link::<E, _, _>(
+        MySillyCircuit { a: None, b: None },
+        &pedersen_bases,
+        &mut rng,
+    )
+    .unwrap();
+    let pvk = prepare_verifying_key::<E>(&params_with_link.vk.groth16_vk);
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let v = E::ScalarField::rand(&mut rng);
+    let link_v = E::ScalarField::rand(&mut rng);
+    let proof = create_random_proof_with_link(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        v,
+        link_v,
+        &params_with_link,
+        &[a, b],
+        &mut rng,
+    )
+    .unwrap();
+
+    let assembled =
+        ProofWithLink::from_parts(proof.groth16_proof.clone(), proof.link_d, proof.link_pi)
+            .unwrap();
+    assert_eq!(assembled, proof);
+    assert!(verify_proof_with_link(&pvk, &params_with_link.vk, &assembled, &[a * b]).unwrap());
+
+    let mut groth16_proof = proof.groth16_proof.clone();
+    groth16_proof.d = E::G1Affine::zero();
+    assert_eq!(
+        ProofWithLink::from_parts(groth16_proof, proof.link_d, proof.link_pi),
+        Err(Error::InvalidWitnessCommitment)
+    );
+}
+
+fn test_check_link_key_pair<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let pp = PP::<E::G1Affine, E::G2Affine> {
+        l: 2,
+        t: 5,
+        g1: E::G1::rand(&mut rng).into_affine(),
+        g2: E::G2::rand(&mut rng).into_affine(),
+    };
+    let bases = (0..6)
+        .map(|_| E::G1::rand(&mut rng).into_affine())
+        .collect::<Vec<_>>();
+    let mut m = SparseMatrix::new(2, 5);
+    m.insert_row_slice(0, 0, &bases[..3]);
+    m.insert_row_slice(1, 2, &bases[3..]);
+
+    let (ek, vk) = PESubspaceSnark::<E>::keygen(&mut rng, &pp, m.clone());
+    let (other_ek, other_vk) = PESubspaceSnark::<E>::keygen(&mut rng, &pp, m.clone());
+    assert!(PESubspaceSnark::<E>::check_key_pair(&pp, &ek, &vk, &m));
+    assert!(PESubspaceSnark::<E>::check_key_pair(
+        &pp, &other_ek, &other_vk, &m
+    ));
+
+    // Halves of different keygen calls
+    assert!(!PESubspaceSnark::<E>::check_key_pair(
+        &pp, &ek, &other_vk, &m
+    ));
+    assert!(!PESubspaceSnark::<E>::check_key_pair(
+        &pp, &other_ek, &vk, &m
+    ));
+
+    // Another matrix
+    let mut other_m = SparseMatrix::new(2, 5);
+    other_m.insert_row_slice(0, 0, &bases[..3]);
+    other_m.insert_row_slice(1, 2, &bases[..3]);
+    assert!(!PESubspaceSnark::<E>::check_key_pair(
+        &pp, &ek, &vk, &other_m
+    ));
+
+    // Keys of a proving key with link
+    let pedersen_bases = (0..3)
+        .map(|_| E::G1::rand(&mut rng).into())
+        .collect::<Vec<_>>();
+    let params_with_link = generate_random_parameters_with_link::<E, _, _>(
+        MySillyCircuit { a: None, b: None },
+        &pedersen_bases,
+        &mut rng,
+    )
+    .unwrap();
+    let mut ek = params_with_link.link_ek.clone();
+    let vk = &params_with_link.vk;
+    let link_m = vk.link_matrix().unwrap();
+    assert!(PESubspaceSnark::<E>::check_key_pair(
+        &vk.link_pp,
+        &ek,
+        &vk.link_vk,
+        &link_m
+    ));
+    ek.p.swap(0, 1);
+    assert!(!PESubspaceSnark::<E>::check_key_pair(
+        &vk.link_pp,
+        &ek,
+        &vk.link_vk,
+        &link_m
+    ));
+}
+
+// `num_inputs` public inputs, each only constrained by `x * 1 = x`
+struct MyManyInputsCircuit<F: Field> {
+    inputs: Vec<Option<F>>,
+}
+
+impl<ConstraintF: Field> ConstraintSynthesizer<ConstraintF> for MyManyInputsCircuit<ConstraintF> {
+    fn generate_constraints(
+        self,
+        cs: ConstraintSystemRef<ConstraintF>,
+    ) -> Result<(), SynthesisError> {
+        for input in self.inputs {
+            let x = cs.new_input_variable(|| input.ok_or(SynthesisError::AssignmentMissing))?;
+            cs.enforce_constraint(lc!() + x, lc!() + Variable::One, lc!() + x)?;
+        }
+        Ok(())
+    }
+}
+
+// Only public inputs, all zero, and no constraints: the cheapest circuit with a large QAP domain
+struct MyUnconstrainedInputsCircuit {
+    count: usize,
+}
+
+impl<ConstraintF: Field> ConstraintSynthesizer<ConstraintF> for MyUnconstrainedInputsCircuit {
+    fn generate_constraints(
+        self,
+        cs: ConstraintSystemRef<ConstraintF>,
+    ) -> Result<(), SynthesisError> {
+        for _ in 0..self.count {
+            cs.new_input_variable(|| Ok(ConstraintF::zero()))?;
+        }
+        Ok(())
+    }
+}
+
+// `MySillyCircuit` with the product `c` as a witness, so without public inputs
+struct MyNoInputsCircuit<F: Field> {
+    a: Option<F>,
+    b: Option<F>,
+}
+
+impl<ConstraintF: Field> ConstraintSynthesizer<ConstraintF> for MyNoInputsCircuit<ConstraintF> {
+    fn generate_constraints(
+        self,
+        cs: ConstraintSystemRef<ConstraintF>,
+    ) -> Result<(), SynthesisError> {
+        let a = cs.new_witness_variable(|| self.a.ok_or(SynthesisError::AssignmentMissing))?;
+        let b = cs.new_witness_variable(|| self.b.ok_or(SynthesisError::AssignmentMissing))?;
+        let c = cs.new_witness_variable(|| {
+            let a = self.a.ok_or(SynthesisError::AssignmentMissing)?;
+            let b = self.b.ok_or(SynthesisError::AssignmentMissing)?;
+            Ok(a * b)
+        })?;
+        cs.enforce_constraint(lc!() + a, lc!() + b, lc!() + c)?;
+        Ok(())
+    }
+}
+
+fn test_prepare_inputs_sparse<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let num_inputs = 50;
+    let params = generate_random_parameters::<E, _, _>(
+        MyManyInputsCircuit {
+            inputs: vec![None; num_inputs],
+        },
+        &mut rng,
+    )
+    .unwrap();
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+
+    let mut inputs = vec![E::ScalarField::zero(); num_inputs];
+    let sparse = [
+        (0, E::ScalarField::rand(&mut rng)),
+        (17, E::ScalarField::rand(&mut rng)),
+        (30, E::ScalarField::zero()),
+        (49, E::ScalarField::rand(&mut rng)),
+    ];
+    for (i, value) in sparse {
+        inputs[i] = value;
+    }
+
+    let dense = prepare_inputs(&pvk, &inputs).unwrap();
+    assert_eq!(prepare_inputs_sparse(&pvk, &sparse).unwrap(), dense);
+    // All-zero inputs leave only the constant term
+    assert_eq!(
+        prepare_inputs_sparse(&pvk, &[]).unwrap(),
+        params.vk.gamma_abc_g1[0].into_group()
+    );
+
+    let v = E::ScalarField::rand(&mut rng);
+    let proof = create_random_proof(
+        MyManyInputsCircuit {
+            inputs: inputs.iter().map(|x| Some(*x)).collect(),
+        },
+        v,
+        &params,
+        &mut rng,
+    )
+    .unwrap();
+    let d = (prepare_inputs_sparse(&pvk, &sparse).unwrap() + proof.d).into_affine();
+    assert!(verify_groth16_proof(&pvk, proof.a, proof.b, proof.c, d).unwrap());
+
+    assert_eq!(
+        prepare_inputs_sparse(&pvk, &[(num_inputs, E::ScalarField::one())]),
+        Err(Error::PublicInputIndexOutOfRange(num_inputs, num_inputs))
+    );
+}
+
+fn test_truncate_commitment<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    // `a`, `b` and 2 zero witnesses are committed
+    let pedersen_bases = (0..5)
+        .map(|_| E::G1::rand(&mut rng).into())
+        .collect::<Vec<_>>();
+    let params_with_link = generate_random_parameters_with_link::<E, _, _>(
+        MyPaddedCircuit {
+            a: None,
+            b: None,
+            padding: 2,
+        },
+        &pedersen_bases,
+        &mut rng,
+    )
+    .unwrap();
+    assert_eq!(params_with_link.vk.groth16_vk.commit_witness_count, 4);
+
+    let truncated = params_with_link.truncate_commitment(2, &mut rng).unwrap();
+    assert_eq!(truncated.vk.groth16_vk.commit_witness_count, 2);
+    assert_eq!(
+        truncated.vk.groth16_vk.gamma_abc_g1.len(),
+        params_with_link.vk.groth16_vk.gamma_abc_g1.len() - 2
+    );
+    assert_eq!(truncated.vk.link_bases.len(), 3);
+    assert_eq!(truncated.vk.link_pp.t, 4);
+    assert!(truncated.vk.check_link_consistency().is_ok());
+    assert!(
+        truncated.serialized_size(ark_serialize::Compress::Yes)
+            < params_with_link.serialized_size(ark_serialize::Compress::Yes)
+    );
+
+    let pvk = prepare_verifying_key::<E>(&truncated.vk.groth16_vk);
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let circuit = || MyPaddedCircuit {
+        a: Some(a),
+        b: Some(b),
+        padding: 2,
+    };
+    let v = E::ScalarField::rand(&mut rng);
+    let link_v = E::ScalarField::rand(&mut rng);
+    let proof =
+        create_random_proof_with_link(circuit(), v, link_v, &truncated, &[a, b], &mut rng).unwrap();
+    assert!(verify_proof_with_link(&pvk, &truncated.vk, &proof, &[a * b]).unwrap());
+    assert!(verify_commitments(&truncated.vk, &proof, 1, &[a, b], &v, &link_v).unwrap());
+
+    // The concatenated queries give the same proof for the truncated key
+    let (r, s) = (
+        E::ScalarField::rand(&mut rng),
+        E::ScalarField::rand(&mut rng),
+    );
+    let groth16_vk = &truncated.vk.groth16_vk;
+    let queries = truncated.common.concat_g1_queries();
+    assert_eq!(
+        create_proof_with_concat_queries(
+            circuit(),
+            &truncated.common,
+            groth16_vk,
+            &queries,
+            r,
+            s,
+            v
+        )
+        .unwrap(),
+        create_proof(circuit(), &truncated.common, groth16_vk, r, s, v).unwrap()
+    );
+
+    // `b` is not zero so it can't be dropped, whichever prover is used
+    let truncated = params_with_link.truncate_commitment(1, &mut rng).unwrap();
+    let groth16_vk = &truncated.vk.groth16_vk;
+    assert_eq!(
+        create_random_proof_with_link(circuit(), v, link_v, &truncated, &[a], &mut rng),
+        Err(Error::UncommittedWitnessNotZero(1))
+    );
+    assert_eq!(
+        create_proof(circuit(), &truncated.common, groth16_vk, r, s, v),
+        Err(Error::UncommittedWitnessNotZero(1))
+    );
+    let queries = truncated.common.concat_g1_queries();
+    assert_eq!(
+        create_proof_with_concat_queries(
+            circuit(),
+            &truncated.common,
+            groth16_vk,
+            &queries,
+            r,
+            s,
+            v
+        ),
+        Err(Error::UncommittedWitnessNotZero(1))
+    );
+
+    assert_eq!(
+        params_with_link.truncate_commitment(5, &mut rng).err(),
+        Some(Error::VectorLongerThanExpected(5, 4))
+    );
+}
+
+// A gate of `RandomCircuit` enforcing `a * b = out` for linear combinations `a` and `b` of the
+// variables allocated before it and a new variable `out`, public if `public` is set
+#[derive(Clone)]
+struct RandomGate<F> {
+    a: Vec<(F, usize)>,
+    b: Vec<(F, usize)>,
+    public: bool,
+}
+
+// Satisfiable circuit of random gates. Variable `0` is the constant `1`, followed by the free
+// witnesses and then by the gate outputs.
+#[derive(Clone)]
+struct RandomCircuit<F: Field> {
+    free_witnesses: Vec<Option<F>>,
+    gates: Vec<RandomGate<F>>,
+}
+
+impl<F: PrimeField> RandomCircuit<F> {
+    fn sample<R: Rng>(rng: &mut R) -> Self {
+        let free_witnesses = (0..rng.gen_range(1..5))
+            .map(|_| Some(F::rand(rng)))
+            .collect::<Vec<_>>();
+        let mut num_variables = 1 + free_witnesses.len();
+        let gates = (0..rng.gen_range(free_witnesses.len()..10))
+            .map(|j| {
+                let mut lc = || {
+                    (0..rng.gen_range(1..4))
+                        .map(|_| (F::rand(rng), rng.gen_range(0..num_variables)))
+                        .collect::<Vec<_>>()
+                };
+                let mut gate = RandomGate {
+                    a: lc(),
+                    b: lc(),
+                    public: rng.gen_bool(0.3),
+                };
+                // A witness in no constraint has the identity as base in `d` so it would not be
+                // bound by the commitment
+                if j < free_witnesses.len() {
+                    gate.a.push((F::rand(rng), 1 + j));
+                }
+                num_variables += 1;
+                gate
+            })
+            .collect();
+        Self {
+            free_witnesses,
+            gates,
+        }
+    }
+
+    fn without_values(&self) -> Self {
+        Self {
+            free_witnesses: vec![None; self.free_witnesses.len()],
+            gates: self.gates.clone(),
+        }
+    }
+
+    // Values of all variables, starting with the constant `1`
+    fn values(&self) -> Vec<F> {
+        let mut values = vec![F::one()];
+        values.extend(self.free_witnesses.iter().map(|w| w.unwrap()));
+        for gate in &self.gates {
+            let eval = |lc: &[(F, usize)]| lc.iter().map(|(c, i)| *c * values[*i]).sum::<F>();
+            let out = eval(&gate.a) * eval(&gate.b);
+            values.push(out);
+        }
+        values
+    }
+
+    // The public inputs and the (committed) witnesses in allocation order
+    fn public_inputs_and_witnesses(&self) -> (Vec<F>, Vec<F>) {
+        let values = self.values();
+        let outputs = &values[1 + self.free_witnesses.len()..];
+        let mut inputs = vec![];
+        let mut witnesses = values[1..1 + self.free_witnesses.len()].to_vec();
+        for (gate, out) in self.gates.iter().zip(outputs) {
+            if gate.public {
+                inputs.push(*out);
+            } else {
+                witnesses.push(*out);
+            }
+        }
+        (inputs, witnesses)
+    }
+}
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for RandomCircuit<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let values = self
+            .free_witnesses
+            .iter()
+            .all(|w| w.is_some())
+            .then(|| self.values());
+        let value = |i: usize| {
+            values
+                .as_ref()
+                .map(|values| values[i])
+                .ok_or(SynthesisError::AssignmentMissing)
+        };
+
+        let mut variables = vec![Variable::One];
+        for i in 0..self.free_witnesses.len() {
+            variables.push(cs.new_witness_variable(|| value(1 + i))?);
+        }
+        for gate in &self.gates {
+            let lc = |terms: &[(F, usize)]| {
+                terms
+                    .iter()
+                    .fold(lc!(), |lc, (c, i)| lc + (*c, variables[*i]))
+            };
+            let (a, b) = (lc(&gate.a), lc(&gate.b));
+            let index = variables.len();
+            let out = if gate.public {
+                cs.new_input_variable(|| value(index))?
+            } else {
+                cs.new_witness_variable(|| value(index))?
+            };
+            cs.enforce_constraint(a, b, lc!() + out)?;
+            variables.push(out);
+        }
+        Ok(())
+    }
+}
+
+// Prove and verify over many random circuits with random splits between public inputs and committed
+// witnesses, and check that a wrong public input or committed witness is always rejected
+fn test_random_circuits<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    for _ in 0..200 {
+        let circuit = RandomCircuit::<E::ScalarField>::sample(&mut rng);
+        let (inputs, witnesses) = circuit.public_inputs_and_witnesses();
+
+        let params =
+            generate_random_parameters::<E, _, _>(circuit.without_values(), &mut rng).unwrap();
+        assert_eq!(params.vk.commit_witness_count as usize, witnesses.len());
+        assert_eq!(params.vk.expected_public_input_count(), inputs.len());
+        assert!(params.vk.gamma_abc_g1.iter().all(|base| !base.is_zero()));
+        let pvk = prepare_verifying_key::<E>(&params.vk);
+
+        let v = E::ScalarField::rand(&mut rng);
+        let proof = create_random_proof(circuit, v, &params, &mut rng).unwrap();
+        assert!(verify_proof(&pvk, &proof, &inputs).unwrap());
+        assert!(
+            verify_witness_commitment(&params.vk, &proof, inputs.len(), &witnesses, &v).unwrap()
+        );
+
+        if !inputs.is_empty() {
+            let mut tampered = inputs.clone();
+            let i = rng.gen_range(0..tampered.len());
+            tampered[i] += E::ScalarField::one();
+            assert!(!verify_proof(&pvk, &proof, &tampered).unwrap());
+        }
+
+        let mut tampered = witnesses.clone();
+        let i = rng.gen_range(0..tampered.len());
+        tampered[i] += E::ScalarField::one();
+        assert!(
+            verify_witness_commitment(&params.vk, &proof, inputs.len(), &tampered, &v).is_err()
+        );
+    }
+}
+
+fn test_prove_with_zero_r<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let s = E::ScalarField::rand(&mut rng);
+    let v = E::ScalarField::rand(&mut rng);
+    let r = E::ScalarField::zero();
+    let circuit = || MySillyCircuit {
+        a: Some(a),
+        b: Some(b),
+    };
+
+    // Deterministic when the randomness is fixed
+    let proof = create_proof(circuit(), &params.common, &params.vk, r, s, v).unwrap();
+    assert_eq!(
+        proof,
+        create_proof(circuit(), &params.common, &params.vk, r, s, v).unwrap()
+    );
+    assert!(verify_proof(&pvk, &proof, &[a * b]).unwrap());
+
+    // `C` is the same whether or not B in G1 was computed
+    let cs = ConstraintSystem::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+    circuit().generate_constraints(cs.clone()).unwrap();
+    cs.finalize();
+    let h = R1CStoQAP::witness_map::<E::ScalarField, GeneralEvaluationDomain<E::ScalarField>>(
+        cs.clone(),
+    )
+    .unwrap();
+    let prover = cs.borrow().unwrap();
+    let assignment = prover.instance_assignment[1..]
+        .iter()
+        .chain(prover.witness_assignment.iter())
+        .map(|w| w.into_bigint())
+        .collect::<Vec<_>>();
+    let pk = &params.common;
+    let g_a = compute_a::<E>(&pk.a_query, params.vk.alpha_g1, pk.delta_g1, r, &assignment);
+    let g1_b = compute_b_g1::<E>(&pk.b_g1_query, pk.beta_g1, pk.delta_g1, s, &assignment);
+    let g_c = compute_c::<E>(pk, g_a, g1_b, r, s, v, &h, &[]);
+    assert_eq!(
+        g_c,
+        compute_c::<E>(pk, g_a, E::G1::zero(), r, s, v, &h, &[])
+    );
+    assert_eq!(g_c.into_affine(), proof.c);
+}
+
+fn test_verify_proof_with_link_prepared_inputs<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let pedersen_bases = (0..3)
+        .map(|_| E::G1::rand(&mut rng).into())
+        .collect::<Vec<_>>();
+    let params_with_link = generate_random_parameters_with_link::<E, _, _>(
+        MySillyCircuit { a: None, b: None },
+        &pedersen_bases,
+        &mut rng,
+    )
+    .unwrap();
+    let vk = &params_with_link.vk;
+    let pvk = prepare_verifying_key::<E>(&vk.groth16_vk);
+
+    // Different factorizations of the same public `c`
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let c = a * b;
+    let prepared_inputs = prepare_inputs(&pvk, &[c]).unwrap();
+    for k in [
+        E::ScalarField::one(),
+        E::ScalarField::rand(&mut rng),
+        E::ScalarField::rand(&mut rng),
+    ] {
+        let (a, b) = (a * k, b * k.inverse().unwrap());
+        let v = E::ScalarField::rand(&mut rng);
+        let link_v = E::ScalarField::rand(&mut rng);
+        let proof = create_random_proof_with_link(
+            MySillyCircuit {
+                a: Some(a),
+                b: Some(b),
+            },
+            v,
+            link_v,
+            &params_with_link,
+            &[a, b],
+            &mut rng,
+        )
+        .unwrap();
+        assert!(verify_proof_with_link(&pvk, vk, &proof, &[c]).unwrap());
+        assert!(
+            verify_proof_with_link_prepared_inputs(&pvk, vk, &proof, &prepared_inputs).unwrap()
+        );
+
+        let mut tampered = proof.clone();
+        tampered.link_pi = (tampered.link_pi + E::G1Affine::generator()).into_affine();
+        assert!(
+            !verify_proof_with_link_prepared_inputs(&pvk, vk, &tampered, &prepared_inputs).unwrap()
+        );
+    }
+
+    let other_inputs = prepare_inputs(&pvk, &[a]).unwrap();
+    let v = E::ScalarField::rand(&mut rng);
+    let proof = create_random_proof_with_link(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        v,
+        v,
+        &params_with_link,
+        &[a, b],
+        &mut rng,
+    )
+    .unwrap();
+    assert!(!verify_proof_with_link_prepared_inputs(&pvk, vk, &proof, &other_inputs).unwrap());
+}
+
+fn test_verify_proofs_batch_dedup<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+
+    let statements = (0..3)
+        .map(|_| {
+            let a = E::ScalarField::rand(&mut rng);
+            let b = E::ScalarField::rand(&mut rng);
+            let v = E::ScalarField::rand(&mut rng);
+            let proof = create_random_proof(
+                MySillyCircuit {
+                    a: Some(a),
+                    b: Some(b),
+                },
+                v,
+                &params,
+                &mut rng,
+            )
+            .unwrap();
+            (proof, vec![a * b])
+        })
+        .collect::<Vec<_>>();
+    let items = statements
+        .iter()
+        .map(|(proof, inputs)| (proof, inputs.as_slice()))
+        .collect::<Vec<_>>();
+
+    assert!(verify_proofs_batch(&pvk, &items, true, &mut rng).unwrap());
+    assert!(verify_proofs_batch(&pvk, &items, false, &mut rng).unwrap());
+    assert!(verify_proofs_batch(&pvk, &[], true, &mut rng).unwrap());
+
+    // The first proof submitted twice
+    let mut with_duplicate = items.clone();
+    with_duplicate.push(items[0]);
+    assert_eq!(
+        verify_proofs_batch(&pvk, &with_duplicate, true, &mut rng),
+        Err(Error::DuplicateProof(3))
+    );
+    assert!(verify_proofs_batch(&pvk, &with_duplicate, false, &mut rng).unwrap());
+
+    // An invalid proof or a wrong input fails the whole batch
+    let mut bad_inputs = items.clone();
+    bad_inputs[1].1 = &statements[2].1;
+    assert!(!verify_proofs_batch(&pvk, &bad_inputs, false, &mut rng).unwrap());
+    let mut bad_proof = statements[1].0.clone();
+    bad_proof.c = (bad_proof.c + E::G1Affine::generator()).into_affine();
+    let mut with_bad_proof = items.clone();
+    with_bad_proof[1].0 = &bad_proof;
+    assert!(!verify_proofs_batch(&pvk, &with_bad_proof, true, &mut rng).unwrap());
+}
+
+fn test_proof_coordinates<E, P1, P2>()
+where
+    E: Pairing<G1Affine = SWAffine<P1>, G2Affine = SWAffine<P2>>,
+    P1: SWCurveConfig,
+    P2: SWCurveConfig,
+    P2::BaseField: Field<BasePrimeField = P1::BaseField>,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let v = E::ScalarField::rand(&mut rng);
+    let proof = create_random_proof(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        v,
+        &params,
+        &mut rng,
+    )
+    .unwrap();
+
+    let coords = proof.to_coordinates();
+    assert_eq!(coords.a, Some([proof.a.x, proof.a.y]));
+    assert_eq!(coords.b.as_ref().unwrap().len(), 4);
+    assert_eq!(Proof::<E>::from_coordinates(&coords).unwrap(), proof);
+
+    // The point at infinity
+    let mut with_identity = proof.clone();
+    with_identity.d = E::G1Affine::zero();
+    let coords = with_identity.to_coordinates();
+    assert_eq!(coords.d, None);
+    assert_eq!(
+        Proof::<E>::from_coordinates(&coords).unwrap(),
+        with_identity
+    );
+
+    // Off-curve points
+    let mut coords = proof.to_coordinates();
+    coords.c.as_mut().unwrap()[1] += P1::BaseField::one();
+    assert_eq!(
+        Proof::<E>::from_coordinates(&coords),
+        Err(Error::InvalidProofPoint)
+    );
+    let mut coords = proof.to_coordinates();
+    coords.b.as_mut().unwrap()[3] += P1::BaseField::one();
+    assert_eq!(
+        Proof::<E>::from_coordinates(&coords),
+        Err(Error::InvalidProofPoint)
+    );
+    let mut coords = proof.to_coordinates();
+    coords.b.as_mut().unwrap().pop();
+    assert_eq!(
+        Proof::<E>::from_coordinates(&coords),
+        Err(Error::InvalidProofPoint)
+    );
+}
+
+fn test_link_blinding_base<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let witness_bases = (0..2)
+        .map(|_| E::G1::rand(&mut rng).into())
+        .collect::<Vec<_>>();
+    let blinding_base = E::G1::rand(&mut rng).into_affine();
+    let params_with_link = generate_random_parameters_with_link_blinding_base::<E, _, _>(
+        MySillyCircuit { a: None, b: None },
+        &witness_bases,
+        Some(blinding_base),
+        &mut rng,
+    )
+    .unwrap();
+    let vk = &params_with_link.vk;
+    assert_eq!(
+        vk.link_bases,
+        [witness_bases[0], witness_bases[1], blinding_base]
+    );
+    assert!(vk.check_link_consistency().is_ok());
+    let pvk = prepare_verifying_key::<E>(&vk.groth16_vk);
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let v = E::ScalarField::rand(&mut rng);
+    let link_v = E::ScalarField::rand(&mut rng);
+    let proof = create_random_proof_with_link(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        v,
+        link_v,
+        &params_with_link,
+        &[a, b],
+        &mut rng,
+    )
+    .unwrap();
+    assert_eq!(
+        proof.link_d,
+        (witness_bases[0] * a + witness_bases[1] * b + blinding_base * link_v).into_affine()
+    );
+    assert!(verify_link_commitment::<E>(&vk.link_bases, &proof.link_d, &[a, b], &link_v).unwrap());
+    assert!(verify_proof_with_link(&pvk, vk, &proof, &[a * b]).unwrap());
+
+    // Without a separate base, the last of `pedersen_bases` is used for the randomness
+    let params_with_link = generate_random_parameters_with_link_blinding_base::<E, _, _>(
+        MySillyCircuit { a: None, b: None },
+        &vk.link_bases,
+        None,
+        &mut rng,
+    )
+    .unwrap();
+    assert_eq!(params_with_link.vk.link_bases, vk.link_bases);
+}
+
+fn test_proving_key_element_count<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+
+    // 4 variables (`1`, `c`, `a`, `b`) and a domain of 8 for 6 constraints and 2 instance variables
+    assert_eq!(params.common.a_query.len(), 4);
+    assert_eq!(params.common.b_g1_query.len(), 4);
+    assert_eq!(params.common.b_g2_query.len(), 4);
+    assert_eq!(params.common.h_query.len(), 7);
+    assert_eq!(params.common.l_query.len(), 0);
+    assert_eq!(params.vk.gamma_abc_g1.len(), 4);
+    assert_eq!(params.element_count(), (5 + 4 + 4 + 4 + 7, 3 + 4));
+
+    let g1_size = E::G1Affine::zero().compressed_size();
+    let g2_size = E::G2Affine::zero().compressed_size();
+    let (g1, g2) = params.element_count();
+    assert!(params.compressed_size() >= g1 * g1_size + g2 * g2_size);
+}
+
+fn test_shared_commitment<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+
+    // Both proofs commit to `(a, b)` with the same `a`
+    let a = E::ScalarField::rand(&mut rng);
+    let b1 = E::ScalarField::rand(&mut rng);
+    let b2 = E::ScalarField::rand(&mut rng);
+    let v1 = E::ScalarField::rand(&mut rng);
+    let v2 = E::ScalarField::rand(&mut rng);
+    let mut prove = |b, v| {
+        create_random_proof(
+            MySillyCircuit {
+                a: Some(a),
+                b: Some(b),
+            },
+            v,
+            &params,
+            &mut rng,
+        )
+        .unwrap()
+    };
+    let proof1 = prove(b1, v1);
+    let proof2 = prove(b2, v2);
+    assert!(verify_proof(&pvk, &proof1, &[a * b1]).unwrap());
+    assert!(verify_proof(&pvk, &proof2, &[a * b2]).unwrap());
+
+    let shared_proof = create_shared_commitment_proof::<E, Blake2b512, _>(
+        &params.vk,
+        1,
+        &[0],
+        (&proof1, &[a, b1], v1),
+        (&proof2, &[a, b2], v2),
+        &mut rng,
+    )
+    .unwrap();
+    assert_eq!(shared_proof.responses.len(), 2);
+    assert!(verify_shared_commitment::<E, Blake2b512>(
+        &params.vk,
+        1,
+        &[0],
+        &proof1,
+        &proof2,
+        &shared_proof
+    )
+    .unwrap());
+
+    // The proof is for the shared position only
+    assert!(!verify_shared_commitment::<E, Blake2b512>(
+        &params.vk,
+        1,
+        &[1],
+        &proof1,
+        &proof2,
+        &shared_proof
+    )
+    .unwrap());
+    assert!(!verify_shared_commitment::<E, Blake2b512>(
+        &params.vk,
+        1,
+        &[0],
+        &proof1,
+        &proof1,
+        &shared_proof
+    )
+    .unwrap());
+
+    // The proof is bound to the bases of the key it was created for
+    let mut other_vk = params.vk.clone();
+    other_vk.eta_gamma_inv_g1 = E::G1::rand(&mut rng).into_affine();
+    assert!(!verify_shared_commitment::<E, Blake2b512>(
+        &other_vk,
+        1,
+        &[0],
+        &proof1,
+        &proof2,
+        &shared_proof
+    )
+    .unwrap());
+
+    // `b` differs so a proof claiming both witnesses are shared doesn't verify
+    let shared_proof = create_shared_commitment_proof::<E, Blake2b512, _>(
+        &params.vk,
+        1,
+        &[0, 1],
+        (&proof1, &[a, b1], v1),
+        (&proof2, &[a, b2], v2),
+        &mut rng,
+    )
+    .unwrap();
+    assert!(!verify_shared_commitment::<E, Blake2b512>(
+        &params.vk,
+        1,
+        &[0, 1],
+        &proof1,
+        &proof2,
+        &shared_proof
+    )
+    .unwrap());
+
+    assert_eq!(
+        verify_shared_commitment::<E, Blake2b512>(
+            &params.vk,
+            1,
+            &[2],
+            &proof1,
+            &proof2,
+            &shared_proof
+        ),
+        Err(Error::CommittedWitnessIndexOutOfRange(2, 2))
+    );
+    assert_eq!(
+        create_shared_commitment_proof::<E, Blake2b512, _>(
+            &params.vk,
+            1,
+            &[0],
+            (&proof1, &[a], v1),
+            (&proof2, &[a, b2], v2),
+            &mut rng,
+        ),
+        Err(Error::InsufficientWitnessesForCommitment(1, 2))
+    );
+}
+
+fn test_commitment_bases<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let v = E::ScalarField::rand(&mut rng);
+    let proof = create_random_proof(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        v,
+        &params,
+        &mut rng,
+    )
+    .unwrap();
+
+    let (bases, eta) = params.vk.commitment_bases(1, 2).unwrap();
+    assert_eq!(bases, &params.vk.gamma_abc_g1[2..4]);
+    assert_eq!(eta, params.vk.eta_gamma_inv_g1);
+    let d = bases[0] * a + bases[1] * b + eta * v;
+    assert_eq!(d.into_affine(), proof.d);
+
+    // A prefix of the committed block
+    let (bases, _) = params.vk.commitment_bases(1, 1).unwrap();
+    assert_eq!(bases, &params.vk.gamma_abc_g1[2..3]);
+
+    assert!(matches!(
+        params.vk.commitment_bases(1, 3),
+        Err(Error::SynthesisError(SynthesisError::MalformedVerifyingKey))
+    ));
+    assert!(matches!(
+        params.vk.commitment_bases(4, 0),
+        Err(Error::PublicInputCountTooLarge(4, 3))
+    ));
+    assert!(matches!(
+        params.vk.commitment_bases(usize::MAX, 0),
+        Err(Error::PublicInputCountTooLarge(_, 3))
+    ));
+}
+
+fn test_reusable_proof<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let c = a * b;
+    let r = E::ScalarField::rand(&mut rng);
+    let s = E::ScalarField::rand(&mut rng);
+    let (partial, ctx) = create_proof_reusable(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        &params.common,
+        &params.vk,
+        r,
+        s,
+    )
+    .unwrap();
+
+    let v1 = E::ScalarField::rand(&mut rng);
+    let v2 = E::ScalarField::rand(&mut rng);
+    let proof1 = finalize_proof(&partial, &ctx, v1);
+    let proof2 = finalize_proof(&partial, &ctx, v2);
+    assert_ne!(proof1.d, proof2.d);
+    for (proof, v) in [(&proof1, v1), (&proof2, v2)] {
+        assert!(verify_proof(&pvk, proof, &[c]).unwrap());
+        assert!(verify_witness_commitment(&params.vk, proof, 1, &[a, b], &v).unwrap());
+    }
+
+    // Same as proving from scratch with the same randomness
+    let proof = create_proof(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        &params.common,
+        &params.vk,
+        r,
+        s,
+        v1,
+    )
+    .unwrap();
+    assert_eq!(proof, proof1);
+}
+
+fn test_supports_commitment<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let pedersen_bases = (0..3)
+        .map(|_| E::G1::rand(&mut rng).into())
+        .collect::<Vec<_>>();
+    let params_with_link = generate_random_parameters_with_link::<E, _, _>(
+        MySillyCircuit { a: None, b: None },
+        &pedersen_bases,
+        &mut rng,
+    )
+    .unwrap();
+    assert!(params_with_link.vk.groth16_vk.supports_commitment());
+
+    let rand = |rng: &mut StdRng| E::ScalarField::rand(rng);
+    let (alpha, beta, gamma, delta) = (
+        rand(&mut rng),
+        rand(&mut rng),
+        rand(&mut rng),
+        rand(&mut rng),
+    );
+    let (params, _) = generate_parameters::<E, _, _>(
+        MySillyCircuit { a: None, b: None },
+        alpha,
+        beta,
+        gamma,
+        delta,
+        E::ScalarField::zero(),
+        &mut rng,
+    )
+    .unwrap();
+    assert!(!params.vk.supports_commitment());
+
+    // Without `eta`, `v` does not change `d`
+    let a = rand(&mut rng);
+    let b = rand(&mut rng);
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+    let proofs = (0..2)
+        .map(|_| {
+            create_random_proof(
+                MySillyCircuit {
+                    a: Some(a),
+                    b: Some(b),
+                },
+                rand(&mut rng),
+                &params,
+                &mut rng,
+            )
+            .unwrap()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(proofs[0].d, proofs[1].d);
+    assert!(verify_proof(&pvk, &proofs[0], &[a * b]).unwrap());
+}
+
+fn test_random_proof_checked<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let circuit = || MySillyCircuit {
+        a: Some(a),
+        b: Some(b),
+    };
+
+    assert_eq!(
+        create_random_proof_checked(circuit(), E::ScalarField::zero(), &params, &mut rng)
+            .unwrap_err(),
+        Error::ZeroRandomness("v")
+    );
+
+    let v = E::ScalarField::rand(&mut rng);
+    let proof = create_random_proof_checked(circuit(), v, &params, &mut rng).unwrap();
+    assert!(verify_proof(&pvk, &proof, &[a * b]).unwrap());
+    assert!(verify_witness_commitment(&params.vk, &proof, 1, &[a, b], &v).unwrap());
+}
+
+fn test_analyze_circuit<E>()
+where
+    E: Pairing,
+{
+    let stats = analyze_circuit::<E::ScalarField, _>(MySillyCircuit { a: None, b: None }).unwrap();
+    assert_eq!(stats.num_constraints, 6);
+    assert_eq!(stats.num_instance_variables, 2);
+    assert_eq!(stats.num_witness_variables, 2);
+    let single_term = BTreeMap::from([(1, 6)]);
+    assert_eq!(stats.a_histogram, single_term);
+    assert_eq!(stats.b_histogram, single_term);
+    assert_eq!(stats.c_histogram, single_term);
+    assert_eq!(
+        (
+            stats.a_num_non_zero,
+            stats.b_num_non_zero,
+            stats.c_num_non_zero
+        ),
+        (6, 6, 6)
+    );
+
+    // The padding constraints have a single term in `A` and `B` and none in `C`
+    let stats = analyze_circuit::<E::ScalarField, _>(MyPaddedCircuit {
+        a: None,
+        b: None,
+        padding: 3,
+    })
+    .unwrap();
+    assert_eq!(stats.num_constraints, 9);
+    assert_eq!(stats.num_witness_variables, 5);
+    assert_eq!(stats.a_histogram, BTreeMap::from([(1, 9)]));
+    assert_eq!(stats.c_histogram, BTreeMap::from([(0, 3), (1, 6)]));
+    assert_eq!(stats.c_num_non_zero, 6);
+}
+
+fn test_generator_cache<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let mut cache = GeneratorCache::<E>::new(&mut rng);
+    let rand = |rng: &mut StdRng| E::ScalarField::rand(rng);
+    let generate = |cache: &mut GeneratorCache<E>, rng: &mut StdRng| {
+        let (alpha, beta, gamma, delta, eta) =
+            (rand(rng), rand(rng), rand(rng), rand(rng), rand(rng));
+        generate_parameters_with_cache::<E, _, _>(
+            MySillyCircuit { a: None, b: None },
+            alpha,
+            beta,
+            gamma,
+            delta,
+            eta,
+            cache,
+            rng,
+        )
+        .unwrap()
+        .0
+    };
+    let params1 = generate(&mut cache, &mut rng);
+    assert_eq!(cache.table_count(), (1, 1));
+    let params2 = generate(&mut cache, &mut rng);
+    // Same size so the tables were reused
+    assert_eq!(cache.table_count(), (1, 1));
+    assert_ne!(params1.vk, params2.vk);
+
+    let a = rand(&mut rng);
+    let b = rand(&mut rng);
+    for params in [&params1, &params2] {
+        let pvk = prepare_verifying_key::<E>(&params.vk);
+        let v = rand(&mut rng);
+        let proof = create_random_proof(
+            MySillyCircuit {
+                a: Some(a),
+                b: Some(b),
+            },
+            v,
+            params,
+            &mut rng,
+        )
+        .unwrap();
+        assert!(verify_proof(&pvk, &proof, &[a * b]).unwrap());
+        assert!(verify_witness_commitment(&params.vk, &proof, 1, &[a, b], &v).unwrap());
+    }
+
+    cache.clear();
+    assert_eq!(cache.table_count(), (0, 0));
+}
+
+fn test_is_legogroth<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let pedersen_bases = (0..3)
+        .map(|_| E::G1::rand(&mut rng).into())
+        .collect::<Vec<_>>();
+    let params_with_link = generate_random_parameters_with_link::<E, _, _>(
+        MySillyCircuit { a: None, b: None },
+        &pedersen_bases,
+        &mut rng,
+    )
+    .unwrap();
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let v = E::ScalarField::rand(&mut rng);
+    let link_v = E::ScalarField::rand(&mut rng);
+    let proof = create_random_proof_with_link(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        v,
+        link_v,
+        &params_with_link,
+        &[a, b],
+        &mut rng,
+    )
+    .unwrap();
+    assert!(proof.groth16_proof.is_legogroth());
+    assert!(proof.has_commitments());
+
+    // A plain Groth16 proof has no commitment
+    let mut plain = proof.groth16_proof.clone();
+    plain.d = E::G1Affine::zero();
+    assert!(!plain.is_legogroth());
+    // and `d` computed from the witnesses makes it a LegoGroth16 proof again
+    plain.d = create_d_projective(&params_with_link.vk.groth16_vk, 1, &[a, b], &v)
+        .unwrap()
+        .into_affine();
+    assert!(plain.is_legogroth());
+    assert_eq!(plain, proof.groth16_proof);
+
+    let mut without_link_d = proof.clone();
+    without_link_d.link_d = E::G1Affine::zero();
+    assert!(!without_link_d.has_commitments());
+    let mut without_d = proof;
+    without_d.groth16_proof.d = E::G1Affine::zero();
+    assert!(!without_d.has_commitments());
+}
+
+fn test_verify_proof_from_raw<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let v = E::ScalarField::rand(&mut rng);
+    let proof = create_random_proof(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        v,
+        &params,
+        &mut rng,
+    )
+    .unwrap();
+
+    fn to_bytes<T: CanonicalSerialize>(t: &T) -> Vec<u8> {
+        let mut bytes = vec![];
+        t.serialize_compressed(&mut bytes).unwrap();
+        bytes
+    }
+    let vk = &params.vk;
+    let alpha_g1 = to_bytes(&vk.alpha_g1);
+    let beta_g2 = to_bytes(&vk.beta_g2);
+    let gamma_g2 = to_bytes(&vk.gamma_g2);
+    let delta_g2 = to_bytes(&vk.delta_g2);
+    let eta_gamma_inv_g1 = to_bytes(&vk.eta_gamma_inv_g1);
+    let mut gamma_abc_g1 = vk.gamma_abc_g1.iter().map(to_bytes).collect::<Vec<_>>();
+    let verify = |alpha_g1: &[u8], gamma_abc_g1: &[Vec<u8>], inputs: &[E::ScalarField]| {
+        let gamma_abc_g1 = gamma_abc_g1
+            .iter()
+            .map(|b| b.as_slice())
+            .collect::<Vec<_>>();
+        verify_proof_from_raw(
+            alpha_g1,
+            &beta_g2,
+            &gamma_g2,
+            &delta_g2,
+            &eta_gamma_inv_g1,
+            &gamma_abc_g1,
+            vk.commit_witness_count,
+            &proof,
+            inputs,
+        )
+    };
+
+    assert!(verify(&alpha_g1, &gamma_abc_g1, &[a * b]).unwrap());
+    assert!(!verify(&alpha_g1, &gamma_abc_g1, &[a]).unwrap());
+
+    assert_eq!(
+        verify(&alpha_g1[1..], &gamma_abc_g1, &[a * b]).unwrap_err(),
+        Error::InvalidVerifyingKeyElement(String::from("alpha_g1"))
+    );
+    gamma_abc_g1[2].push(0);
+    assert_eq!(
+        verify(&alpha_g1, &gamma_abc_g1, &[a * b]).unwrap_err(),
+        Error::InvalidVerifyingKeyElement(String::from("gamma_abc_g1[2]"))
+    );
+}
+
+fn test_b_g2_query_matches_g1<E>()
+where
+    E: Pairing,
+{
+    let params = generate_random_parameters::<E, _, _>(
+        MySillyCircuit { a: None, b: None },
+        &mut StdRng::seed_from_u64(0u64),
+    )
+    .unwrap();
+    // The G2 and G1 queries are computed concurrently but the result is still deterministic
+    let again = generate_random_parameters::<E, _, _>(
+        MySillyCircuit { a: None, b: None },
+        &mut StdRng::seed_from_u64(0u64),
+    )
+    .unwrap();
+    assert_eq!(params, again);
+
+    // Both B-queries are for the same `b_i(t)`: e(b_i(t) * G, beta * H) = e(beta * G, b_i(t) * H)
+    let common = &params.common;
+    assert_eq!(common.b_g1_query.len(), common.b_g2_query.len());
+    for (b_g1, b_g2) in common.b_g1_query.iter().zip(&common.b_g2_query) {
+        assert_eq!(
+            E::pairing(*b_g1, params.vk.beta_g2),
+            E::pairing(common.beta_g1, *b_g2)
+        );
+    }
+}
+
+fn test_verify_link_relation<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let pedersen_bases = (0..3)
+        .map(|_| E::G1::rand(&mut rng).into())
+        .collect::<Vec<_>>();
+    let params_with_link = generate_random_parameters_with_link::<E, _, _>(
+        MySillyCircuit { a: None, b: None },
+        &pedersen_bases,
+        &mut rng,
+    )
+    .unwrap();
+    let vk = &params_with_link.vk;
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let v = E::ScalarField::rand(&mut rng);
+    let link_v = E::ScalarField::rand(&mut rng);
+    let proof = create_random_proof_with_link(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        v,
+        link_v,
+        &params_with_link,
+        &[a, b],
+        &mut rng,
+    )
+    .unwrap();
+
+    // The commitments as known to the verifier out of band
+    let link_d = E::G1::msm(&vk.link_bases, &[a, b, link_v])
+        .unwrap()
+        .into_affine();
+    let d = create_d_projective(&vk.groth16_vk, 1, &[a, b], &v)
+        .unwrap()
+        .into_affine();
+    assert_eq!(link_d, proof.link_d);
+    assert!(verify_link_relation(vk, &link_d, &d, &proof.link_pi));
+
+    assert!(!verify_link_relation(vk, &d, &link_d, &proof.link_pi));
+    let other_link_d = E::G1::msm(&vk.link_bases, &[b, a, link_v])
+        .unwrap()
+        .into_affine();
+    assert!(!verify_link_relation(vk, &other_link_d, &d, &proof.link_pi));
+}
+
+fn test_updatable_inputs<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let num_inputs = 5;
+    let params = generate_random_parameters::<E, _, _>(
+        MyManyInputsCircuit {
+            inputs: vec![None; num_inputs],
+        },
+        &mut rng,
+    )
+    .unwrap();
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+
+    let mut inputs = (0..num_inputs)
+        .map(|_| E::ScalarField::rand(&mut rng))
+        .collect::<Vec<_>>();
+    let mut updatable = UpdatableInputs::new(&pvk, &inputs).unwrap();
+
+    let new_value = E::ScalarField::rand(&mut rng);
+    updatable.set(3, &inputs[3], &new_value).unwrap();
+    inputs[3] = new_value;
+    assert_eq!(updatable.acc, prepare_inputs(&pvk, &inputs).unwrap());
+
+    let proof = create_random_proof(
+        MyManyInputsCircuit {
+            inputs: inputs.iter().map(|x| Some(*x)).collect(),
+        },
+        E::ScalarField::rand(&mut rng),
+        &params,
+        &mut rng,
+    )
+    .unwrap();
+    assert!(verify_proof_with_prepared_inputs(&pvk, &proof, &updatable.acc).unwrap());
+
+    assert_eq!(
+        updatable.set(num_inputs, &inputs[0], &new_value),
+        Err(Error::PublicInputIndexOutOfRange(num_inputs, num_inputs))
+    );
+    assert!(UpdatableInputs::new(&pvk, &inputs[1..]).is_err());
+}
+
+fn test_link_without_public_inputs<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let pedersen_bases = (0..4)
+        .map(|_| E::G1::rand(&mut rng).into())
+        .collect::<Vec<_>>();
+    let params_with_link = generate_random_parameters_with_link::<E, _, _>(
+        MyNoInputsCircuit { a: None, b: None },
+        &pedersen_bases,
+        &mut rng,
+    )
+    .unwrap();
+    let vk = &params_with_link.vk;
+    assert_eq!(vk.groth16_vk.expected_public_input_count(), 0);
+    let pvk = prepare_verifying_key::<E>(&vk.groth16_vk);
+
+    // Only the constant term is left without public inputs
+    assert_eq!(
+        prepare_inputs(&pvk, &[]).unwrap(),
+        vk.groth16_vk.gamma_abc_g1[0].into_group()
+    );
+    assert_eq!(
+        prepare_inputs_sparse(&pvk, &[]).unwrap(),
+        vk.groth16_vk.gamma_abc_g1[0].into_group()
+    );
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let v = E::ScalarField::rand(&mut rng);
+    let link_v = E::ScalarField::rand(&mut rng);
+    let proof = create_random_proof_with_link(
+        MyNoInputsCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        v,
+        link_v,
+        &params_with_link,
+        &[a, b, a * b],
+        &mut rng,
+    )
+    .unwrap();
+
+    assert!(verify_proof_with_link(&pvk, vk, &proof, &[]).unwrap());
+    assert!(
+        verify_witness_commitment(&vk.groth16_vk, &proof.groth16_proof, 0, &[a, b, a * b], &v)
+            .unwrap()
+    );
+    assert!(
+        verify_link_commitment::<E>(&vk.link_bases, &proof.link_d, &[a, b, a * b], &link_v)
+            .unwrap()
+    );
+    let prepared = prepare_inputs(&pvk, &[]).unwrap();
+    assert!(verify_proof_with_link_prepared_inputs(&pvk, vk, &proof, &prepared).unwrap());
+
+    // A public input the key doesn't have
+    assert!(verify_proof_with_link(&pvk, vk, &proof, &[a]).is_err());
+}
+
+fn test_proof_with_link_prepared<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let pedersen_bases = (0..3)
+        .map(|_| E::G1::rand(&mut rng).into())
+        .collect::<Vec<_>>();
+    let params_with_link = generate_random_parameters_with_link::<E, _, _>(
+        MySillyCircuit { a: None, b: None },
+        &pedersen_bases,
+        &mut rng,
+    )
+    .unwrap();
+    let pvk = prepare_verifying_key::<E>(&params_with_link.vk.groth16_vk);
+    let prepared = params_with_link.prepare_link_bases();
+    assert_eq!(prepared.tables.len(), pedersen_bases.len());
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let v = E::ScalarField::rand(&mut rng);
+    let link_v = E::ScalarField::rand(&mut rng);
+    let circuit = || MySillyCircuit {
+        a: Some(a),
+        b: Some(b),
+    };
+
+    // Same randomness for both paths
+    let proof = create_random_proof_with_link(
+        circuit(),
+        v,
+        link_v,
+        &params_with_link,
+        &[a, b],
+        &mut StdRng::seed_from_u64(1u64),
+    )
+    .unwrap();
+    let prepared_proof = create_random_proof_with_link_prepared(
+        circuit(),
+        v,
+        link_v,
+        &params_with_link,
+        &prepared,
+        &[a, b],
+        &mut StdRng::seed_from_u64(1u64),
+    )
+    .unwrap();
+    assert_eq!(prepared_proof, proof);
+    assert!(verify_proof_with_link(&pvk, &params_with_link.vk, &prepared_proof, &[a * b]).unwrap());
+
+    assert!(create_random_proof_with_link_prepared(
+        circuit(),
+        v,
+        link_v,
+        &params_with_link,
+        &prepared,
+        &[a],
+        &mut rng,
+    )
+    .is_err());
+}
+
+fn test_verify_proof_strict<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let v = E::ScalarField::rand(&mut rng);
+    let proof = create_random_proof(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        v,
+        &params,
+        &mut rng,
+    )
+    .unwrap();
+
+    assert_eq!(verify_proof_strict(&pvk, &proof, &[a * b]), Ok(true));
+    assert_eq!(verify_proof_strict(&pvk, &proof, &[a]), Ok(false));
+    assert_eq!(
+        verify_proof_strict(&pvk, &proof, &[a * b, a]),
+        Err(VerifyError::InputLengthMismatch {
+            expected: 1,
+            got: 2
+        })
+    );
+
+    let off_subgroup = (0u8..)
+        .filter_map(|i| E::G1Affine::from_random_bytes(&[i; 64]))
+        .find(|p| p.check().is_err())
+        .unwrap();
+    let mut bad_proof = proof.clone();
+    bad_proof.d = off_subgroup;
+    assert_eq!(
+        verify_proof_strict(&pvk, &bad_proof, &[a * b]),
+        Err(VerifyError::InvalidPoint)
+    );
+
+    // A key without even the constant term can't prepare the inputs
+    let mut vk = params.vk.clone();
+    vk.gamma_abc_g1.clear();
+    vk.commit_witness_count = 0;
+    let pvk = prepare_verifying_key::<E>(&vk);
+    assert!(matches!(
+        verify_proof_strict(&pvk, &proof, &[]),
+        Err(VerifyError::Internal(_))
+    ));
+}
+
+fn test_proof_from_assignment<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let circuit = || MySillyCircuit {
+        a: Some(a),
+        b: Some(b),
+    };
+
+    // The assignment and `h` as an external witness generator would compute them
+    let cs = ConstraintSystem::new_ref();
+    circuit().generate_constraints(cs.clone()).unwrap();
+    cs.finalize();
+    let h =
+        R1CStoQAP::witness_map::<E::ScalarField, GeneralEvaluationDomain<_>>(cs.clone()).unwrap();
+    let instance = cs.borrow().unwrap().instance_assignment.clone();
+    let witnesses = cs.borrow().unwrap().witness_assignment.clone();
+    assert_eq!(instance, vec![E::ScalarField::one(), a * b]);
+    assert_eq!(witnesses, vec![a, b]);
+
+    let r = E::ScalarField::rand(&mut rng);
+    let s = E::ScalarField::rand(&mut rng);
+    let v = E::ScalarField::rand(&mut rng);
+    let proof = create_proof_from_assignment(
+        &instance,
+        &witnesses,
+        &h,
+        &params.common,
+        &params.vk,
+        r,
+        s,
+        v,
+    )
+    .unwrap();
+    assert!(verify_proof(&pvk, &proof, &[a * b]).unwrap());
+    assert!(verify_witness_commitment(&params.vk, &proof, 1, &[a, b], &v).unwrap());
+    assert_eq!(
+        proof,
+        create_proof(circuit(), &params.common, &params.vk, r, s, v).unwrap()
+    );
+
+    // A wrong assignment gives a proof that doesn't verify
+    let proof =
+        create_proof_from_assignment(&instance, &[a, a], &h, &params.common, &params.vk, r, s, v)
+            .unwrap();
+    assert!(!verify_proof(&pvk, &proof, &[a * b]).unwrap());
+
+    // Lengths are checked
+    let prove = |instance: &[E::ScalarField],
+                 witnesses: &[E::ScalarField],
+                 h: &[E::ScalarField]| {
+        create_proof_from_assignment(instance, witnesses, h, &params.common, &params.vk, r, s, v)
+    };
+    assert!(prove(&instance[1..], &witnesses, &h).is_err());
+    assert!(prove(&[a * b, E::ScalarField::one()], &witnesses, &h).is_err());
+    assert!(prove(&instance, &witnesses[1..], &h).is_err());
+    assert!(prove(&instance, &witnesses, &h[2..]).is_err());
+}
+
+fn test_link_matrix_serialization<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let pedersen_bases = (0..3)
+        .map(|_| E::G1::rand(&mut rng).into())
+        .collect::<Vec<_>>();
+    let (params_with_link, link_m) = generate_random_parameters_with_link_matrix::<E, _, _>(
+        MySillyCircuit { a: None, b: None },
+        &pedersen_bases,
+        &mut rng,
+    )
+    .unwrap();
+    assert_eq!(link_m, params_with_link.vk.link_matrix().unwrap());
+
+    for compress in [Compress::Yes, Compress::No] {
+        let mut bytes = vec![];
+        link_m.serialize_with_mode(&mut bytes, compress).unwrap();
+        assert_eq!(bytes.len(), link_m.serialized_size(compress));
+        let deserialized = SparseMatrix::<E::G1Affine>::deserialize_with_mode(
+            bytes.as_slice(),
+            compress,
+            Validate::Yes,
+        )
+        .unwrap();
+        assert_eq!(deserialized, link_m);
+
+        // The same keys come out of keygen with the same randomness
+        let pp = &params_with_link.vk.link_pp;
+        let keys =
+            PESubspaceSnark::<E>::keygen(&mut StdRng::seed_from_u64(1u64), pp, link_m.clone());
+        let keys_from_deserialized =
+            PESubspaceSnark::<E>::keygen(&mut StdRng::seed_from_u64(1u64), pp, deserialized);
+        assert_eq!(keys, keys_from_deserialized);
+    }
+
+    // A row out of range: `nr` is the first field
+    let mut bytes = vec![];
+    link_m.serialize_compressed(&mut bytes).unwrap();
+    bytes[0] = 1;
+    assert!(SparseMatrix::<E::G1Affine>::deserialize_compressed(bytes.as_slice()).is_err());
+
+    // A huge `nc` is rejected without allocating a column for each: `nc` is the second field
+    let mut bytes = vec![];
+    link_m.serialize_compressed(&mut bytes).unwrap();
+    bytes[8..16].copy_from_slice(&(u64::MAX >> 4).to_le_bytes());
+    assert!(SparseMatrix::<E::G1Affine>::deserialize_compressed(bytes.as_slice()).is_err());
+
+    // A matrix with an empty column couldn't be read back so it isn't written either
+    let mut m = SparseMatrix::new(2, 2);
+    m.insert_val(0, 0, &E::G1Affine::generator());
+    assert!(m.serialize_compressed(&mut vec![]).is_err());
+}
+
+fn test_prover_self_verify<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let v = E::ScalarField::rand(&mut rng);
+    let proof = create_random_proof(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        v,
+        &params,
+        &mut rng,
+    )
+    .unwrap();
+
+    assert_eq!(
+        prover_self_verify(&params.vk, &proof, &[a * b], &[a, b], &v),
+        Ok(true)
+    );
+    assert_eq!(
+        prover_self_verify(&params.vk, &proof, &[a * b], &[b, a], &v),
+        Err(Error::InvalidWitnessCommitment)
+    );
+    assert_eq!(
+        prover_self_verify(&params.vk, &proof, &[a * b], &[a, b], &a),
+        Err(Error::InvalidWitnessCommitment)
+    );
+    assert_eq!(
+        prover_self_verify(&params.vk, &proof, &[a], &[a, b], &v),
+        Err(Error::InvalidProof)
+    );
+}
+
+fn test_generate_with_domain_and_t<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    // 6 constraints and 2 instance variables
+    let domain = GeneralEvaluationDomain::<E::ScalarField>::new(8).unwrap();
+    let t = domain.sample_element_outside_domain(&mut rng);
+    let rand = |rng: &mut StdRng| E::ScalarField::rand(rng);
+    let (alpha, beta, gamma, delta, eta) = (
+        rand(&mut rng),
+        rand(&mut rng),
+        rand(&mut rng),
+        rand(&mut rng),
+        rand(&mut rng),
+    );
+    let generate = |domain, t, seed| {
+        generate_parameters_with_domain_and_t::<E, _, _>(
+            MySillyCircuit { a: None, b: None },
+            domain,
+            t,
+            alpha,
+            beta,
+            gamma,
+            delta,
+            eta,
+            &mut StdRng::seed_from_u64(seed),
+        )
+    };
+
+    let (params, num_instance) = generate(domain, t, 1).unwrap();
+    assert_eq!(num_instance, 2);
+    // Deterministic in the toxic waste, `t` and the randomness of the generators
+    assert_eq!(generate(domain, t, 1).unwrap().0, params);
+
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+    let a = rand(&mut rng);
+    let b = rand(&mut rng);
+    let v = rand(&mut rng);
+    let proof = create_random_proof(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        v,
+        &params,
+        &mut rng,
+    )
+    .unwrap();
+    assert!(verify_proof(&pvk, &proof, &[a * b]).unwrap());
+
+    let larger = GeneralEvaluationDomain::<E::ScalarField>::new(16).unwrap();
+    assert_eq!(
+        generate(larger, t, 1).unwrap_err(),
+        Error::DomainMismatch(8, 16)
+    );
+    assert_eq!(
+        generate(domain, domain.element(3), 1).unwrap_err(),
+        Error::EvaluationPointInDomain
+    );
+}
+
+fn test_link_types_clone_and_debug<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let pp = PP::<E::G1Affine, E::G2Affine> {
+        l: 2,
+        t: 5,
+        g1: E::G1::rand(&mut rng).into_affine(),
+        g2: E::G2::rand(&mut rng).into_affine(),
+    };
+    let bases = (0..6)
+        .map(|_| E::G1::rand(&mut rng).into_affine())
+        .collect::<Vec<_>>();
+    let mut m = SparseMatrix::new(2, 5);
+    m.insert_row_slice(0, 0, &bases[..3]);
+    m.insert_row_slice(1, 2, &bases[3..]);
+
+    let x = (0..5)
+        .map(|_| E::ScalarField::rand(&mut rng))
+        .collect::<Vec<_>>();
+    let y = [
+        (bases[0] * x[0] + bases[1] * x[1] + bases[2] * x[2]).into_affine(),
+        (bases[3] * x[2] + bases[4] * x[3] + bases[5] * x[4]).into_affine(),
+    ];
+
+    let (ek, vk) = PESubspaceSnark::<E>::keygen(&mut rng, &pp, m.clone());
+
+    // Clones are equal to the originals and usable in their place
+    let (pp_clone, ek_clone, vk_clone, m_clone) = (pp.clone(), ek.clone(), vk.clone(), m.clone());
+    assert_eq!(pp_clone, pp);
+    assert_eq!(ek_clone, ek);
+    assert_eq!(vk_clone, vk);
+    assert_eq!(m_clone, m);
+    let pi = PESubspaceSnark::<E>::prove(&pp_clone, &ek_clone, &x);
+    assert_eq!(pi, PESubspaceSnark::<E>::prove(&pp, &ek, &x));
+    assert!(PESubspaceSnark::<E>::verify(&pp, &vk_clone, &y, &pi));
+
+    // A cloned generator cache has the same generators and tables
+    let cache = GeneratorCache::<E>::new(&mut rng);
+    let cache_clone = cache.clone();
+    assert_eq!(cache_clone.g1_generator, cache.g1_generator);
+    assert_eq!(cache_clone.g2_generator, cache.g2_generator);
+    assert_eq!(cache_clone.table_count(), cache.table_count());
+}
+
+fn test_input_capacity<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    // 1 public input and 2 committed witnesses
+    assert_eq!(params.vk.input_capacity(), 3);
+    assert_eq!(
+        params.vk.input_capacity(),
+        params.vk.expected_public_input_count() + params.vk.commit_witness_count as usize
+    );
+    assert_eq!(params.vk.input_capacity() + 1, params.vk.gamma_abc_g1.len());
+
+    let mut vk = params.vk;
+    vk.gamma_abc_g1.clear();
+    assert_eq!(vk.input_capacity(), 0);
+}
+
+fn test_prepared_proof<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let v = E::ScalarField::rand(&mut rng);
+    let proof = create_random_proof(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        v,
+        &params,
+        &mut rng,
+    )
+    .unwrap();
+
+    let prepared = proof.prepare();
+    let other_d = E::G1::rand(&mut rng).into_affine();
+    for (d, inputs) in [
+        (proof.d, vec![a * b]),
+        (proof.d, vec![a]),
+        (other_d, vec![a * b]),
+        (other_d, vec![b]),
+    ] {
+        let unprepared = Proof::<E> { d, ..proof.clone() };
+        let expected = verify_proof(&pvk, &unprepared, &inputs).unwrap();
+        assert_eq!(
+            verify_prepared_proof(&pvk, &prepared, &d, &inputs).unwrap(),
+            expected
+        );
+    }
+    assert!(verify_prepared_proof(&pvk, &prepared, &proof.d, &[a * b]).unwrap());
+    assert!(!verify_prepared_proof(&pvk, &prepared, &other_d, &[a * b]).unwrap());
+
+    // The number of public inputs is checked
+    assert!(verify_prepared_proof(&pvk, &prepared, &proof.d, &[a * b, a]).is_err());
+}
+
+fn test_committed_witnesses_overlap_public_inputs<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let v = E::ScalarField::rand(&mut rng);
+    let proof = create_random_proof(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        v,
+        &params,
+        &mut rng,
+    )
+    .unwrap();
+    assert!(verify_witness_commitment(&params.vk, &proof, 1, &[a, b], &v).unwrap());
+
+    // With no public inputs the committed block would start at the base of the public input `a * b`
+    assert_eq!(
+        create_d_projective(&params.vk, 0, &[a * b, a], &v),
+        Err(Error::CommittedWitnessesOverlapPublicInputs(0, 1))
+    );
+    assert_eq!(
+        verify_witness_commitment(&params.vk, &proof, 0, &[a * b, a], &v),
+        Err(Error::CommittedWitnessesOverlapPublicInputs(0, 1))
+    );
+    assert_eq!(
+        params.vk.commitment_bases(0, 1),
+        Err(Error::CommittedWitnessesOverlapPublicInputs(0, 1))
+    );
+}
+
+fn test_proofs_same_statement<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let mut proofs = (0..5)
+        .map(|_| {
+            let v = E::ScalarField::rand(&mut rng);
+            create_random_proof(
+                MySillyCircuit {
+                    a: Some(a),
+                    b: Some(b),
+                },
+                v,
+                &params,
+                &mut rng,
+            )
+            .unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        verify_proofs_same_statement(&pvk, &proofs, &[a * b], &mut rng).unwrap(),
+        vec![true; 5]
+    );
+    assert_eq!(
+        verify_proofs_same_statement(&pvk, &proofs, &[a], &mut rng).unwrap(),
+        vec![false; 5]
+    );
+
+    // Tamper with some of the proofs
+    proofs[1].c = (proofs[1].c + proofs[1].c).into_affine();
+    proofs[3].d = proofs[4].d;
+    assert_eq!(
+        verify_proofs_same_statement(&pvk, &proofs, &[a * b], &mut rng).unwrap(),
+        vec![true, false, true, false, true]
+    );
+
+    assert!(verify_proofs_same_statement(&pvk, &[], &[a * b], &mut rng)
+        .unwrap()
+        .is_empty());
+    assert!(verify_proofs_same_statement(&pvk, &proofs, &[a * b, a], &mut rng).is_err());
+}
+
+fn test_position_opening<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    // Commits to `(a, b, 0)`
+    let params = generate_random_parameters::<E, _, _>(
+        MyPaddedCircuit {
+            a: None,
+            b: None,
+            padding: 1,
+        },
+        &mut rng,
+    )
+    .unwrap();
+    assert_eq!(params.vk.commit_witness_count, 3);
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let v = E::ScalarField::rand(&mut rng);
+    let proof = create_random_proof(
+        MyPaddedCircuit {
+            a: Some(a),
+            b: Some(b),
+            padding: 1,
+        },
+        v,
+        &params,
+        &mut rng,
+    )
+    .unwrap();
+    let witnesses = [a, b, E::ScalarField::zero()];
+    assert!(verify_witness_commitment(&params.vk, &proof, 1, &witnesses, &v).unwrap());
+
+    let opening =
+        open_position::<E, Blake2b512, _>(&params.vk, 1, &witnesses, 1, &v, &mut rng).unwrap();
+    assert_eq!(opening.responses.len(), 3);
+    assert!(
+        verify_position_opening::<E, Blake2b512>(&params.vk, 1, &proof.d, 1, &b, &opening).unwrap()
+    );
+
+    // Wrong value, position or commitment
+    assert!(
+        !verify_position_opening::<E, Blake2b512>(&params.vk, 1, &proof.d, 1, &a, &opening)
+            .unwrap()
+    );
+    assert!(
+        !verify_position_opening::<E, Blake2b512>(&params.vk, 1, &proof.d, 0, &b, &opening)
+            .unwrap()
+    );
+    let other_d = E::G1::rand(&mut rng).into_affine();
+    assert!(
+        !verify_position_opening::<E, Blake2b512>(&params.vk, 1, &other_d, 1, &b, &opening)
+            .unwrap()
+    );
+
+    // The opening is bound to the bases of the key it was created for
+    let mut other_vk = params.vk.clone();
+    other_vk.eta_gamma_inv_g1 = E::G1::rand(&mut rng).into_affine();
+    assert!(
+        !verify_position_opening::<E, Blake2b512>(&other_vk, 1, &proof.d, 1, &b, &opening).unwrap()
+    );
+
+    // Positions outside of the committed block
+    assert_eq!(
+        open_position::<E, Blake2b512, _>(&params.vk, 1, &witnesses, 3, &v, &mut rng),
+        Err(Error::CommittedWitnessIndexOutOfRange(3, 3))
+    );
+    assert!(
+        verify_position_opening::<E, Blake2b512>(&params.vk, 1, &proof.d, 3, &b, &opening).is_err()
+    );
+    assert!(
+        open_position::<E, Blake2b512, _>(&params.vk, 1, &witnesses[..2], 1, &v, &mut rng).is_err()
+    );
+}
+
+fn test_verifying_key_only<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+    let alpha = E::ScalarField::rand(&mut rng);
+    let beta = E::ScalarField::rand(&mut rng);
+    let gamma = E::ScalarField::rand(&mut rng);
+    let delta = E::ScalarField::rand(&mut rng);
+    let eta = E::ScalarField::rand(&mut rng);
+
+    let circuit = || MyPaddedCircuit::<E::ScalarField> {
+        a: None,
+        b: None,
+        padding: 2,
+    };
+    let (pk, _) = generate_parameters::<E, _, _>(
+        circuit(),
+        alpha,
+        beta,
+        gamma,
+        delta,
+        eta,
+        &mut StdRng::seed_from_u64(1u64),
+    )
+    .unwrap();
+    let vk = generate_verifying_key::<E, _, _>(
+        circuit(),
+        alpha,
+        beta,
+        gamma,
+        delta,
+        eta,
+        &mut StdRng::seed_from_u64(1u64),
+    )
+    .unwrap();
+    assert_eq!(vk, pk.vk);
+
+    // The same with random toxic waste
+    let params =
+        generate_random_parameters::<E, _, _>(circuit(), &mut StdRng::seed_from_u64(2u64)).unwrap();
+    let vk = generate_verifying_key_only::<E, _, _>(circuit(), &mut StdRng::seed_from_u64(2u64))
+        .unwrap();
+    assert_eq!(vk, params.vk);
+
+    // Proofs for the full parameters verify with the key
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let v = E::ScalarField::rand(&mut rng);
+    let proof = create_random_proof(
+        MyPaddedCircuit {
+            a: Some(a),
+            b: Some(b),
+            padding: 2,
+        },
+        v,
+        &params,
+        &mut rng,
+    )
+    .unwrap();
+    let pvk = prepare_verifying_key::<E>(&vk);
+    assert!(verify_proof(&pvk, &proof, &[a * b]).unwrap());
+}
+
+fn test_prepared_verifying_key_gt<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+    assert!(pvk.validate_gt().is_ok());
+
+    // In the subgroup but not the element of the key, which only the full check detects
+    let mut other_pvk = pvk.clone();
+    other_pvk.alpha_g1_beta_g2.square_in_place();
+    assert!(other_pvk.validate_gt().is_ok());
+    assert_eq!(
+        other_pvk.validate(),
+        Err(Error::InvalidPreparedVerifyingKey)
+    );
+
+    // Not in the subgroup
+    let mut bad_pvk = pvk.clone();
+    bad_pvk.alpha_g1_beta_g2 = E::TargetField::rand(&mut rng);
+    assert_eq!(
+        bad_pvk.validate_gt(),
+        Err(Error::InvalidPreparedVerifyingKey)
+    );
+    bad_pvk.alpha_g1_beta_g2 = E::TargetField::zero();
+    assert_eq!(
+        bad_pvk.validate_gt(),
+        Err(Error::InvalidPreparedVerifyingKey)
+    );
+}
+
+#[cfg(feature = "soa-inputs")]
+fn test_soa_inputs<E, P>()
+where
+    E: Pairing<G1Affine = SWAffine<P>>,
+    P: SWCurveConfig,
+{
+    use crate::{prepare_inputs_soa, prepare_verifying_key_soa, verify_proof_soa};
+
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let v = E::ScalarField::rand(&mut rng);
+    let proof = create_random_proof(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        v,
+        &params,
+        &mut rng,
+    )
+    .unwrap();
+    let pvk = prepare_verifying_key_soa::<E, P>(&params.vk);
+    assert!(verify_proof_soa(&pvk, &proof, &[a * b]).unwrap());
+    assert!(!verify_proof_soa(&pvk, &proof, &[a]).unwrap());
+    assert!(verify_proof_soa(&pvk, &proof, &[a * b, a]).is_err());
+
+    // A statement with 1000 inputs, over several chunks, with some bases at infinity
+    let mut vk = params.vk.clone();
+    vk.gamma_abc_g1 = (0..1001)
+        .map(|i| {
+            if i % 300 == 7 {
+                E::G1Affine::zero()
+            } else {
+                E::G1::rand(&mut rng).into_affine()
+            }
+        })
+        .collect();
+    vk.commit_witness_count = 0;
+    let inputs = (0..1000)
+        .map(|_| E::ScalarField::rand(&mut rng))
+        .collect::<Vec<_>>();
+    let pvk = prepare_verifying_key_soa::<E, P>(&vk);
+    assert_eq!(pvk.gamma_abc_x.len(), 1001);
+    assert_eq!(
+        prepare_inputs_soa(&pvk, &inputs).unwrap(),
+        prepare_inputs(&pvk.pvk, &inputs).unwrap()
+    );
+    assert_eq!(
+        prepare_inputs_soa(&pvk, &inputs[..3]).unwrap(),
+        prepare_inputs(&pvk.pvk, &inputs[..3]).unwrap()
+    );
+    let too_many = vec![E::ScalarField::one(); 1001];
+    assert!(prepare_inputs_soa(&pvk, &too_many).is_err());
+}
+
+fn test_check_v<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let v = E::ScalarField::rand(&mut rng);
+    let proof = create_random_proof(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        v,
+        &params,
+        &mut rng,
+    )
+    .unwrap();
+
+    assert!(check_v(&params.vk, &proof.d, 1, &[a, b], &v));
+    assert!(!check_v(
+        &params.vk,
+        &proof.d,
+        1,
+        &[a, b],
+        &(v + E::ScalarField::one())
+    ));
+    assert!(!check_v(&params.vk, &proof.d, 1, &[b, a], &v));
+    // Witnesses that don't fit the key
+    assert!(!check_v(&params.vk, &proof.d, 1, &[a, b, a], &v));
+}
+
+fn test_commitment_equality<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let pedersen_bases = (0..3)
+        .map(|_| E::G1::rand(&mut rng).into())
+        .collect::<Vec<_>>();
+    let params_with_link = generate_random_parameters_with_link::<E, _, _>(
+        MySillyCircuit { a: None, b: None },
+        &pedersen_bases,
+        &mut rng,
+    )
+    .unwrap();
+    let bases = &params_with_link.vk.link_bases;
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let mut prove = |a, b| {
+        let v = E::ScalarField::rand(&mut rng);
+        let link_v = E::ScalarField::rand(&mut rng);
+        let proof = create_random_proof_with_link(
+            MySillyCircuit {
+                a: Some(a),
+                b: Some(b),
+            },
+            v,
+            link_v,
+            &params_with_link,
+            &[a, b],
+            &mut rng,
+        )
+        .unwrap();
+        (proof, link_v)
+    };
+    let (proof1, link_v1) = prove(a, b);
+    let (proof2, link_v2) = prove(a, b);
+    let (proof3, link_v3) = prove(a, a);
+    assert_ne!(proof1.link_d, proof2.link_d);
+
+    // Same witnesses with different randomness
+    let eq_proof = prove_commitment_equality::<E, Blake2b512, _>(
+        &proof1.link_d,
+        &proof2.link_d,
+        &link_v1,
+        &link_v2,
+        bases,
+        &mut rng,
+    )
+    .unwrap();
+    assert!(verify_commitment_equality::<E, Blake2b512>(
+        &proof1.link_d,
+        &proof2.link_d,
+        bases,
+        &eq_proof
+    )
+    .unwrap());
+    assert!(!verify_commitment_equality::<E, Blake2b512>(
+        &proof2.link_d,
+        &proof1.link_d,
+        bases,
+        &eq_proof
+    )
+    .unwrap());
+
+    // Different witnesses
+    let eq_proof = prove_commitment_equality::<E, Blake2b512, _>(
+        &proof1.link_d,
+        &proof3.link_d,
+        &link_v1,
+        &link_v3,
+        bases,
+        &mut rng,
+    )
+    .unwrap();
+    assert!(!verify_commitment_equality::<E, Blake2b512>(
+        &proof1.link_d,
+        &proof3.link_d,
+        bases,
+        &eq_proof
+    )
+    .unwrap());
+
+    assert_eq!(
+        prove_commitment_equality::<E, Blake2b512, _>(
+            &proof1.link_d,
+            &proof2.link_d,
+            &link_v1,
+            &link_v2,
+            &[],
+            &mut rng,
+        ),
+        Err(Error::EmptyPedersenBases)
+    );
+    assert!(verify_commitment_equality::<E, Blake2b512>(
+        &proof1.link_d,
+        &proof2.link_d,
+        &[],
+        &eq_proof
+    )
+    .is_err());
+}
+
+fn test_link_checks_concurrently<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let pedersen_bases = (0..3)
+        .map(|_| E::G1::rand(&mut rng).into())
+        .collect::<Vec<_>>();
+    let params_with_link = generate_random_parameters_with_link::<E, _, _>(
+        MySillyCircuit { a: None, b: None },
+        &pedersen_bases,
+        &mut rng,
+    )
+    .unwrap();
+    let vk = &params_with_link.vk;
+    let pvk = prepare_verifying_key::<E>(&vk.groth16_vk);
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let v = E::ScalarField::rand(&mut rng);
+    let link_v = E::ScalarField::rand(&mut rng);
+    let proof = create_random_proof_with_link(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        v,
+        link_v,
+        &params_with_link,
+        &[a, b],
+        &mut rng,
+    )
+    .unwrap();
+
+    let random_point = E::G1::rand(&mut rng).into_affine();
+    let mut bad_groth16 = proof.clone();
+    bad_groth16.groth16_proof.c = random_point;
+    let mut bad_link_pi = proof.clone();
+    bad_link_pi.link_pi = random_point;
+    let mut bad_link_d = proof.clone();
+    bad_link_d.link_d = random_point;
+
+    for (proof, inputs) in [
+        (&proof, vec![a * b]),
+        (&proof, vec![a]),
+        (&proof, vec![a * b, a]),
+        (&bad_groth16, vec![a * b]),
+        (&bad_link_pi, vec![a * b]),
+        (&bad_link_d, vec![a * b]),
+    ] {
+        // Both checks one after the other
+        let sequential = verify_proof(&pvk, &proof.groth16_proof, &inputs).map(|verified| {
+            verified
+                && verify_link_relation(vk, &proof.link_d, &proof.groth16_proof.d, &proof.link_pi)
+        });
+        assert_eq!(verify_proof_with_link(&pvk, vk, proof, &inputs), sequential);
+        if let Ok(prepared_inputs) = prepare_inputs(&pvk, &inputs) {
+            if inputs.len() == 1 {
+                assert_eq!(
+                    verify_proof_with_link_prepared_inputs(&pvk, vk, proof, &prepared_inputs),
+                    sequential
+                );
+            }
+        }
+    }
+    assert!(verify_proof_with_link(&pvk, vk, &proof, &[a * b]).unwrap());
+    assert!(!verify_proof_with_link(&pvk, vk, &bad_link_pi, &[a * b]).unwrap());
+}
+
+fn test_verify_all<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+
+    let mut pairs = (0..5)
+        .map(|_| {
+            let a = E::ScalarField::rand(&mut rng);
+            let b = E::ScalarField::rand(&mut rng);
+            let v = E::ScalarField::rand(&mut rng);
+            let proof = create_random_proof(
+                MySillyCircuit {
+                    a: Some(a),
+                    b: Some(b),
+                },
+                v,
+                &params,
+                &mut rng,
+            )
+            .unwrap();
+            (proof, vec![a * b])
+        })
+        .collect::<Vec<_>>();
+    assert!(verify_all(&pvk, &pairs));
+    assert!(verify_all(&pvk, &[]));
+
+    // The third proof is invalid so the ones after it are not verified
+    pairs[2].1[0] += E::ScalarField::one();
+    assert!(!verify_all(&pvk, &pairs));
+    assert!(verify_all(&pvk, &pairs[..2]));
+    assert!(verify_all(&pvk, &pairs[3..]));
+
+    // Malformed inputs are failures too
+    pairs[2].1 = vec![];
+    assert!(!verify_all(&pvk, &pairs));
+}
+
+fn test_verify_against_either<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params1 =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    let params2 =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    let pvk1 = prepare_verifying_key::<E>(&params1.vk);
+    let pvk2 = prepare_verifying_key::<E>(&params2.vk);
+
+    let a = E::ScalarField::rand(&mut rng);
+    let b = E::ScalarField::rand(&mut rng);
+    let v = E::ScalarField::rand(&mut rng);
+    let proof = create_random_proof(
+        MySillyCircuit {
+            a: Some(a),
+            b: Some(b),
+        },
+        v,
+        &params1,
+        &mut rng,
+    )
+    .unwrap();
+
+    assert!(verify_proof(&pvk1, &proof, &[a * b]).unwrap());
+    assert!(!verify_proof(&pvk2, &proof, &[a * b]).unwrap());
+    assert_eq!(
+        verify_against_either(&pvk1, &pvk2, &proof, &[a * b]),
+        Some(0)
+    );
+    assert_eq!(
+        verify_against_either(&pvk2, &pvk1, &proof, &[a * b]),
+        Some(1)
+    );
+    assert_eq!(verify_against_either(&pvk2, &pvk2, &proof, &[a * b]), None);
+    assert_eq!(verify_against_either(&pvk1, &pvk2, &proof, &[a]), None);
+
+    // A key for another number of public inputs
+    let params3 =
+        generate_random_parameters::<E, _, _>(MyPublicCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    let pvk3 = prepare_verifying_key::<E>(&params3.vk);
+    assert_eq!(
+        verify_against_either(&pvk3, &pvk1, &proof, &[a * b]),
+        Some(1)
+    );
+}
+
+fn test_legacy_key_deserialization<E>()
+where
+    E: Pairing,
+{
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let params =
+        generate_random_parameters::<E, _, _>(MySillyCircuit { a: None, b: None }, &mut rng)
+            .unwrap();
+    let vk = &params.vk;
+
+    // The layout of version 0.2, without `commit_witness_count`
+    let mut legacy_vk = vec![];
+    vk.alpha_g1.serialize_compressed(&mut legacy_vk).unwrap();
+    vk.beta_g2.serialize_compressed(&mut legacy_vk).unwrap();
+    vk.gamma_g2.serialize_compressed(&mut legacy_vk).unwrap();
+    vk.delta_g2.serialize_compressed(&mut legacy_vk).unwrap();
+    vk.gamma_abc_g1
+        .serialize_compressed(&mut legacy_vk)
+        .unwrap();
+    vk.eta_gamma_inv_g1
+        .serialize_compressed(&mut legacy_vk)
+        .unwrap();
+    let mut legacy_pk = legacy_vk.clone();
+    params.common.serialize_compressed(&mut legacy_pk).unwrap();
+
+    assert_eq!(
+        VerifyingKey::<E>::deserialize_legacy(legacy_vk.as_slice(), 1).unwrap(),
+        *vk
+    );
+    assert_eq!(
+        ProvingKey::<E>::deserialize_legacy(legacy_pk.as_slice(), 1).unwrap(),
+        params
+    );
+    // The current layout can't read them
+    assert!(VerifyingKey::<E>::deserialize_compressed(legacy_vk.as_slice()).is_err());
+    assert!(ProvingKey::<E>::deserialize_compressed(legacy_pk.as_slice()).is_err());
+
+    // More public inputs than `gamma_abc_g1` has room for
+    assert!(VerifyingKey::<E>::deserialize_legacy(legacy_vk.as_slice(), 4).is_err());
+}
+
+mod bls12_377 {
+    use super::*;
+    use ark_bls12_377::Bls12_377;
+
+    #[test]
+    fn prove_and_verify() {
+        test_prove_and_verify::<Bls12_377>(1);
+    }
+
+    #[test]
+    fn split_proof_with_link_serialization() {
+        test_split_proof_with_link_serialization::<Bls12_377>();
+    }
+
+    #[test]
+    fn instance_map_at_point() {
+        test_instance_map_at_point::<Bls12_377>();
+    }
+
+    #[test]
+    fn public_input_count_checked_before_pairing() {
+        test_public_input_count_checked_before_pairing::<Bls12_377>();
+    }
+
+    #[test]
+    fn parameters_from_matrices() {
+        test_parameters_from_matrices::<Bls12_377>();
+    }
+
+    #[test]
+    fn create_and_self_check_proof() {
+        test_create_and_self_check_proof::<Bls12_377>();
+    }
+
+    #[test]
+    fn verify_vanilla_groth16_proof() {
+        test_verify_vanilla_groth16_proof::<Bls12_377>();
+    }
+
+    #[test]
+    fn parameters_are_deterministic() {
+        test_parameters_are_deterministic::<Bls12_377>();
+    }
+
+    #[test]
+    fn expected_proof_d() {
+        test_expected_proof_d::<Bls12_377>();
+    }
+
+    #[test]
+    fn empty_pedersen_bases() {
+        test_empty_pedersen_bases::<Bls12_377>();
+    }
+
+    #[test]
+    fn estimate_prover_memory() {
+        test_estimate_prover_memory::<Bls12_377>();
+    }
+
+    #[test]
+    fn verify_groth16_proof_with_prepared_b() {
+        test_verify_groth16_proof_with_prepared_b::<Bls12_377>();
+    }
+
+    #[test]
+    fn pad_committed_witnesses() {
+        test_pad_committed_witnesses::<Bls12_377>();
+    }
+
+    #[test]
+    fn link_commitment_verifier() {
+        test_link_commitment_verifier::<Bls12_377>();
+    }
+
+    #[test]
+    fn create_proof_with_report() {
+        test_create_proof_with_report::<Bls12_377>();
+    }
+
+    #[test]
+    fn compact_proof_serialization() {
+        test_compact_proof_serialization::<Bls12_377>();
+    }
+
+    #[test]
+    fn link_commitments_batch() {
+        test_link_commitments_batch::<Bls12_377>();
+    }
+
+    #[test]
+    fn derive_commitment_randomness() {
+        test_derive_commitment_randomness::<Bls12_377>();
+    }
+
+    #[test]
+    fn update_delta() {
+        test_update_delta::<Bls12_377>();
+    }
+
+    #[test]
+    fn check_link_consistency() {
+        test_check_link_consistency::<Bls12_377>();
+    }
+
+    #[test]
+    fn batch_inverse() {
+        test_batch_inverse::<Bls12_377>();
+    }
+
+    #[test]
+    fn verify_with_delta_updated_vk() {
+        test_verify_with_delta_updated_vk::<Bls12_377>();
+    }
+
+    #[test]
+    fn proof_from_separate_elements() {
+        test_proof_from_separate_elements::<Bls12_377>();
+    }
+
+    #[test]
+    fn witness_commitment_public_input_count() {
+        test_witness_commitment_public_input_count::<Bls12_377>();
+    }
+
+    #[test]
+    fn proof_eq_normalized() {
+        test_proof_eq_normalized::<Bls12_377>();
+    }
+
+    #[test]
+    fn verify_proof_checked() {
+        test_verify_proof_checked::<Bls12_377>();
+    }
+
+    #[test]
+    fn batch_finalize_d() {
+        test_batch_finalize_d::<Bls12_377>();
+    }
+
+    #[test]
+    fn compute_alpha_beta() {
+        test_compute_alpha_beta::<Bls12_377>();
+    }
+
+    #[test]
+    fn create_proof_with_concat_queries() {
+        test_create_proof_with_concat_queries::<Bls12_377>();
+    }
+
+    #[test]
+    fn verify_d_only() {
+        test_verify_d_only::<Bls12_377>();
+    }
+
+    #[test]
+    fn domain_size() {
+        test_domain_size::<Bls12_377>();
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn verify_with_shared_pvk() {
+        test_verify_with_shared_pvk::<Bls12_377>();
+    }
+
+    #[cfg(feature = "debug-mapping")]
+    #[test]
+    fn variable_mapping() {
+        test_variable_mapping::<Bls12_377>();
+    }
+
+    #[test]
+    fn create_proof_with_h_commitment() {
+        test_create_proof_with_h_commitment::<Bls12_377>();
+    }
+
+    #[test]
+    fn identity_constant_term() {
+        test_identity_constant_term::<Bls12_377>();
+    }
+
+    #[test]
+    fn verify_proof_by_name() {
+        test_verify_proof_by_name::<Bls12_377>();
+    }
+
+    #[test]
+    fn standalone_subspace_snark() {
+        test_standalone_subspace_snark::<Bls12_377>();
+    }
+
+    #[test]
+    fn large_counts() {
+        test_large_counts::<Bls12_377>();
+    }
+
+    #[test]
+    fn qap_density() {
+        test_qap_density::<Bls12_377>();
+    }
+
+    #[test]
+    fn verify_proof_with_disclosed_commitment() {
+        test_verify_proof_with_disclosed_commitment::<Bls12_377>();
+    }
+
+    #[test]
+    fn versioned_verifying_key_with_link() {
+        test_versioned_verifying_key_with_link::<Bls12_377>();
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn msm_window_tuning() {
+        test_msm_window_tuning::<Bls12_377>();
+    }
+
+    #[test]
+    fn verify_witness_commitment_with_eta() {
+        test_verify_witness_commitment_with_eta::<Bls12_377>();
+    }
+
+    #[test]
+    fn verify_proof_with_link_single_pairing() {
+        test_verify_proof_with_link_single_pairing::<Bls12_377>();
+    }
+
+    #[test]
+    fn proof_with_link_from_parts() {
+        test_proof_with_link_from_parts::<Bls12_377>();
+    }
+
+    #[test]
+    fn check_link_key_pair() {
+        test_check_link_key_pair::<Bls12_377>();
+    }
+
+    #[test]
+    fn prepare_inputs_sparse() {
+        test_prepare_inputs_sparse::<Bls12_377>();
+    }
+
+    #[test]
+    fn truncate_commitment() {
+        test_truncate_commitment::<Bls12_377>();
+    }
+
+    #[test]
+    fn random_circuits() {
+        test_random_circuits::<Bls12_377>();
+    }
+
+    #[test]
+    fn prove_with_zero_r() {
+        test_prove_with_zero_r::<Bls12_377>();
+    }
+
+    #[test]
+    fn verify_proof_with_link_prepared_inputs() {
+        test_verify_proof_with_link_prepared_inputs::<Bls12_377>();
+    }
+
+    #[test]
+    fn verify_proofs_batch_dedup() {
+        test_verify_proofs_batch_dedup::<Bls12_377>();
+    }
+
+    #[test]
+    fn proof_coordinates() {
+        test_proof_coordinates::<Bls12_377, _, _>();
+    }
+
+    #[test]
+    fn link_blinding_base() {
+        test_link_blinding_base::<Bls12_377>();
+    }
+
+    #[test]
+    fn proving_key_element_count() {
+        test_proving_key_element_count::<Bls12_377>();
+    }
+
+    #[test]
+    fn shared_commitment() {
+        test_shared_commitment::<Bls12_377>();
+    }
+
+    #[test]
+    fn commitment_bases() {
+        test_commitment_bases::<Bls12_377>();
+    }
+
+    #[test]
+    fn reusable_proof() {
+        test_reusable_proof::<Bls12_377>();
+    }
+
+    #[test]
+    fn supports_commitment() {
+        test_supports_commitment::<Bls12_377>();
+    }
+
+    #[test]
+    fn random_proof_checked() {
+        test_random_proof_checked::<Bls12_377>();
+    }
+
+    #[test]
+    fn analyze_circuit() {
+        test_analyze_circuit::<Bls12_377>();
+    }
+
+    #[test]
+    fn generator_cache() {
+        test_generator_cache::<Bls12_377>();
+    }
+
+    #[test]
+    fn is_legogroth() {
+        test_is_legogroth::<Bls12_377>();
+    }
+
+    #[test]
+    fn verify_proof_from_raw() {
+        test_verify_proof_from_raw::<Bls12_377>();
+    }
+
+    #[test]
+    fn b_g2_query_matches_g1() {
+        test_b_g2_query_matches_g1::<Bls12_377>();
+    }
+
+    #[test]
+    fn verify_link_relation() {
+        test_verify_link_relation::<Bls12_377>();
+    }
+
+    #[test]
+    fn updatable_inputs() {
+        test_updatable_inputs::<Bls12_377>();
+    }
+
+    #[test]
+    fn link_without_public_inputs() {
+        test_link_without_public_inputs::<Bls12_377>();
+    }
+
+    #[test]
+    fn proof_with_link_prepared() {
+        test_proof_with_link_prepared::<Bls12_377>();
+    }
+
+    #[test]
+    fn verify_proof_strict() {
+        test_verify_proof_strict::<Bls12_377>();
+    }
+
+    #[test]
+    fn proof_from_assignment() {
+        test_proof_from_assignment::<Bls12_377>();
+    }
+
+    #[test]
+    fn link_matrix_serialization() {
+        test_link_matrix_serialization::<Bls12_377>();
+    }
+
+    #[test]
+    fn prover_self_verify() {
+        test_prover_self_verify::<Bls12_377>();
+    }
+
+    #[test]
+    fn generate_with_domain_and_t() {
+        test_generate_with_domain_and_t::<Bls12_377>();
+    }
+
+    #[test]
+    fn link_types_clone_and_debug() {
+        test_link_types_clone_and_debug::<Bls12_377>();
+    }
+
+    #[test]
+    fn input_capacity() {
+        test_input_capacity::<Bls12_377>();
+    }
+
+    #[test]
+    fn prepared_proof() {
+        test_prepared_proof::<Bls12_377>();
+    }
+
+    #[test]
+    fn committed_witnesses_overlap_public_inputs() {
+        test_committed_witnesses_overlap_public_inputs::<Bls12_377>();
+    }
+
+    #[test]
+    fn proofs_same_statement() {
+        test_proofs_same_statement::<Bls12_377>();
+    }
+
+    #[test]
+    fn position_opening() {
+        test_position_opening::<Bls12_377>();
+    }
+
+    #[test]
+    fn verifying_key_only() {
+        test_verifying_key_only::<Bls12_377>();
+    }
+
+    #[test]
+    fn prepared_verifying_key_gt() {
+        test_prepared_verifying_key_gt::<Bls12_377>();
+    }
+
+    #[cfg(feature = "soa-inputs")]
+    #[test]
+    fn soa_inputs() {
+        test_soa_inputs::<Bls12_377, _>();
+    }
+
+    #[test]
+    fn check_v() {
+        test_check_v::<Bls12_377>();
+    }
+
+    #[test]
+    fn commitment_equality() {
+        test_commitment_equality::<Bls12_377>();
+    }
+
+    #[test]
+    fn link_checks_concurrently() {
+        test_link_checks_concurrently::<Bls12_377>();
+    }
+
+    #[test]
+    fn verify_all() {
+        test_verify_all::<Bls12_377>();
+    }
+
+    #[test]
+    fn verify_against_either() {
+        test_verify_against_either::<Bls12_377>();
+    }
+
+    #[test]
+    fn legacy_key_deserialization() {
+        test_legacy_key_deserialization::<Bls12_377>();
+    }
+}
+
+mod bls12_381 {
+    use super::*;
+    use ark_bls12_381::{Bls12_381, Fq};
+    use ark_std::str::FromStr;
+
+    // Coordinates of the standard generators of BLS12-381
+    #[test]
+    fn proof_coordinates_known_answer() {
+        let fq = |s: &str| Fq::from_str(s).unwrap();
+        let g1 = [
+            fq("3685416753713387016781088315183077757961620795782546409894578378688607592378376318836054947676345821548104185464507"),
+            fq("1339506544944476473020471379941921221584933875938349620426543736416511423956333506472724655353366534992391756441569"),
+        ];
+        let g2 = vec![
+            fq("352701069587466618187139116011060144890029952792775240219908644239793785735715026873347600343865175952761926303160"),
+            fq("3059144344244213709971259814753781636986470325476647558659373206291635324768958432433509563104347017837885763365758"),
+            fq("1985150602287291935568054521177171638300868978215655730859378665066344726373823718423869104263333984641494340347905"),
+            fq("927553665492332455747201965776037880757740193453592970025027978793976877002675564980949289727957565575433344219582"),
+        ];
+
+        let proof = Proof::<Bls12_381> {
+            a: <Bls12_381 as Pairing>::G1Affine::generator(),
+            b: <Bls12_381 as Pairing>::G2Affine::generator(),
+            c: <Bls12_381 as Pairing>::G1Affine::zero(),
+            d: <Bls12_381 as Pairing>::G1Affine::generator(),
+        };
+        let coords = proof.to_coordinates();
+        assert_eq!(
+            coords,
+            ProofCoords {
+                a: Some(g1),
+                b: Some(g2),
+                c: None,
+                d: Some(g1),
+            }
+        );
+        assert_eq!(
+            Proof::<Bls12_381>::from_coordinates(&coords).unwrap(),
+            proof
+        );
+
+        test_proof_coordinates::<Bls12_381, _, _>();
+    }
+}
+
+mod cp6_782 {
+    use super::test_prove_and_verify;
+
+    use ark_cp6_782::CP6_782;
+
+    #[test]
+    fn prove_and_verify() {
+        test_prove_and_verify::<CP6_782>(1);
+    }
+}
+
+mod mnt4_298_mnt6_298 {
+    use super::{test_domain_too_large, test_dual_prepared_verifying_key};
+    use ark_mnt4_298::MNT4_298;
+    use ark_mnt6_298::MNT6_298;
+
+    #[test]
+    fn domain_too_large() {
+        // The scalar field of MNT6-298 has mixed-radix domains of up to `2^17 * 7^2` elements
+        test_domain_too_large::<MNT6_298>((1 << 17) * 49);
+    }
+
+    #[test]
+    fn dual_prepared_verifying_key() {
+        test_dual_prepared_verifying_key::<MNT4_298, MNT6_298>();
+    }
+}
+
+#[cfg(feature = "zkey")]
+mod bn254 {
+    use super::*;
+    use crate::zkey::read_zkey;
+    use ark_bn254::{Bn254, Fq, Fr, G1Affine};
+    use core::str::FromStr;
+
+    // The `test.zkey` of ark-circom (MIT or Apache-2.0), written by snarkjs for the Circom circuit
+    // `c <== a * b` with the private inputs `a`, `b` and the public output `c`
+    const MULTIPLIER_ZKEY: &[u8] = include_bytes!("../testdata/multiplier.zkey");
+
+    fn g1(x: &str, y: &str) -> G1Affine {
+        G1Affine::new(Fq::from_str(x).unwrap(), Fq::from_str(y).unwrap())
+    }
+
+    #[test]
+    fn zkey_import() {
+        let pk = ProvingKey::<Bn254>::from_zkey_reader(MULTIPLIER_ZKEY).unwrap();
+
+        // As in the `verification_key.json` exported by snarkjs for the same key
+        assert_eq!(
+            pk.vk.alpha_g1,
+            g1(
+                "20491192805390485299153009773594534940189261866228447918068658471970481763042",
+                "9383485363053290200918347156157836566562967994039712273449902621266178545958"
+            )
+        );
+        assert_eq!(
+            pk.vk.gamma_abc_g1,
+            vec![
+                g1(
+                    "6819801395408938350212900248749732364821477541620635511814266536599629892365",
+                    "9092252330033992554755034971584864587974280972948086568597554018278609861372"
+                ),
+                g1(
+                    "17882351432929302592725330552407222299541667716607588771282887857165175611387",
+                    "18907419617206324833977586007131055763810739835484972981819026406579664278293"
+                ),
+            ]
+        );
+        // Plain mode
+        assert!(pk.vk.eta_gamma_inv_g1.is_zero());
+        assert!(pk.common.eta_delta_inv_g1.is_zero());
+        assert_eq!(pk.vk.commit_witness_count, 0);
+        assert_eq!(pk.vk.expected_public_input_count(), 1);
+        assert_eq!(pk.common.a_query.len(), 4);
+        assert_eq!(pk.common.l_query.len(), 2);
+        assert_eq!(pk.common.h_query.len(), 4);
+
+        let mut bad_magic = MULTIPLIER_ZKEY.to_vec();
+        bad_magic[0] = b'x';
+        assert_eq!(
+            ProvingKey::<Bn254>::from_zkey_reader(bad_magic.as_slice()).err(),
+            Some(Error::InvalidZkey("not a zkey"))
+        );
+        assert_eq!(
+            ProvingKey::<Bn254>::from_zkey_reader(&MULTIPLIER_ZKEY[..MULTIPLIER_ZKEY.len() - 1])
+                .err(),
+            Some(Error::InvalidZkey("unexpected end of input"))
+        );
+    }
+
+    #[test]
+    fn zkey_prove() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let (pk, constraints) = read_zkey(MULTIPLIER_ZKEY).unwrap();
+        let pvk = prepare_verifying_key(&pk.vk);
+
+        let a = Fr::from(3u64);
+        let b = Fr::from(11u64);
+        let c = a * b;
+        let full_assignment = [Fr::one(), c, a, b];
+        let h = constraints.witness_map(&full_assignment).unwrap();
+        let proof = create_proof_from_assignment(
+            &full_assignment[..2],
+            &full_assignment[2..],
+            &h,
+            &pk.common,
+            &pk.vk,
+            Fr::rand(&mut rng),
+            Fr::rand(&mut rng),
+            Fr::rand(&mut rng),
+        )
+        .unwrap();
+        assert!(proof.d.is_zero());
+        assert!(verify_proof(&pvk, &proof, &[c]).unwrap());
+        assert!(!verify_proof(&pvk, &proof, &[c + Fr::one()]).unwrap());
+
+        // An assignment not satisfying the constraints gives a proof that doesn't verify
+        let wrong_assignment = [Fr::one(), c, a, a];
+        let h = constraints.witness_map(&wrong_assignment).unwrap();
+        let proof = create_proof_from_assignment(
+            &wrong_assignment[..2],
+            &wrong_assignment[2..],
+            &h,
+            &pk.common,
+            &pk.vk,
+            Fr::rand(&mut rng),
+            Fr::rand(&mut rng),
+            Fr::rand(&mut rng),
+        )
+        .unwrap();
+        assert!(!verify_proof(&pvk, &proof, &[c]).unwrap());
+
+        assert_eq!(
+            constraints.witness_map(&full_assignment[..3]).err(),
+            Some(SynthesisError::MalformedVerifyingKey.into())
+        );
+    }
+}
+
+// Only built by `cargo test --no-default-features`, where the crate itself is compiled without `std`
+#[cfg(not(feature = "std"))]
+mod no_std {
+    use super::*;
+    use ark_bls12_377::Bls12_377;
+
+    #[test]
+    fn prepare_and_verify() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+
+        let params = generate_random_parameters::<Bls12_377, _, _>(
+            MySillyCircuit { a: None, b: None },
+            &mut rng,
+        )
+        .unwrap();
+        let pvk = prepare_verifying_key::<Bls12_377>(&params.vk);
+
+        let a = <Bls12_377 as Pairing>::ScalarField::rand(&mut rng);
+        let b = <Bls12_377 as Pairing>::ScalarField::rand(&mut rng);
+        let v = <Bls12_377 as Pairing>::ScalarField::rand(&mut rng);
+        let proof = create_random_proof(
+            MySillyCircuit {
+                a: Some(a),
+                b: Some(b),
+            },
+            v,
+            &params,
+            &mut rng,
+        )
+        .unwrap();
+
+        assert!(verify_proof(&pvk, &proof, &[a * b]).unwrap());
+        let d = calculate_d(&pvk, &proof, &[a * b]).unwrap();
+        assert!(verify_groth16_proof(&pvk, proof.a, proof.b, proof.c, d).unwrap());
+        assert!(!verify_proof(&pvk, &proof, &[a]).unwrap());
     }
 }
@@ -1,31 +1,137 @@
-use crate::{VerifyingKeyWithLink, ProofWithLink};
+#[cfg(feature = "soa-inputs")]
+use super::PreparedVerifyingKeySoa;
+use super::{
+    CommitmentEqualityProof, DeltaUpdateProof, DualPreparedVerifyingKey, DualProof,
+    PositionOpening, PreparedProof, PreparedVerifyingKey, Proof, ProvingKey, UpdatableInputs,
+    VerifyingKey,
+};
 use crate::link::{PESubspaceSnark, SubspaceSnark};
-use ark_ff::{One, PrimeField};
-use super::{PreparedVerifyingKey, Proof, VerifyingKey};
+use crate::{
+    create_d_projective,
+    error::{Error, VerifyError},
+    generator::{delta_update_r_g2, join},
+    prover::{
+        commitment_equality_challenge, create_d_projective_with_eta, position_opening_challenge,
+        shared_commitment_bases, shared_commitment_challenge, windowed_commitment,
+    },
+    LinkCommitmentVerifier, ProofWithLink, SharedCommitmentProof, VerifyingKeyWithLink,
+};
+use ark_ff::{Field, One, PrimeField, UniformRand, Zero};
 
+#[cfg(feature = "soa-inputs")]
+use ark_ec::short_weierstrass::{Affine as SWAffine, SWCurveConfig};
 use ark_ec::{
-    pairing::Pairing,AffineRepr, CurveGroup,
-    VariableBaseMSM,
+    pairing::Pairing, scalar_mul::fixed_base::FixedBase, AffineRepr, CurveGroup, VariableBaseMSM,
 };
 use ark_relations::r1cs::{Result as R1CSResult, SynthesisError};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Valid};
 
 use ark_std::{
     cfg_iter,
+    collections::{BTreeMap, BTreeSet},
+    format,
+    rand::Rng,
+    string::String,
     vec,
     vec::Vec,
 };
 use core::ops::{AddAssign, Neg};
+use digest::Digest;
 
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
 
 /// Prepare the verifying key `vk` for use in proof verification.
 pub fn prepare_verifying_key<E: Pairing>(vk: &VerifyingKey<E>) -> PreparedVerifyingKey<E> {
-    PreparedVerifyingKey {
+    let pvk = PreparedVerifyingKey {
         vk: vk.clone(),
-        alpha_g1_beta_g2: E::pairing(vk.alpha_g1, vk.beta_g2).0,
+        alpha_g1_beta_g2: compute_alpha_beta(vk),
         gamma_g2_neg_pc: vk.gamma_g2.into_group().neg().into().into(),
         delta_g2_neg_pc: vk.delta_g2.into_group().neg().into().into(),
+    };
+    debug_assert!(pvk.validate_gt().is_ok());
+    pvk
+}
+
+/// Same as [`prepare_verifying_key`] but first checks `vk` with [`VerifyingKey::validate`].
+pub fn prepare_verifying_key_checked<E: Pairing>(
+    vk: &VerifyingKey<E>,
+) -> crate::Result<PreparedVerifyingKey<E>> {
+    vk.validate()?;
+    Ok(prepare_verifying_key(vk))
+}
+
+/// Compute `e(alpha * G, beta * H)`, the element stored in the prepared verifying key.
+pub fn compute_alpha_beta<E: Pairing>(vk: &VerifyingKey<E>) -> E::TargetField {
+    E::pairing(vk.alpha_g1, vk.beta_g2).0
+}
+
+impl<E: Pairing> PreparedVerifyingKey<E> {
+    /// Check that the precomputed elements match the verifying key `self.vk`, e.g. for a prepared key
+    /// that was imported rather than created with [`prepare_verifying_key`]. Returns
+    /// `Error::InvalidPreparedVerifyingKey` otherwise.
+    pub fn validate(&self) -> crate::Result<()> {
+        if self.alpha_g1_beta_g2 != compute_alpha_beta(&self.vk) {
+            return Err(Error::InvalidPreparedVerifyingKey);
+        }
+        // Prepared elements are compared through their serialization as they don't implement `Eq`
+        let prepared_eq = |a: &E::G2Prepared, b: E::G2Prepared| {
+            let (mut a_bytes, mut b_bytes) = (Vec::new(), Vec::new());
+            a.serialize_uncompressed(&mut a_bytes).is_ok()
+                && b.serialize_uncompressed(&mut b_bytes).is_ok()
+                && a_bytes == b_bytes
+        };
+        if !prepared_eq(
+            &self.gamma_g2_neg_pc,
+            self.vk.gamma_g2.into_group().neg().into().into(),
+        ) || !prepared_eq(
+            &self.delta_g2_neg_pc,
+            self.vk.delta_g2.into_group().neg().into().into(),
+        ) {
+            return Err(Error::InvalidPreparedVerifyingKey);
+        }
+        Ok(())
+    }
+
+    /// Check that `alpha_g1_beta_g2` is in the form that `final_exponentiation` outputs, which the
+    /// verifiers compare it to by representation: canonically encoded and in the order `r` subgroup
+    /// of the target field. Much cheaper than [`Self::validate`] as no pairing is computed, but does
+    /// not check that the element is the one of `self.vk`. Returns
+    /// `Error::InvalidPreparedVerifyingKey` otherwise.
+    pub fn validate_gt(&self) -> crate::Result<()> {
+        let gt = &self.alpha_g1_beta_g2;
+        let mut bytes = Vec::with_capacity(gt.uncompressed_size());
+        gt.serialize_uncompressed(&mut bytes)
+            .map_err(|_| Error::InvalidPreparedVerifyingKey)?;
+        // Deserialization rejects coordinates that are not reduced
+        let canonical = E::TargetField::deserialize_uncompressed(bytes.as_slice())
+            .is_ok_and(|decoded| decoded == *gt);
+        if !canonical || gt.is_zero() || !gt.pow(E::ScalarField::MODULUS).is_one() {
+            return Err(Error::InvalidPreparedVerifyingKey);
+        }
+        Ok(())
+    }
+}
+
+impl<E1: Pairing, E2: Pairing> DualPreparedVerifyingKey<E1, E2> {
+    /// Prepare the verifying keys `vk1` and `vk2` of the two curves.
+    pub fn new(vk1: &VerifyingKey<E1>, vk2: &VerifyingKey<E2>) -> Self {
+        Self {
+            first: prepare_verifying_key(vk1),
+            second: prepare_verifying_key(vk2),
+        }
+    }
+
+    /// Verify `proof` with the prepared verifying key of the curve it is over.
+    pub fn verify(&self, proof: DualProof<'_, E1, E2>) -> R1CSResult<bool> {
+        match proof {
+            DualProof::First(proof, public_inputs) => {
+                verify_proof(&self.first, proof, public_inputs)
+            }
+            DualProof::Second(proof, public_inputs) => {
+                verify_proof(&self.second, proof, public_inputs)
+            }
+        }
     }
 }
 
@@ -35,7 +141,7 @@ pub fn prepare_inputs<E: Pairing>(
     pvk: &PreparedVerifyingKey<E>,
     public_inputs: &[E::ScalarField],
 ) -> crate::Result<E::G1> {
-    if (public_inputs.len() + 1) > pvk.vk.gamma_abc_g1.len() {
+    if public_inputs.len() >= pvk.vk.gamma_abc_g1.len() {
         return Err(SynthesisError::MalformedVerifyingKey).map_err(|e| e.into());
     }
 
@@ -54,6 +160,145 @@ pub fn prepare_inputs<E: Pairing>(
     }
 }
 
+/// Number of bases of a [`PreparedVerifyingKeySoa`] rebuilt at a time by [`prepare_inputs_soa`].
+#[cfg(feature = "soa-inputs")]
+const SOA_CHUNK_SIZE: usize = 256;
+
+/// Same as [`prepare_verifying_key`] but also stores `gamma_abc_g1` in struct-of-arrays layout.
+#[cfg(feature = "soa-inputs")]
+pub fn prepare_verifying_key_soa<E, P>(vk: &VerifyingKey<E>) -> PreparedVerifyingKeySoa<E, P>
+where
+    E: Pairing<G1Affine = SWAffine<P>>,
+    P: SWCurveConfig,
+{
+    let gamma_abc_x = vk.gamma_abc_g1.iter().map(|g| g.x).collect();
+    let gamma_abc_y = vk.gamma_abc_g1.iter().map(|g| g.y).collect();
+    let gamma_abc_infinity = vk.gamma_abc_g1.iter().map(|g| g.infinity).collect();
+    PreparedVerifyingKeySoa {
+        pvk: prepare_verifying_key(vk),
+        gamma_abc_x,
+        gamma_abc_y,
+        gamma_abc_infinity,
+    }
+}
+
+/// Same as [`prepare_inputs`] but reads the bases from the struct-of-arrays layout of `pvk`. They are
+/// rebuilt and multiplied in chunks small enough to stay in cache, so the MSM only makes sequential
+/// passes over the coordinate arrays. The result is the same as of [`prepare_inputs`].
+#[cfg(feature = "soa-inputs")]
+pub fn prepare_inputs_soa<E, P>(
+    pvk: &PreparedVerifyingKeySoa<E, P>,
+    public_inputs: &[E::ScalarField],
+) -> crate::Result<E::G1>
+where
+    E: Pairing<G1Affine = SWAffine<P>>,
+    P: SWCurveConfig,
+{
+    if public_inputs.len() >= pvk.gamma_abc_x.len() {
+        return Err(SynthesisError::MalformedVerifyingKey.into());
+    }
+
+    let one = E::ScalarField::one();
+    let scalars = core::iter::once(&one)
+        .chain(public_inputs)
+        .map(|a| a.into_bigint())
+        .collect::<Vec<_>>();
+    let mut bases = Vec::with_capacity(SOA_CHUNK_SIZE);
+    let mut d = E::G1::zero();
+    for (start, chunk) in (0..scalars.len())
+        .step_by(SOA_CHUNK_SIZE)
+        .zip(scalars.chunks(SOA_CHUNK_SIZE))
+    {
+        let end = start + chunk.len();
+        bases.clear();
+        bases.extend(
+            pvk.gamma_abc_x[start..end]
+                .iter()
+                .zip(&pvk.gamma_abc_y[start..end])
+                .zip(&pvk.gamma_abc_infinity[start..end])
+                .map(|((x, y), infinity)| {
+                    if *infinity {
+                        SWAffine::identity()
+                    } else {
+                        SWAffine::new_unchecked(*x, *y)
+                    }
+                }),
+        );
+        d += E::G1::msm_bigint(&bases, chunk);
+    }
+    Ok(d)
+}
+
+/// Same as [`verify_proof`] but with the public inputs prepared by [`prepare_inputs_soa`].
+#[cfg(feature = "soa-inputs")]
+pub fn verify_proof_soa<E, P>(
+    pvk: &PreparedVerifyingKeySoa<E, P>,
+    proof: &Proof<E>,
+    public_inputs: &[E::ScalarField],
+) -> R1CSResult<bool>
+where
+    E: Pairing<G1Affine = SWAffine<P>>,
+    P: SWCurveConfig,
+{
+    if public_inputs.len() != pvk.pvk.vk.expected_public_input_count() {
+        return Err(SynthesisError::MalformedVerifyingKey);
+    }
+    let prepared_inputs = prepare_inputs_soa(pvk, public_inputs)
+        .map_err(|_| SynthesisError::MalformedVerifyingKey)?;
+    verify_proof_with_prepared_inputs(&pvk.pvk, proof, &prepared_inputs)
+}
+
+/// Same as [`prepare_inputs`] but for public inputs given as `(index, value)` pairs, where `index`
+/// is the position of the input among the public inputs and all other inputs are zero. Only the
+/// non-zero values are accumulated, which is much cheaper for mostly-zero inputs. Values given for
+/// the same index are added up. Returns `Error::PublicInputIndexOutOfRange(index, count)` if an
+/// index is not less than the number of public inputs `count` of `pvk.vk`.
+pub fn prepare_inputs_sparse<E: Pairing>(
+    pvk: &PreparedVerifyingKey<E>,
+    public_inputs: &[(usize, E::ScalarField)],
+) -> crate::Result<E::G1> {
+    let count = pvk.vk.expected_public_input_count();
+    if let Some((index, _)) = public_inputs.iter().find(|(index, _)| *index >= count) {
+        return Err(Error::PublicInputIndexOutOfRange(*index, count));
+    }
+
+    let (bases, scalars): (Vec<_>, Vec<_>) = public_inputs
+        .iter()
+        .filter(|(_, value)| !value.is_zero())
+        .map(|(index, value)| (pvk.vk.gamma_abc_g1[index + 1], value.into_bigint()))
+        .unzip();
+    let mut d = E::G1::msm_bigint(&bases, &scalars);
+    d.add_assign(&pvk.vk.gamma_abc_g1[0]);
+    Ok(d)
+}
+
+/// Verify a LegoGroth16 proof like [`verify_proof`] but with the public inputs already combined with
+/// the verification key by [`prepare_inputs`], e.g. when they are shared by several proofs.
+pub fn verify_proof_with_prepared_inputs<E: Pairing>(
+    pvk: &PreparedVerifyingKey<E>,
+    proof: &Proof<E>,
+    prepared_inputs: &E::G1,
+) -> R1CSResult<bool> {
+    let d = (*prepared_inputs + proof.d).into_affine();
+    verify_groth16_proof(pvk, proof.a, proof.b, proof.c, d)
+}
+
+/// Same as [`verify_proof_with_link`] but with the public inputs already prepared with
+/// [`prepare_inputs`], so the input MSM can be computed once, or elsewhere, for many proofs.
+pub fn verify_proof_with_link_prepared_inputs<E: Pairing>(
+    pvk: &PreparedVerifyingKey<E>,
+    vk: &VerifyingKeyWithLink<E>,
+    proof: &ProofWithLink<E>,
+    prepared_inputs: &E::G1,
+) -> R1CSResult<bool> {
+    // The Groth16 and the CP-link pairings are independent so are computed concurrently
+    let (proof_verified, link_verified) = join(
+        || verify_proof_with_prepared_inputs(pvk, &proof.groth16_proof, prepared_inputs),
+        || verify_link_relation(vk, &proof.link_d, &proof.groth16_proof.d, &proof.link_pi),
+    );
+    Ok(proof_verified? && link_verified)
+}
+
 /// Verify the groth16 proof and the the Subspace Snark on the equality of openings of cp_link and proof.d
 pub fn verify_proof_with_link<E: Pairing>(
     pvk: &PreparedVerifyingKey<E>,
@@ -61,29 +306,320 @@ pub fn verify_proof_with_link<E: Pairing>(
     proof: &ProofWithLink<E>,
     public_inputs: &[E::ScalarField],
 ) -> R1CSResult<bool> {
-    let proof_verified = verify_proof(
-        pvk,
-        &proof.groth16_proof,
+    // The Groth16 and the CP-link pairings are independent so are computed concurrently
+    let (proof_verified, link_verified) = join(
+        || verify_proof(pvk, &proof.groth16_proof, public_inputs),
+        || verify_link_relation(vk, &proof.link_d, &proof.groth16_proof.d, &proof.link_pi),
+    );
+    Ok(proof_verified? && link_verified)
+}
+
+/// Verify the CP-link proof `link_pi` that `link_d` and `groth16_d`, a proof's commitment `d`, commit
+/// to the same witnesses, with commitments supplied by the caller rather than taken from a
+/// [`ProofWithLink`], e.g. when `link_d` is a public input of a larger protocol. The order matters:
+/// `link_d` is the commitment under `vk.link_bases`.
+pub fn verify_link_relation<E: Pairing>(
+    vk: &VerifyingKeyWithLink<E>,
+    link_d: &E::G1Affine,
+    groth16_d: &E::G1Affine,
+    link_pi: &E::G1Affine,
+) -> bool {
+    let commitments = vec![*link_d, *groth16_d];
+    PESubspaceSnark::<E>::verify(&vk.link_pp, &vk.link_vk, &commitments, link_pi)
+}
+
+/// Same as [`verify_proof_with_link`] but the Groth16 and the CP-link pairing checks are folded into
+/// a single multi-Miller loop with one final exponentiation. The CP-link check, whose target is the
+/// identity, is raised to a random power so that it can't cancel out a failing Groth16 check.
+pub fn verify_proof_with_link_single_pairing<E: Pairing, R: Rng>(
+    pvk: &PreparedVerifyingKey<E>,
+    vk: &VerifyingKeyWithLink<E>,
+    proof: &ProofWithLink<E>,
+    public_inputs: &[E::ScalarField],
+    rng: &mut R,
+) -> R1CSResult<bool> {
+    if public_inputs.len() != pvk.vk.expected_public_input_count() {
+        return Err(SynthesisError::MalformedVerifyingKey);
+    }
+    if vk.link_pp.l != 2 || vk.link_vk.c.len() != 2 {
+        return Err(SynthesisError::MalformedVerifyingKey);
+    }
+    let d = calculate_d(pvk, &proof.groth16_proof, public_inputs)
+        .map_err(|_| SynthesisError::MalformedVerifyingKey)?;
+
+    let r = E::ScalarField::rand(rng);
+    let link_g1 = E::G1::normalize_batch(&[
+        proof.link_d * r,
+        proof.groth16_proof.d * r,
+        proof.link_pi * -r,
+    ]);
+
+    let qap = E::multi_miller_loop(
+        [
+            proof.groth16_proof.a,
+            proof.groth16_proof.c,
+            d,
+            link_g1[0],
+            link_g1[1],
+            link_g1[2],
+        ],
+        [
+            proof.groth16_proof.b.into(),
+            pvk.delta_g2_neg_pc.clone(),
+            pvk.gamma_g2_neg_pc.clone(),
+            vk.link_vk.c[0].into(),
+            vk.link_vk.c[1].into(),
+            vk.link_vk.a.into(),
+        ],
+    );
+
+    let test = E::final_exponentiation(qap).ok_or(SynthesisError::UnexpectedIdentity)?;
+
+    Ok(test.0 == pvk.alpha_g1_beta_g2)
+}
+
+/// Verify a single LegoGroth16 proof against the unprepared verifying key `vk`. The key is prepared
+/// on every call, which costs a pairing, the preparation of 2 G2 elements and a copy of `vk`, so when
+/// several proofs are verified with the same key, prepare it once with [`prepare_verifying_key`] and use
+/// [`verify_proof`] instead.
+///
+/// ```
+/// use ark_bls12_381::{Bls12_381, Fr};
+/// use ark_relations::{
+///     lc,
+///     r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+/// };
+/// use ark_std::{rand::{rngs::StdRng, SeedableRng}, UniformRand};
+/// use legogro16::{create_random_proof, generate_random_parameters, verify};
+///
+/// // Prove knowledge of `a` and `b` such that `a * b = c` for a public `c`
+/// struct MySillyCircuit {
+///     a: Option<Fr>,
+///     b: Option<Fr>,
+/// }
+///
+/// impl ConstraintSynthesizer<Fr> for MySillyCircuit {
+///     fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+///         let a = cs.new_witness_variable(|| self.a.ok_or(SynthesisError::AssignmentMissing))?;
+///         let b = cs.new_witness_variable(|| self.b.ok_or(SynthesisError::AssignmentMissing))?;
+///         let c = cs.new_input_variable(|| {
+///             Ok(self.a.ok_or(SynthesisError::AssignmentMissing)?
+///                 * self.b.ok_or(SynthesisError::AssignmentMissing)?)
+///         })?;
+///         cs.enforce_constraint(lc!() + a, lc!() + b, lc!() + c)
+///     }
+/// }
+///
+/// let mut rng = StdRng::seed_from_u64(0u64);
+/// let params = generate_random_parameters::<Bls12_381, _, _>(
+///     MySillyCircuit { a: None, b: None },
+///     &mut rng,
+/// )
+/// .unwrap();
+///
+/// let (a, b, v) = (Fr::rand(&mut rng), Fr::rand(&mut rng), Fr::rand(&mut rng));
+/// let circuit = MySillyCircuit { a: Some(a), b: Some(b) };
+/// let proof = create_random_proof(circuit, v, &params, &mut rng).unwrap();
+///
+/// assert!(verify(&params.vk, &proof, &[a * b]).unwrap());
+/// ```
+pub fn verify<E: Pairing>(
+    vk: &VerifyingKey<E>,
+    proof: &Proof<E>,
+    public_inputs: &[E::ScalarField],
+) -> crate::Result<bool> {
+    Ok(verify_proof(
+        &prepare_verifying_key(vk),
+        proof,
         public_inputs,
-    )?;
-    let commitments = vec![proof.link_d.clone(), proof.groth16_proof.d.clone()];
-    let link_verified = PESubspaceSnark::<E>::verify(&vk.link_pp, &vk.link_vk, &commitments, &proof.link_pi);
-    Ok(proof_verified && link_verified)
+    )?)
 }
 
-/// Verify a LegoGroth16 proof `proof` against the prepared verification key `pvk`
+/// Verify a LegoGroth16 proof `proof` against the prepared verification key `pvk`.
+/// The number of public inputs is checked before doing any group operation.
 pub fn verify_proof<E: Pairing>(
     pvk: &PreparedVerifyingKey<E>,
     proof: &Proof<E>,
     public_inputs: &[E::ScalarField],
 ) -> R1CSResult<bool> {
-    verify_groth16_proof(
-        pvk,
-        proof.a,
-        proof.b,
-        proof.c,
-        calculate_d(pvk, proof, public_inputs).unwrap(),
-    )
+    if public_inputs.len() != pvk.vk.expected_public_input_count() {
+        return Err(SynthesisError::MalformedVerifyingKey);
+    }
+    let d = calculate_d(pvk, proof, public_inputs)
+        .map_err(|_| SynthesisError::MalformedVerifyingKey)?;
+    verify_groth16_proof(pvk, proof.a, proof.b, proof.c, d)
+}
+
+/// Verify `proof` against a verifying key given as the compressed serialization of each of its
+/// elements, e.g. as kept in separate slots of a hardware security module, without deserializing a
+/// whole [`VerifyingKey`]. Each element is checked to be on the curve and in the prime order
+/// subgroup, and must use all of its bytes; the first one that fails is reported as
+/// `Error::InvalidVerifyingKeyElement` with its name.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_proof_from_raw<E: Pairing>(
+    alpha_g1_bytes: &[u8],
+    beta_g2_bytes: &[u8],
+    gamma_g2_bytes: &[u8],
+    delta_g2_bytes: &[u8],
+    eta_gamma_inv_g1_bytes: &[u8],
+    gamma_abc_g1_bytes: &[&[u8]],
+    commit_witness_count: u32,
+    proof: &Proof<E>,
+    public_inputs: &[E::ScalarField],
+) -> crate::Result<bool> {
+    let vk = VerifyingKey::<E> {
+        alpha_g1: deserialize_vk_element(alpha_g1_bytes, || String::from("alpha_g1"))?,
+        beta_g2: deserialize_vk_element(beta_g2_bytes, || String::from("beta_g2"))?,
+        gamma_g2: deserialize_vk_element(gamma_g2_bytes, || String::from("gamma_g2"))?,
+        delta_g2: deserialize_vk_element(delta_g2_bytes, || String::from("delta_g2"))?,
+        eta_gamma_inv_g1: deserialize_vk_element(eta_gamma_inv_g1_bytes, || {
+            String::from("eta_gamma_inv_g1")
+        })?,
+        gamma_abc_g1: gamma_abc_g1_bytes
+            .iter()
+            .enumerate()
+            .map(|(i, bytes)| deserialize_vk_element(bytes, || format!("gamma_abc_g1[{}]", i)))
+            .collect::<crate::Result<Vec<_>>>()?,
+        commit_witness_count,
+    };
+    Ok(verify_proof(
+        &prepare_verifying_key(&vk),
+        proof,
+        public_inputs,
+    )?)
+}
+
+/// Deserialize and validate a verifying key element from all of `bytes`, naming it with `name` in
+/// the error.
+fn deserialize_vk_element<T: CanonicalDeserialize>(
+    mut bytes: &[u8],
+    name: impl FnOnce() -> String,
+) -> crate::Result<T> {
+    match T::deserialize_compressed(&mut bytes) {
+        Ok(element) if bytes.is_empty() => Ok(element),
+        _ => Err(Error::InvalidVerifyingKeyElement(name())),
+    }
+}
+
+/// Verify a LegoGroth16 proof whose committed witnesses `disclosed_committed` and commitment
+/// randomness `v` were disclosed, e.g. to an auditor. Checks both the proof with [`verify_proof`] and
+/// that `proof.d` opens to the disclosed values, using `pvk.vk`. Returns `Error::InvalidProof` if the
+/// proof doesn't verify and `Error::InvalidWitnessCommitment` if the disclosure is wrong.
+pub fn verify_proof_with_disclosed_commitment<E: Pairing>(
+    pvk: &PreparedVerifyingKey<E>,
+    proof: &Proof<E>,
+    public_inputs: &[E::ScalarField],
+    disclosed_committed: &[E::ScalarField],
+    v: &E::ScalarField,
+) -> crate::Result<()> {
+    if !verify_proof(pvk, proof, public_inputs)? {
+        return Err(Error::InvalidProof);
+    }
+    if !verify_d_only(&pvk.vk, proof, public_inputs.len(), disclosed_committed, v)? {
+        return Err(Error::InvalidWitnessCommitment);
+    }
+    Ok(())
+}
+
+/// Verify a LegoGroth16 proof like [`verify_proof`] with the public inputs given by name. `ordering`
+/// lists the names in the order the circuit declares its public inputs. Returns
+/// `Error::MissingPublicInput` if a name of `ordering` is not in `inputs` and
+/// `Error::UnexpectedPublicInput` if `inputs` has a name not in `ordering`.
+pub fn verify_proof_by_name<E: Pairing>(
+    pvk: &PreparedVerifyingKey<E>,
+    proof: &Proof<E>,
+    inputs: &BTreeMap<String, E::ScalarField>,
+    ordering: &[String],
+) -> crate::Result<bool> {
+    if let Some(extra) = inputs.keys().find(|name| !ordering.contains(name)) {
+        return Err(Error::UnexpectedPublicInput(extra.clone()));
+    }
+    let public_inputs = ordering
+        .iter()
+        .map(|name| {
+            inputs
+                .get(name)
+                .copied()
+                .ok_or_else(|| Error::MissingPublicInput(name.clone()))
+        })
+        .collect::<crate::Result<Vec<_>>>()?;
+    Ok(verify_proof(pvk, proof, &public_inputs)?)
+}
+
+/// Check a proof the way its prover can, knowing the committed witnesses and the randomness `v`:
+/// prepare `vk` and check both the proof and the opening of `proof.d` with
+/// [`verify_proof_with_disclosed_commitment`]. Returns `Ok(true)` if both pass,
+/// `Error::InvalidProof` if the proof doesn't verify and `Error::InvalidWitnessCommitment` if
+/// `proof.d` doesn't open to `committed_witnesses` and `v`.
+pub fn prover_self_verify<E: Pairing>(
+    vk: &VerifyingKey<E>,
+    proof: &Proof<E>,
+    public_inputs: &[E::ScalarField],
+    committed_witnesses: &[E::ScalarField],
+    v: &E::ScalarField,
+) -> crate::Result<bool> {
+    let pvk = prepare_verifying_key(vk);
+    verify_proof_with_disclosed_commitment(&pvk, proof, public_inputs, committed_witnesses, v)?;
+    Ok(true)
+}
+
+/// Same as [`verify_proof_checked`] but with a [`VerifyError`] that tells apart an invalid point in
+/// `proof`, a wrong number of public inputs and any other error. `Ok(false)` still means that the
+/// proof is well-formed but doesn't verify.
+pub fn verify_proof_strict<E: Pairing>(
+    pvk: &PreparedVerifyingKey<E>,
+    proof: &Proof<E>,
+    public_inputs: &[E::ScalarField],
+) -> Result<bool, VerifyError> {
+    let expected = pvk.vk.expected_public_input_count();
+    if public_inputs.len() != expected {
+        return Err(VerifyError::InputLengthMismatch {
+            expected,
+            got: public_inputs.len(),
+        });
+    }
+    verify_proof_checked(pvk, proof, public_inputs).map_err(|e| match e {
+        Error::InvalidProofPoint => VerifyError::InvalidPoint,
+        e => VerifyError::Internal(e),
+    })
+}
+
+/// Verify a LegoGroth16 proof like [`verify_proof`] but first check that all elements of `proof` are
+/// on the curve and in the prime order subgroup, returning `Error::InvalidProofPoint` if not. Use
+/// this for proofs that were deserialized without validation, e.g. with
+/// `deserialize_compressed_unchecked`.
+pub fn verify_proof_checked<E: Pairing>(
+    pvk: &PreparedVerifyingKey<E>,
+    proof: &Proof<E>,
+    public_inputs: &[E::ScalarField],
+) -> crate::Result<bool> {
+    if proof.a.check().is_err()
+        || proof.b.check().is_err()
+        || proof.c.check().is_err()
+        || proof.d.check().is_err()
+    {
+        return Err(Error::InvalidProofPoint);
+    }
+    Ok(verify_proof(pvk, proof, public_inputs)?)
+}
+
+/// Verify a proof `[a,b,c]` produced by vanilla Groth16 (like `ark_groth16`) against the prepared
+/// verification key `pvk`. LegoGroth16 reduces to Groth16 when there are no committed witnesses and
+/// `d` is the identity, so `vk.eta_gamma_inv_g1` is ignored and all of `vk.gamma_abc_g1` except the
+/// constant term is used for the public inputs.
+pub fn verify_vanilla_groth16_proof<E: Pairing>(
+    pvk: &PreparedVerifyingKey<E>,
+    a: E::G1Affine,
+    b: E::G2Affine,
+    c: E::G1Affine,
+    public_inputs: &[E::ScalarField],
+) -> R1CSResult<bool> {
+    if pvk.vk.commit_witness_count != 0 || public_inputs.len() + 1 != pvk.vk.gamma_abc_g1.len() {
+        return Err(SynthesisError::MalformedVerifyingKey);
+    }
+    let inputs =
+        prepare_inputs(pvk, public_inputs).map_err(|_| SynthesisError::MalformedVerifyingKey)?;
+    verify_groth16_proof(pvk, a, b, c, inputs.into_affine())
 }
 
 /// Verify a Groth16 proof [a,b,c,d] against the prepared verification key `pvk`
@@ -94,7 +630,6 @@ pub fn verify_groth16_proof<E: Pairing>(
     c: E::G1Affine,
     d: E::G1Affine,
 ) -> R1CSResult<bool> {
-
     let qap = E::multi_miller_loop(
         [a, c, d],
         [
@@ -109,6 +644,58 @@ pub fn verify_groth16_proof<E: Pairing>(
     Ok(test.0 == pvk.alpha_g1_beta_g2)
 }
 
+/// Same as [`verify_groth16_proof`] but with `b` already prepared, e.g. ahead of time when the same
+/// `b` is verified many times. This is not allocation-free: `multi_miller_loop` takes prepared
+/// elements by value and a `G2Prepared` keeps its line coefficients on the heap, so the prepared
+/// `delta` and `gamma` of `pvk` are cloned on each call.
+pub fn verify_groth16_proof_with_prepared_b<E: Pairing>(
+    pvk: &PreparedVerifyingKey<E>,
+    a: E::G1Affine,
+    b: E::G2Prepared,
+    c: E::G1Affine,
+    d: E::G1Affine,
+) -> R1CSResult<bool> {
+    let g1: [E::G1Prepared; 3] = [a.into(), c.into(), d.into()];
+    let g2: [E::G2Prepared; 3] = [b, pvk.delta_g2_neg_pc.clone(), pvk.gamma_g2_neg_pc.clone()];
+    let qap = E::multi_miller_loop(g1, g2);
+
+    let test = E::final_exponentiation(qap).ok_or(SynthesisError::UnexpectedIdentity)?;
+
+    Ok(test.0 == pvk.alpha_g1_beta_g2)
+}
+
+/// Same as [`verify_proof`] but with `a`, `b` and `c` prepared once with [`Proof::prepare`], and the
+/// commitment `d` given separately, for verifying the same prepared elements against several
+/// statements.
+pub fn verify_prepared_proof<E: Pairing>(
+    pvk: &PreparedVerifyingKey<E>,
+    prepared: &PreparedProof<E>,
+    d: &E::G1Affine,
+    public_inputs: &[E::ScalarField],
+) -> R1CSResult<bool> {
+    if public_inputs.len() != pvk.vk.expected_public_input_count() {
+        return Err(SynthesisError::MalformedVerifyingKey);
+    }
+    let d =
+        prepare_inputs(pvk, public_inputs).map_err(|_| SynthesisError::MalformedVerifyingKey)? + d;
+    let qap = E::multi_miller_loop(
+        [
+            prepared.a.clone(),
+            prepared.c.clone(),
+            d.into_affine().into(),
+        ],
+        [
+            prepared.b.clone(),
+            pvk.delta_g2_neg_pc.clone(),
+            pvk.gamma_g2_neg_pc.clone(),
+        ],
+    );
+
+    let test = E::final_exponentiation(qap).ok_or(SynthesisError::UnexpectedIdentity)?;
+
+    Ok(test.0 == pvk.alpha_g1_beta_g2)
+}
+
 pub fn calculate_d<E: Pairing>(
     pvk: &PreparedVerifyingKey<E>,
     proof: &Proof<E>,
@@ -120,7 +707,7 @@ pub fn calculate_d<E: Pairing>(
 }
 
 // this function checks that the commitments in the proof open to the witnesses
-// but with different bases and randomness. 
+// but with different bases and randomness.
 // This function should only be called by the prover, the verifier does not
 // know `witnesses_expected_in_commitment` or `link_v`.
 
@@ -131,7 +718,7 @@ pub fn verify_commitments<E: Pairing>(
     witnesses_expected_in_commitment: &[E::ScalarField],
     v: &E::ScalarField,
     link_v: &E::ScalarField,
-) -> Result<bool, SynthesisError>{
+) -> crate::Result<bool> {
     verify_link_commitment::<E>(
         &vk.link_bases,
         &proof.link_d,
@@ -153,7 +740,7 @@ pub fn verify_link_commitment<E: Pairing>(
     link_d: &E::G1Affine,
     witnesses_expected_in_commitment: &[E::ScalarField],
     link_v: &E::ScalarField,
-) -> Result<bool, SynthesisError>{
+) -> Result<bool, SynthesisError> {
     // Some witnesses are committed in `link_d` with randomness `link_v`
     if (witnesses_expected_in_commitment.len() + 1) > cp_link_bases.len() {
         return Err(SynthesisError::MalformedVerifyingKey);
@@ -169,6 +756,336 @@ pub fn verify_link_commitment<E: Pairing>(
     Ok(true)
 }
 
+/// Opening `(link_d, witnesses, link_v)` of a cp_link commitment.
+pub type LinkOpening<'a, E> = (
+    <E as Pairing>::G1Affine,
+    &'a [<E as Pairing>::ScalarField],
+    <E as Pairing>::ScalarField,
+);
+
+/// A proof with the public inputs it should be verified against.
+pub type ProofWithInputs<'a, E> = (&'a Proof<E>, &'a [<E as Pairing>::ScalarField]);
+
+/// Verify several proofs under the same key at once. The verification equations are combined with
+/// random coefficients from `rng` so only `items.len() + 2` Miller loops and one final
+/// exponentiation are done. Returns `Ok(false)` if any proof is invalid.
+///
+/// With `dedup`, a proof that appears more than once in `items` is rejected with
+/// `Error::DuplicateProof(index)` of its second occurrence. This is meant for callers that count
+/// verified proofs, e.g. votes or payments, where replaying a valid proof would inflate the count.
+/// Only identical proofs are detected: a Groth16 proof can be re-randomized into a different valid
+/// proof for the same statement, so a count of distinct statements needs checks on the inputs too.
+pub fn verify_proofs_batch<E: Pairing, R: Rng>(
+    pvk: &PreparedVerifyingKey<E>,
+    items: &[ProofWithInputs<'_, E>],
+    dedup: bool,
+    rng: &mut R,
+) -> crate::Result<bool> {
+    if dedup {
+        // `Proof` is `Hash` but a `HashSet` needs `std`, so the serialized proofs are compared
+        let mut seen = BTreeSet::new();
+        for (i, (proof, _)) in items.iter().enumerate() {
+            let mut bytes = Vec::with_capacity(proof.compressed_size());
+            proof
+                .serialize_compressed(&mut bytes)
+                .map_err(|_| Error::InvalidProofPoint)?;
+            if !seen.insert(bytes) {
+                return Err(Error::DuplicateProof(i));
+            }
+        }
+    }
+
+    let expected_inputs = pvk.vk.expected_public_input_count();
+    let mut g1 = Vec::with_capacity(items.len() + 2);
+    let mut g2 = Vec::with_capacity(items.len() + 2);
+    let mut c_acc = E::G1::zero();
+    let mut d_acc = E::G1::zero();
+    let mut r_sum = E::ScalarField::zero();
+    for (proof, public_inputs) in items {
+        if public_inputs.len() != expected_inputs {
+            return Err(SynthesisError::MalformedVerifyingKey.into());
+        }
+        let r = E::ScalarField::rand(rng);
+        let d = prepare_inputs(pvk, public_inputs)? + proof.d;
+        g1.push(proof.a.mul_bigint(r.into_bigint()));
+        g2.push(E::G2Prepared::from(proof.b));
+        c_acc += proof.c.mul_bigint(r.into_bigint());
+        d_acc += d * r;
+        r_sum += r;
+    }
+    g1.push(c_acc);
+    g2.push(pvk.delta_g2_neg_pc.clone());
+    g1.push(d_acc);
+    g2.push(pvk.gamma_g2_neg_pc.clone());
+
+    let qap = E::multi_miller_loop(E::G1::normalize_batch(&g1), g2);
+    let test = E::final_exponentiation(qap).ok_or(SynthesisError::UnexpectedIdentity)?;
+
+    Ok(test.0 == pvk.alpha_g1_beta_g2.pow(r_sum.into_bigint()))
+}
+
+/// Verify `proof` against either of two verifying keys, e.g. of two circuits a statement can be
+/// proven with, returning `Some(0)` if it verifies under `pvk1`, else `Some(1)` if it does under
+/// `pvk2` and `None` otherwise. The inputs are prepared for each key, and a key expecting another
+/// number of public inputs does not match.
+pub fn verify_against_either<E: Pairing>(
+    pvk1: &PreparedVerifyingKey<E>,
+    pvk2: &PreparedVerifyingKey<E>,
+    proof: &Proof<E>,
+    public_inputs: &[E::ScalarField],
+) -> Option<usize> {
+    [pvk1, pvk2]
+        .iter()
+        .position(|pvk| verify_proof(pvk, proof, public_inputs) == Ok(true))
+}
+
+/// Check that all of `pairs`, each a proof with its public inputs, verify under `pvk`, one after the
+/// other and stopping at the first that doesn't, e.g. a proof with the wrong number of inputs. Unlike
+/// [`verify_proofs_batch`], which always processes every proof, an invalid list costs only the
+/// proofs up to the first invalid one. Returns `true` for an empty list.
+pub fn verify_all<E: Pairing>(
+    pvk: &PreparedVerifyingKey<E>,
+    pairs: &[(Proof<E>, Vec<E::ScalarField>)],
+) -> bool {
+    pairs
+        .iter()
+        .all(|(proof, public_inputs)| verify_proof(pvk, proof, public_inputs) == Ok(true))
+}
+
+/// Verify several proofs of the same statement, e.g. by different provers, returning whether each of
+/// them is valid. The public inputs are prepared once for all proofs, which are first checked
+/// together with random coefficients from `rng` as in [`verify_proofs_batch`]; only if that fails
+/// are they checked one by one to find the invalid ones.
+pub fn verify_proofs_same_statement<E: Pairing, R: Rng>(
+    pvk: &PreparedVerifyingKey<E>,
+    proofs: &[Proof<E>],
+    public_inputs: &[E::ScalarField],
+    rng: &mut R,
+) -> crate::Result<Vec<bool>> {
+    if public_inputs.len() != pvk.vk.expected_public_input_count() {
+        return Err(SynthesisError::MalformedVerifyingKey.into());
+    }
+    if proofs.is_empty() {
+        return Ok(Vec::new());
+    }
+    let prepared_inputs = prepare_inputs(pvk, public_inputs)?;
+
+    let mut g1 = Vec::with_capacity(proofs.len() + 2);
+    let mut g2 = Vec::with_capacity(proofs.len() + 2);
+    let mut c_acc = E::G1::zero();
+    let mut d_acc = E::G1::zero();
+    let mut r_sum = E::ScalarField::zero();
+    for proof in proofs {
+        let r = E::ScalarField::rand(rng);
+        g1.push(proof.a.mul_bigint(r.into_bigint()));
+        g2.push(E::G2Prepared::from(proof.b));
+        c_acc += proof.c.mul_bigint(r.into_bigint());
+        d_acc += proof.d.mul_bigint(r.into_bigint());
+        r_sum += r;
+    }
+    // The prepared inputs are the same in every equation so are only multiplied once
+    g1.push(c_acc);
+    g2.push(pvk.delta_g2_neg_pc.clone());
+    g1.push(d_acc + prepared_inputs * r_sum);
+    g2.push(pvk.gamma_g2_neg_pc.clone());
+
+    let qap = E::multi_miller_loop(E::G1::normalize_batch(&g1), g2);
+    let test = E::final_exponentiation(qap).ok_or(SynthesisError::UnexpectedIdentity)?;
+    if test.0 == pvk.alpha_g1_beta_g2.pow(r_sum.into_bigint()) {
+        return Ok(vec![true; proofs.len()]);
+    }
+
+    proofs
+        .iter()
+        .map(|proof| {
+            verify_proof_with_prepared_inputs(pvk, proof, &prepared_inputs).map_err(Error::from)
+        })
+        .collect()
+}
+
+/// Check the openings of several cp_link commitments `(link_d, witnesses, link_v)` under the same
+/// commitment key at once. The openings are combined with random coefficients from `rng` so only 2
+/// MSMs are done in total. Returns `Ok(false)` if any opening is wrong, use
+/// [`verify_link_commitments_individually`] to find which one.
+pub fn verify_link_commitments_batch<E: Pairing, R: Rng>(
+    cp_link_bases: &[E::G1Affine],
+    items: &[LinkOpening<'_, E>],
+    rng: &mut R,
+) -> Result<bool, SynthesisError> {
+    let mut combined = vec![E::ScalarField::zero(); cp_link_bases.len()];
+    let mut link_ds = Vec::with_capacity(items.len());
+    let mut randomizers = Vec::with_capacity(items.len());
+    for (link_d, witnesses, link_v) in items {
+        if (witnesses.len() + 1) > cp_link_bases.len() {
+            return Err(SynthesisError::MalformedVerifyingKey);
+        }
+        let r = E::ScalarField::rand(rng);
+        for (c, w) in combined.iter_mut().zip(witnesses.iter()) {
+            *c += r * w;
+        }
+        combined[witnesses.len()] += r * link_v;
+        link_ds.push(*link_d);
+        randomizers.push(r);
+    }
+    let lhs = E::G1::msm_unchecked(&link_ds, &randomizers);
+    let rhs = E::G1::msm_unchecked(cp_link_bases, &combined);
+    Ok(lhs == rhs)
+}
+
+/// Check each of the openings of cp_link commitments `(link_d, witnesses, link_v)` separately with
+/// [`verify_link_commitment`] and return whether each one is correct.
+pub fn verify_link_commitments_individually<E: Pairing>(
+    cp_link_bases: &[E::G1Affine],
+    items: &[LinkOpening<'_, E>],
+) -> Vec<bool> {
+    cfg_iter!(items)
+        .map(|(link_d, witnesses, link_v)| {
+            verify_link_commitment::<E>(cp_link_bases, link_d, witnesses, link_v).is_ok()
+        })
+        .collect()
+}
+
+impl<E: Pairing> DeltaUpdateProof<E> {
+    /// Check that `new_pk` was obtained from `old_pk` with [`ProvingKey::update_delta`] and that this
+    /// is the proof of that update. Only `delta` dependent elements may differ between the keys. The
+    /// rescaling of `h_query`, `l_query` and `eta_delta_inv_g1` is checked with a random linear
+    /// combination using `rng`. `D` must be the hash function given to [`ProvingKey::update_delta`].
+    pub fn verify<D: Digest, R: Rng>(
+        &self,
+        old_pk: &ProvingKey<E>,
+        new_pk: &ProvingKey<E>,
+        rng: &mut R,
+    ) -> bool {
+        let (old, new) = (&old_pk.common, &new_pk.common);
+        if self.s_g1.is_zero()
+            || new.delta_g1.is_zero()
+            || old.beta_g1 != new.beta_g1
+            || old.a_query != new.a_query
+            || old.b_g1_query != new.b_g1_query
+            || old.b_g2_query != new.b_g2_query
+            || old.h_query.len() != new.h_query.len()
+            || old.l_query.len() != new.l_query.len()
+            || old_pk.vk.alpha_g1 != new_pk.vk.alpha_g1
+            || old_pk.vk.beta_g2 != new_pk.vk.beta_g2
+            || old_pk.vk.gamma_g2 != new_pk.vk.gamma_g2
+            || old_pk.vk.gamma_abc_g1 != new_pk.vk.gamma_abc_g1
+            || old_pk.vk.eta_gamma_inv_g1 != new_pk.vk.eta_gamma_inv_g1
+            || old_pk.vk.commit_witness_count != new_pk.vk.commit_witness_count
+        {
+            return false;
+        }
+
+        // The contributor knows the ratio between `s_g1` and `s_delta_ratio_g1` as it multiplied
+        // `r_g2` by it, which is only determined by hashing them
+        let r_g2 =
+            match delta_update_r_g2::<E, D>(old_pk, new_pk, &self.s_g1, &self.s_delta_ratio_g1) {
+                Ok(r_g2) => r_g2,
+                Err(_) => return false,
+            };
+        if E::pairing(self.s_g1, self.r_delta_ratio_g2) != E::pairing(self.s_delta_ratio_g1, r_g2) {
+            return false;
+        }
+        // `delta` was multiplied by that ratio
+        if E::pairing(old.delta_g1, self.r_delta_ratio_g2) != E::pairing(new.delta_g1, r_g2)
+            || E::pairing(new.delta_g1, old_pk.vk.delta_g2)
+                != E::pairing(old.delta_g1, new_pk.vk.delta_g2)
+        {
+            return false;
+        }
+
+        // `h_query`, `l_query` and `eta_delta_inv_g1` were divided by that ratio
+        let mut old_elems = old.h_query.clone();
+        old_elems.extend_from_slice(&old.l_query);
+        old_elems.push(old.eta_delta_inv_g1);
+        let mut new_elems = new.h_query.clone();
+        new_elems.extend_from_slice(&new.l_query);
+        new_elems.push(new.eta_delta_inv_g1);
+        let randomizers = (0..old_elems.len())
+            .map(|_| E::ScalarField::rand(rng))
+            .collect::<Vec<_>>();
+        let old_comb = E::G1::msm_unchecked(&old_elems, &randomizers);
+        let new_comb = E::G1::msm_unchecked(&new_elems, &randomizers);
+        E::pairing(old_comb, old_pk.vk.delta_g2) == E::pairing(new_comb, new_pk.vk.delta_g2)
+    }
+}
+
+impl<'a, E: Pairing> UpdatableInputs<'a, E> {
+    /// Prepare `public_inputs` for `pvk` with [`prepare_inputs`]. `public_inputs` must have the
+    /// number of public inputs of `pvk.vk`.
+    pub fn new(
+        pvk: &'a PreparedVerifyingKey<E>,
+        public_inputs: &[E::ScalarField],
+    ) -> crate::Result<Self> {
+        let count = pvk.vk.expected_public_input_count();
+        if public_inputs.len() != count {
+            return Err(SynthesisError::MalformedVerifyingKey.into());
+        }
+        Ok(Self {
+            input_bases: &pvk.vk.gamma_abc_g1[1..1 + count],
+            acc: prepare_inputs(pvk, public_inputs)?,
+        })
+    }
+
+    /// Change the public input at `index` from `old_value` to `new_value` with a single scalar
+    /// multiplication. `old_value` must be the current value of the input, which isn't checked.
+    /// Returns `Error::PublicInputIndexOutOfRange` if there is no input at `index`.
+    pub fn set(
+        &mut self,
+        index: usize,
+        old_value: &E::ScalarField,
+        new_value: &E::ScalarField,
+    ) -> crate::Result<()> {
+        let base = self
+            .input_bases
+            .get(index)
+            .ok_or(Error::PublicInputIndexOutOfRange(
+                index,
+                self.input_bases.len(),
+            ))?;
+        self.acc += *base * (*new_value - old_value);
+        Ok(())
+    }
+}
+
+impl<E: Pairing> LinkCommitmentVerifier<E> {
+    /// Window size of the tables, sized for checking many openings.
+    pub const WINDOW: usize = 6;
+
+    /// Build the window tables for the commitment key `cp_link_bases`.
+    pub fn new(cp_link_bases: &[E::G1Affine]) -> Self {
+        let scalar_bits = E::ScalarField::MODULUS_BIT_SIZE as usize;
+        let tables = cfg_iter!(cp_link_bases)
+            .map(|b| {
+                FixedBase::get_window_table::<E::G1>(scalar_bits, Self::WINDOW, b.into_group())
+            })
+            .collect::<Vec<_>>();
+        Self {
+            window: Self::WINDOW,
+            tables,
+        }
+    }
+
+    /// Check the opening of cp_link like [`verify_link_commitment`] but using the window tables.
+    pub fn open_eq(
+        &self,
+        witnesses_expected_in_commitment: &[E::ScalarField],
+        link_v: &E::ScalarField,
+        link_d: &E::G1Affine,
+    ) -> Result<bool, SynthesisError> {
+        if (witnesses_expected_in_commitment.len() + 1) > self.tables.len() {
+            return Err(SynthesisError::MalformedVerifyingKey);
+        }
+        let mut committed = witnesses_expected_in_commitment.to_vec();
+        committed.push(*link_v);
+        let d = windowed_commitment::<E>(self.window, &self.tables, &committed);
+
+        if *link_d != d.into_affine() {
+            return Err(SynthesisError::MalformedVerifyingKey);
+        }
+        Ok(true)
+    }
+}
+
 /// Given the proof, verify that the commitment in it (`proof.d`) commits to the witness.
 pub fn verify_witness_commitment<E: Pairing>(
     vk: &VerifyingKey<E>,
@@ -176,25 +1093,162 @@ pub fn verify_witness_commitment<E: Pairing>(
     public_inputs_count: usize,
     witnesses_expected_in_commitment: &[E::ScalarField],
     v: &E::ScalarField,
-) -> Result<bool, SynthesisError> {
-    // Some witnesses are also committed in `proof.d` with randomness `v`
-    if (public_inputs_count + witnesses_expected_in_commitment.len() + 1) > vk.gamma_abc_g1.len() {
-        return Err(SynthesisError::MalformedVerifyingKey);
-    }
-    let committed = cfg_iter!(witnesses_expected_in_commitment)
-        .map(|p| p.into_bigint())
-        .collect::<Vec<_>>();
+) -> crate::Result<bool> {
+    verify_witness_commitment_with_eta(
+        vk,
+        proof,
+        public_inputs_count,
+        witnesses_expected_in_commitment,
+        v,
+        &vk.eta_gamma_inv_g1,
+    )
+}
 
+/// Same as [`verify_witness_commitment`] but the randomness `v` is expected to be committed with
+/// the base `eta_gamma_inv_g1` instead of `vk.eta_gamma_inv_g1`. This allows checking proofs created
+/// under a verifying key with a different `eta` but otherwise the same parameters, e.g. while
+/// migrating after rotating `eta`.
+pub fn verify_witness_commitment_with_eta<E: Pairing>(
+    vk: &VerifyingKey<E>,
+    proof: &Proof<E>,
+    public_inputs_count: usize,
+    witnesses_expected_in_commitment: &[E::ScalarField],
+    v: &E::ScalarField,
+    eta_gamma_inv_g1: &E::G1Affine,
+) -> crate::Result<bool> {
     // Check that proof.d is correctly constructed.
-    let mut d = E::G1::msm_bigint(
-        &vk.gamma_abc_g1[1 + public_inputs_count..1 + public_inputs_count + committed.len()],
-        &committed,
-    );
-    d.add_assign(&vk.eta_gamma_inv_g1.mul_bigint(v.into_bigint()));
+    let d = create_d_projective_with_eta(
+        vk,
+        public_inputs_count,
+        witnesses_expected_in_commitment,
+        v,
+        eta_gamma_inv_g1,
+    )?;
 
     if proof.d != d.into_affine() {
-        return Err(SynthesisError::MalformedVerifyingKey);
+        return Err(SynthesisError::MalformedVerifyingKey.into());
     }
 
     Ok(true)
-}
\ No newline at end of file
+}
+
+/// Verify a [`SharedCommitmentProof`] created by
+/// [`create_shared_commitment_proof`](crate::create_shared_commitment_proof), i.e. that the
+/// commitments `d` of `proof1` and `proof2`, for `vk` and with `public_inputs_count` public inputs,
+/// commit to the same witnesses at the positions `shared_indices`. `D` must be the hash function the
+/// proof was created with. The proofs themselves are not verified.
+pub fn verify_shared_commitment<E: Pairing, D: Digest>(
+    vk: &VerifyingKey<E>,
+    public_inputs_count: usize,
+    shared_indices: &[usize],
+    proof1: &Proof<E>,
+    proof2: &Proof<E>,
+    shared_proof: &SharedCommitmentProof<E>,
+) -> crate::Result<bool> {
+    let (bases, _) = shared_commitment_bases(vk, public_inputs_count, shared_indices)?;
+    if shared_proof.responses.len() != bases.len() {
+        return Ok(false);
+    }
+    let challenge = shared_commitment_challenge::<E, D>(
+        public_inputs_count,
+        shared_indices,
+        &bases,
+        &proof1.d,
+        &proof2.d,
+        &shared_proof.t,
+    );
+    let lhs = E::G1::msm_unchecked(&bases, &shared_proof.responses);
+    let rhs = (proof1.d.into_group() - proof2.d) * challenge + shared_proof.t;
+    Ok(lhs == rhs)
+}
+
+/// Verify a [`CommitmentEqualityProof`] created by
+/// [`prove_commitment_equality`](crate::prove_commitment_equality), i.e. that the cp_link
+/// commitments `link_d1` and `link_d2` commit to the same witnesses under `bases`, the last of which
+/// is the base of the randomness. `D` must be the hash function the proof was created with. Returns
+/// `Error::EmptyPedersenBases` if `bases` is empty.
+pub fn verify_commitment_equality<E: Pairing, D: Digest>(
+    link_d1: &E::G1Affine,
+    link_d2: &E::G1Affine,
+    bases: &[E::G1Affine],
+    eq_proof: &CommitmentEqualityProof<E>,
+) -> crate::Result<bool> {
+    let blinding_base = bases.last().ok_or(Error::EmptyPedersenBases)?;
+    let challenge =
+        commitment_equality_challenge::<E, D>(blinding_base, link_d1, link_d2, &eq_proof.t);
+    let lhs = blinding_base.mul_bigint(eq_proof.response.into_bigint());
+    let rhs = (link_d1.into_group() - link_d2) * challenge + eq_proof.t;
+    Ok(lhs == rhs)
+}
+
+/// Verify a [`PositionOpening`] created by [`open_position`](crate::open_position), i.e. that the
+/// commitment `proof_d`, for `vk` and with `public_inputs_count` public inputs, commits to
+/// `claimed_value` at `position` of the committed witnesses. `D` must be the hash function the
+/// opening was created with.
+pub fn verify_position_opening<E: Pairing, D: Digest>(
+    vk: &VerifyingKey<E>,
+    public_inputs_count: usize,
+    proof_d: &E::G1Affine,
+    position: usize,
+    claimed_value: &E::ScalarField,
+    opening: &PositionOpening<E>,
+) -> crate::Result<bool> {
+    let (bases, _) = shared_commitment_bases(vk, public_inputs_count, &[position])?;
+    if opening.responses.len() != bases.len() {
+        return Ok(false);
+    }
+    let (committed, _) = vk.commitment_bases(public_inputs_count, position + 1)?;
+    let challenge = position_opening_challenge::<E, D>(
+        public_inputs_count,
+        position,
+        &committed[position],
+        &bases,
+        claimed_value,
+        proof_d,
+        &opening.t,
+    );
+    let lhs = E::G1::msm_unchecked(&bases, &opening.responses);
+    let rhs = (proof_d.into_group() - committed[position] * claimed_value) * challenge + opening.t;
+    Ok(lhs == rhs)
+}
+
+/// Check only that `proof.d` opens to `committed_witnesses` with randomness `v`, e.g. when the
+/// Groth16 part of `proof` was already verified. Unlike [`verify_witness_commitment`], a wrong
+/// opening gives `Ok(false)`; errors are only for inputs that don't fit `vk`.
+pub fn verify_d_only<E: Pairing>(
+    vk: &VerifyingKey<E>,
+    proof: &Proof<E>,
+    public_inputs_count: usize,
+    committed_witnesses: &[E::ScalarField],
+    v: &E::ScalarField,
+) -> crate::Result<bool> {
+    let d = expected_proof_d(vk, public_inputs_count, committed_witnesses, v)?;
+    Ok(proof.d == d)
+}
+
+/// Check whether `candidate_v` is the randomness of the commitment `proof_d` to
+/// `committed_witnesses`, e.g. when debugging a commitment mismatch with the witnesses known but not
+/// `v`. The randomness can't be computed from `proof_d`, as that is a discrete logarithm, so only a
+/// guess can be confirmed. Returns `false` as well if the witnesses don't fit `vk`.
+pub fn check_v<E: Pairing>(
+    vk: &VerifyingKey<E>,
+    proof_d: &E::G1Affine,
+    public_inputs_count: usize,
+    committed_witnesses: &[E::ScalarField],
+    candidate_v: &E::ScalarField,
+) -> bool {
+    expected_proof_d(vk, public_inputs_count, committed_witnesses, candidate_v)
+        .is_ok_and(|d| d == *proof_d)
+}
+
+/// Compute the commitment `proof.d` a proof should have when it commits to `committed_witnesses`
+/// with randomness `v`. Returns `Error::PublicInputCountTooLarge` if `public_inputs_count` leaves no
+/// room in `vk.gamma_abc_g1` for the committed block.
+pub fn expected_proof_d<E: Pairing>(
+    vk: &VerifyingKey<E>,
+    public_inputs_count: usize,
+    committed_witnesses: &[E::ScalarField],
+    v: &E::ScalarField,
+) -> crate::Result<E::G1Affine> {
+    Ok(create_d_projective(vk, public_inputs_count, committed_witnesses, v)?.into_affine())
+}
@@ -0,0 +1,348 @@
+//! Import of Groth16 proving keys in the `.zkey` format of snarkjs, so that circuits compiled with
+//! Circom can be proven with this crate.
+//!
+//! A zkey is a plain Groth16 key: it has no `eta` elements and no witnesses committed in `D`. The
+//! imported key is in plain mode, with `eta_gamma_inv_g1` and `eta_delta_inv_g1` the identity and a
+//! `commit_witness_count` of 0, so all witnesses go with `l_query` and `D` is the identity.
+//!
+//! snarkjs also computes the quotient polynomial differently from
+//! [`R1CStoQAP`](crate::r1cs_to_qap::R1CStoQAP): its `h_query` is for the evaluations of `h` on
+//! the odd powers of the root of unity of a domain twice as large.
+//! [`ZkeyConstraints::witness_map`](crate::zkey::ZkeyConstraints::witness_map) computes these from
+//! the constraints stored in the zkey, to be given to
+//! [`create_proof_from_assignment`](crate::create_proof_from_assignment).
+
+use crate::{error::Error, ProvingKey, ProvingKeyCommon, VerifyingKey};
+use ark_bn254::{Bn254, Fq, Fq2, Fr, G1Affine, G2Affine};
+use ark_ec::AffineRepr;
+use ark_ff::{BigInt, Fp256, MontBackend, MontConfig, PrimeField, Zero};
+use ark_poly::{EvaluationDomain, Radix2EvaluationDomain};
+use ark_relations::r1cs::SynthesisError;
+use ark_std::{collections::BTreeMap, io::Read, vec, vec::Vec};
+
+/// Id of the sections of a zkey used by the importer.
+const HEADER: u32 = 1;
+const GROTH16_HEADER: u32 = 2;
+const IC: u32 = 3;
+const COEFFS: u32 = 4;
+const POINTS_A: u32 = 5;
+const POINTS_B1: u32 = 6;
+const POINTS_B2: u32 = 7;
+const POINTS_C: u32 = 8;
+const POINTS_H: u32 = 9;
+
+/// Id of the Groth16 protocol in the header section.
+const GROTH16_PROTOCOL: u32 = 1;
+
+/// Size in bytes of the elements of `Fq` and `Fr`.
+const N8: usize = 32;
+
+/// Largest evaluation domain accepted, that of the largest powers of tau file of snarkjs.
+const MAX_DOMAIN_SIZE: usize = 1 << 28;
+
+/// The constraints stored in a zkey along the key, as needed to compute the quotient polynomial the
+/// way snarkjs does.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ZkeyConstraints {
+    /// Number of variables including the constant `1`, which comes first, then the public inputs.
+    pub num_variables: usize,
+    /// Number of public inputs, excluding the constant `1`.
+    pub num_public_inputs: usize,
+    /// Size of the evaluation domain, the length of `h_query`.
+    pub domain_size: usize,
+    /// Rows of the `A` matrix as `(coefficient, variable)`, one for each element of the domain.
+    pub a: Vec<Vec<(Fr, usize)>>,
+    /// Rows of the `B` matrix as `(coefficient, variable)`, one for each element of the domain.
+    pub b: Vec<Vec<(Fr, usize)>>,
+}
+
+impl ZkeyConstraints {
+    /// The evaluations of the quotient polynomial `h` on the odd powers of the root of unity of a
+    /// domain twice as large as `domain_size`, which is what the `h_query` of a zkey is for.
+    /// `full_assignment` has the constant `1`, the public inputs and then the witnesses, in the
+    /// order of the Circom signals, and must satisfy the constraints for the proof to verify.
+    /// Returns `SynthesisError::MalformedVerifyingKey` if it doesn't have `num_variables` values.
+    pub fn witness_map(&self, full_assignment: &[Fr]) -> crate::Result<Vec<Fr>> {
+        if full_assignment.len() != self.num_variables {
+            return Err(SynthesisError::MalformedVerifyingKey.into());
+        }
+        let domain = Radix2EvaluationDomain::<Fr>::new(self.domain_size)
+            .ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+        let double_domain = Radix2EvaluationDomain::<Fr>::new(2 * self.domain_size)
+            .ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+        let odd_coset = domain
+            .get_coset(double_domain.element(1))
+            .ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+
+        let evaluate = |row: &[(Fr, usize)]| {
+            row.iter()
+                .map(|(coeff, index)| *coeff * full_assignment[*index])
+                .sum::<Fr>()
+        };
+        let mut a = self.a.iter().map(|row| evaluate(row)).collect::<Vec<_>>();
+        let mut b = self.b.iter().map(|row| evaluate(row)).collect::<Vec<_>>();
+        // The assignment satisfies the constraints so `C` evaluates to `A * B` on the domain
+        let mut c = a.iter().zip(&b).map(|(a, b)| *a * b).collect::<Vec<_>>();
+
+        for evals in [&mut a, &mut b, &mut c] {
+            domain.ifft_in_place(evals);
+            odd_coset.fft_in_place(evals);
+        }
+
+        Ok(a.into_iter()
+            .zip(b)
+            .zip(c)
+            .map(|((a, b), c)| a * b - c)
+            .collect())
+    }
+}
+
+impl ProvingKey<Bn254> {
+    /// Read the Groth16 proving key of a zkey written by snarkjs, in plain mode as described in the
+    /// [module documentation](crate::zkey). Use [`read_zkey`] to also get the constraints needed to
+    /// prove with it. Returns `Error::InvalidZkey` if the zkey is malformed, for another curve or
+    /// protocol, or has points not in the prime order subgroup.
+    pub fn from_zkey_reader<R: Read>(reader: R) -> crate::Result<Self> {
+        read_zkey(reader).map(|(pk, _)| pk)
+    }
+}
+
+/// Read the Groth16 proving key of a zkey written by snarkjs together with its constraints. See
+/// [`ProvingKey::from_zkey_reader`].
+pub fn read_zkey<R: Read>(mut reader: R) -> crate::Result<(ProvingKey<Bn254>, ZkeyConstraints)> {
+    let mut sections = read_sections(&mut reader)?;
+    let mut section = |id: u32| {
+        sections
+            .remove(&id)
+            .ok_or(Error::InvalidZkey("missing section"))
+    };
+
+    let header = section(HEADER)?;
+    let mut bytes = header.as_slice();
+    if read_u32(&mut bytes)? != GROTH16_PROTOCOL {
+        return Err(Error::InvalidZkey("not a Groth16 key"));
+    }
+    end_of_section(bytes)?;
+
+    let header = section(GROTH16_HEADER)?;
+    let mut bytes = header.as_slice();
+    read_modulus::<Fq>(&mut bytes)?;
+    read_modulus::<Fr>(&mut bytes)?;
+    let num_variables = read_u32(&mut bytes)? as usize;
+    let num_public_inputs = read_u32(&mut bytes)? as usize;
+    let domain_size = read_u32(&mut bytes)? as usize;
+    if num_public_inputs >= num_variables
+        || !domain_size.is_power_of_two()
+        || domain_size > MAX_DOMAIN_SIZE
+    {
+        return Err(Error::InvalidZkey("invalid sizes in header"));
+    }
+    let alpha_g1 = read_g1(&mut bytes)?;
+    let beta_g1 = read_g1(&mut bytes)?;
+    let beta_g2 = read_g2(&mut bytes)?;
+    let gamma_g2 = read_g2(&mut bytes)?;
+    let delta_g1 = read_g1(&mut bytes)?;
+    let delta_g2 = read_g2(&mut bytes)?;
+    end_of_section(bytes)?;
+
+    let gamma_abc_g1 = read_g1_section(&section(IC)?, num_public_inputs + 1)?;
+    let a_query = read_g1_section(&section(POINTS_A)?, num_variables)?;
+    let b_g1_query = read_g1_section(&section(POINTS_B1)?, num_variables)?;
+    let b_g2_query = read_g2_section(&section(POINTS_B2)?, num_variables)?;
+    let l_query = read_g1_section(&section(POINTS_C)?, num_variables - num_public_inputs - 1)?;
+    let h_query = read_g1_section(&section(POINTS_H)?, domain_size)?;
+
+    let coeffs = section(COEFFS)?;
+    let mut bytes = coeffs.as_slice();
+    let mut matrices = [vec![vec![]; domain_size], vec![vec![]; domain_size]];
+    for _ in 0..read_u32(&mut bytes)? {
+        let matrix = read_u32(&mut bytes)? as usize;
+        let constraint = read_u32(&mut bytes)? as usize;
+        let variable = read_u32(&mut bytes)? as usize;
+        // snarkjs writes the coefficients multiplied by `R^2`, i.e. in Montgomery form twice
+        let coeff: Fr = read_montgomery(&mut bytes)?;
+        let coeff = Fr::new_unchecked(coeff.into_bigint());
+        if matrix > 1 || constraint >= domain_size || variable >= num_variables {
+            return Err(Error::InvalidZkey("coefficient out of range"));
+        }
+        matrices[matrix][constraint].push((coeff, variable));
+    }
+    end_of_section(bytes)?;
+    let [a, b] = matrices;
+
+    let vk = VerifyingKey {
+        alpha_g1,
+        beta_g2,
+        gamma_g2,
+        delta_g2,
+        gamma_abc_g1,
+        eta_gamma_inv_g1: G1Affine::zero(),
+        commit_witness_count: 0,
+    };
+    let common = ProvingKeyCommon {
+        beta_g1,
+        delta_g1,
+        eta_delta_inv_g1: G1Affine::zero(),
+        a_query,
+        b_g1_query,
+        b_g2_query,
+        h_query,
+        l_query,
+    };
+    let constraints = ZkeyConstraints {
+        num_variables,
+        num_public_inputs,
+        domain_size,
+        a,
+        b,
+    };
+    Ok((ProvingKey { vk, common }, constraints))
+}
+
+/// Read the file header and the sections, by id. Sections can be in any order so all are read
+/// before being parsed.
+fn read_sections<R: Read>(reader: &mut R) -> crate::Result<BTreeMap<u32, Vec<u8>>> {
+    let mut magic = [0u8; 4];
+    read_exact(reader, &mut magic)?;
+    if &magic != b"zkey" {
+        return Err(Error::InvalidZkey("not a zkey"));
+    }
+    let mut word = [0u8; 4];
+    // The version, of which there is only one
+    read_exact(reader, &mut word)?;
+    read_exact(reader, &mut word)?;
+    let num_sections = u32::from_le_bytes(word);
+
+    let mut sections = BTreeMap::new();
+    for _ in 0..num_sections {
+        read_exact(reader, &mut word)?;
+        let id = u32::from_le_bytes(word);
+        let mut size = [0u8; 8];
+        read_exact(reader, &mut size)?;
+        let size = u64::from_le_bytes(size);
+
+        // Read in chunks so a corrupted size fails at the end of the input instead of allocating it
+        let mut section = Vec::new();
+        let mut remaining = size;
+        let mut chunk = [0u8; 4096];
+        while remaining > 0 {
+            let len = remaining.min(chunk.len() as u64) as usize;
+            read_exact(reader, &mut chunk[..len])?;
+            section.extend_from_slice(&chunk[..len]);
+            remaining -= len as u64;
+        }
+        // Only the first section with an id is used, like snarkjs does
+        sections.entry(id).or_insert(section);
+    }
+    Ok(sections)
+}
+
+fn read_exact<R: Read>(reader: &mut R, buf: &mut [u8]) -> crate::Result<()> {
+    reader
+        .read_exact(buf)
+        .map_err(|_| Error::InvalidZkey("unexpected end of input"))
+}
+
+fn end_of_section(bytes: &[u8]) -> crate::Result<()> {
+    if bytes.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::InvalidZkey("section longer than expected"))
+    }
+}
+
+fn read_bytes<'a>(bytes: &mut &'a [u8], len: usize) -> crate::Result<&'a [u8]> {
+    if bytes.len() < len {
+        return Err(Error::InvalidZkey("section shorter than expected"));
+    }
+    let (read, rest) = bytes.split_at(len);
+    *bytes = rest;
+    Ok(read)
+}
+
+fn read_u32(bytes: &mut &[u8]) -> crate::Result<u32> {
+    let mut word = [0u8; 4];
+    word.copy_from_slice(read_bytes(bytes, 4)?);
+    Ok(u32::from_le_bytes(word))
+}
+
+fn read_bigint(bytes: &mut &[u8]) -> crate::Result<BigInt<4>> {
+    let read = read_bytes(bytes, N8)?;
+    let mut limbs = [0u64; 4];
+    for (limb, chunk) in limbs.iter_mut().zip(read.chunks_exact(8)) {
+        let mut word = [0u8; 8];
+        word.copy_from_slice(chunk);
+        *limb = u64::from_le_bytes(word);
+    }
+    Ok(BigInt(limbs))
+}
+
+/// Read the size and modulus of a field from the header, checking that they are those of `F`.
+fn read_modulus<F: PrimeField<BigInt = BigInt<4>>>(bytes: &mut &[u8]) -> crate::Result<()> {
+    if read_u32(bytes)? as usize != N8 || read_bigint(bytes)? != F::MODULUS {
+        return Err(Error::InvalidZkey("not a Bn254 key"));
+    }
+    Ok(())
+}
+
+/// Read a field element written, like all of them in a zkey, in little-endian Montgomery form.
+/// This is the internal representation of the field so it is used as is.
+fn read_montgomery<P: MontConfig<4>>(bytes: &mut &[u8]) -> crate::Result<Fp256<MontBackend<P, 4>>> {
+    let bigint = read_bigint(bytes)?;
+    if bigint >= P::MODULUS {
+        return Err(Error::InvalidZkey("field element out of range"));
+    }
+    Ok(Fp256::new_unchecked(bigint))
+}
+
+fn read_fq2(bytes: &mut &[u8]) -> crate::Result<Fq2> {
+    let c0: Fq = read_montgomery(bytes)?;
+    let c1: Fq = read_montgomery(bytes)?;
+    Ok(Fq2::new(c0, c1))
+}
+
+/// Read an uncompressed point, where the point at infinity has both coordinates zero, and check
+/// that it is in the prime order subgroup.
+fn read_g1(bytes: &mut &[u8]) -> crate::Result<G1Affine> {
+    let x: Fq = read_montgomery(bytes)?;
+    let y: Fq = read_montgomery(bytes)?;
+    if x.is_zero() && y.is_zero() {
+        return Ok(G1Affine::zero());
+    }
+    let point = G1Affine::new_unchecked(x, y);
+    if !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(Error::InvalidZkey("point not in the prime order subgroup"));
+    }
+    Ok(point)
+}
+
+fn read_g2(bytes: &mut &[u8]) -> crate::Result<G2Affine> {
+    let x = read_fq2(bytes)?;
+    let y = read_fq2(bytes)?;
+    if x.is_zero() && y.is_zero() {
+        return Ok(G2Affine::zero());
+    }
+    let point = G2Affine::new_unchecked(x, y);
+    if !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(Error::InvalidZkey("point not in the prime order subgroup"));
+    }
+    Ok(point)
+}
+
+fn read_g1_section(section: &[u8], count: usize) -> crate::Result<Vec<G1Affine>> {
+    let mut bytes = section;
+    let points = (0..count)
+        .map(|_| read_g1(&mut bytes))
+        .collect::<crate::Result<Vec<_>>>()?;
+    end_of_section(bytes)?;
+    Ok(points)
+}
+
+fn read_g2_section(section: &[u8], count: usize) -> crate::Result<Vec<G2Affine>> {
+    let mut bytes = section;
+    let points = (0..count)
+        .map(|_| read_g2(&mut bytes))
+        .collect::<crate::Result<Vec<_>>>()?;
+    end_of_section(bytes)?;
+    Ok(points)
+}